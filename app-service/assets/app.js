@@ -10,6 +10,9 @@ logoutLink.addEventListener("click", (e) => {
     fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
+        headers: {
+            'X-CSRF-Token': getCookie("csrf_token"), // Double-submit the CSRF token set by the auth service
+        },
     }).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
@@ -21,6 +24,14 @@ logoutLink.addEventListener("click", (e) => {
     });
 });
 
+function getCookie(name) {
+    const cookie = document.cookie
+        .split("; ")
+        .find(row => row.startsWith(`${name}=`));
+
+    return cookie ? decodeURIComponent(cookie.split("=")[1]) : "";
+}
+
 (() => {
     fetch('/protected').then(response => {
         if (response.ok) {
@@ -81,6 +81,16 @@ loginButton.addEventListener("click", (e) => {
     });
 });
 
+// Password policy errors list every rule the password broke
+function errorAlertHtml(data) {
+    let html = `<span><strong>Error: </strong>${data.error}</span>`;
+    if (Array.isArray(data.violations) && data.violations.length > 0) {
+        const items = data.violations.map(violation => `<li>${violation.message}</li>`).join("");
+        html += `<ul>${items}</ul>`;
+    }
+    return html;
+}
+
 const signupForm = document.getElementById("signup-form");
 const signupButton = document.getElementById("signup-form-submit");
 const signupErrAlter = document.getElementById("signup-err-alert");
@@ -112,7 +122,7 @@ signupButton.addEventListener("click", (e) => {
             response.json().then(data => {
                 let error_msg = data.error;
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
-                    signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    signupErrAlter.innerHTML = errorAlertHtml(data);
                     signupErrAlter.style.display = "block";
                 } else {
                     signupErrAlter.style.display = "none";
@@ -161,4 +171,130 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// -----------------------------------------------------
+
+// Opened from the "this wasn't me" link of a new sign-in email, or from the password reset
+// link sent when a password is found in a data breach
+const secureAccountSection = document.getElementById("secure-account-section");
+const secureAccountDescription = document.getElementById("secure-account-description");
+const secureAccountForm = document.getElementById("secure-account-form");
+const secureAccountButton = document.getElementById("secure-account-form-submit");
+const secureAccountErrAlter = document.getElementById("secure-account-err-alert");
+
+const reportToken = new URLSearchParams(window.location.search).get("report");
+const resetToken = new URLSearchParams(window.location.search).get("reset");
+
+if (resetToken) {
+    secureAccountDescription.textContent = "Your password was found in a data breach. Choose a new password to log in again.";
+}
+
+if (reportToken || resetToken) {
+    loginSection.style.display = "none";
+    twoFASection.style.display = "none";
+    signupSection.style.display = "none";
+    secureAccountSection.style.display = "block";
+}
+
+function showSecureAccountError(response) {
+    response.json().then(data => {
+        let error_msg = data.error;
+        if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+            secureAccountErrAlter.innerHTML = errorAlertHtml(data);
+            secureAccountErrAlter.style.display = "block";
+        } else {
+            secureAccountErrAlter.style.display = "none";
+        }
+    });
+}
+
+function resetPassword(token, newPassword) {
+    fetch('/reset-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token, newPassword }),
+    }).then(response => {
+        if (response.ok) {
+            secureAccountForm.password.value = "";
+            secureAccountErrAlter.style.display = "none";
+            alert("Your password has been reset. You can now log in.");
+            window.history.replaceState(null, "", "/");
+            loginSection.style.display = "block";
+            secureAccountSection.style.display = "none";
+        } else {
+            showSecureAccountError(response);
+        }
+    });
+}
+
+secureAccountButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const newPassword = secureAccountForm.password.value;
+
+    if (resetToken) {
+        resetPassword(resetToken, newPassword);
+        return;
+    }
+
+    fetch('/report-login', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: reportToken }),
+    }).then(response => {
+        if (!response.ok) {
+            showSecureAccountError(response);
+            return;
+        }
+
+        response.json().then(data => {
+            resetPassword(data.passwordResetToken, newPassword);
+        });
+    });
+});
+
+// -----------------------------------------------------
+
+// Opened from the confirmation link sent to the new address of an email change, or from the
+// cancel link sent to the current one
+const confirmEmailToken = new URLSearchParams(window.location.search).get("confirm-email");
+const cancelEmailChangeToken = new URLSearchParams(window.location.search).get("cancel-email-change");
+
+function submitEmailChangeToken(path, token, successMessage) {
+    fetch(path, {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token }),
+    }).then(response => {
+        window.history.replaceState(null, "", "/");
+        if (response.ok) {
+            alert(successMessage);
+            return;
+        }
+
+        response.json().then(data => {
+            alert(data.error);
+        });
+    });
+}
+
+if (confirmEmailToken) {
+    submitEmailChangeToken(
+        '/email/confirm',
+        confirmEmailToken,
+        "Your email address has been changed. You can now log in with it."
+    );
+} else if (cancelEmailChangeToken) {
+    submitEmailChangeToken(
+        '/email/cancel',
+        cancelEmailChangeToken,
+        "The email change has been cancelled and all sessions have been signed out."
+    );
+}
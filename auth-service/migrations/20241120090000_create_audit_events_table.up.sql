@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_events(
+   id UUID NOT NULL PRIMARY KEY,
+   kind TEXT NOT NULL,
+   email TEXT,
+   ip TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_events_email_idx ON audit_events (email);
+CREATE INDEX IF NOT EXISTS audit_events_kind_idx ON audit_events (kind);
@@ -0,0 +1,33 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS outbox_events(
+   id UUID NOT NULL PRIMARY KEY,
+   kind TEXT NOT NULL,
+   payload JSONB NOT NULL,
+   occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   dispatched_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_undispatched_idx
+   ON outbox_events (occurred_at) WHERE dispatched_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS webhook_endpoints(
+   id UUID NOT NULL PRIMARY KEY,
+   url TEXT NOT NULL,
+   secret TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries(
+   id UUID NOT NULL PRIMARY KEY,
+   event_id UUID NOT NULL REFERENCES outbox_events (id) ON DELETE CASCADE,
+   endpoint_id UUID NOT NULL REFERENCES webhook_endpoints (id) ON DELETE CASCADE,
+   status TEXT NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_error TEXT,
+   delivered_at TIMESTAMPTZ,
+   UNIQUE (event_id, endpoint_id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due_idx
+   ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS known_devices(
+   email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+   user_agent TEXT NOT NULL,
+   ip TEXT NOT NULL,
+   first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (email, user_agent, ip)
+);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS phone_number TEXT,
+   ADD COLUMN IF NOT EXISTS phone_number_verified BOOLEAN NOT NULL DEFAULT FALSE,
+   ADD COLUMN IF NOT EXISTS two_fa_channel TEXT NOT NULL DEFAULT 'email',
+   ADD COLUMN IF NOT EXISTS phone_verification_code TEXT,
+   ADD COLUMN IF NOT EXISTS phone_verification_expires_at TIMESTAMPTZ;
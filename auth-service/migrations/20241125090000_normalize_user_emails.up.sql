@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- Trims emails and lowercases their domain, the way the service normalizes them whatever
+-- EMAIL_LOWERCASE_LOCAL_PART says. The service lowercases the local part at startup when that
+-- setting is on.
+UPDATE users
+SET email = REGEXP_REPLACE(BTRIM(email), '@[^@]*$', LOWER(SUBSTRING(BTRIM(email) FROM '@[^@]*$')))
+WHERE email <> BTRIM(email) OR SUBSTRING(email FROM '@[^@]*$') <> LOWER(SUBSTRING(email FROM '@[^@]*$'));
+
+-- Accounts can't differ only in case, even when the local part case is kept. Fails if two
+-- existing ones do, one of them has to be removed or renamed by hand first.
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_idx ON users (LOWER(email));
@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE known_devices DROP CONSTRAINT IF EXISTS known_devices_email_fkey;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_pkey;
+ALTER TABLE users ADD CONSTRAINT users_pkey PRIMARY KEY (email);
+ALTER TABLE known_devices ADD CONSTRAINT known_devices_email_fkey
+   FOREIGN KEY (email) REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE users DROP COLUMN IF EXISTS id;
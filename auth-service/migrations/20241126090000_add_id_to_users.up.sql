@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- Existing users get a random id each. Tokens now carry it as their `sub` instead of the email,
+-- so every token issued before this deploy is rejected and its user has to log in again.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS id UUID NOT NULL DEFAULT gen_random_uuid();
+
+-- The email stays unique, and known devices keep referring to it
+ALTER TABLE known_devices DROP CONSTRAINT IF EXISTS known_devices_email_fkey;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_pkey;
+ALTER TABLE users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+ALTER TABLE known_devices ADD CONSTRAINT known_devices_email_fkey
+   FOREIGN KEY (email) REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE;
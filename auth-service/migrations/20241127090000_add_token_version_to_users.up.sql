@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Tokens carry the version they were issued with. Bumping it revokes them all.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
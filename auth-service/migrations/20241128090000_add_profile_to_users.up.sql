@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- Existing users get the time of the migration as their creation time
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS display_name TEXT,
+   ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   ADD COLUMN IF NOT EXISTS last_login_at TIMESTAMPTZ,
+   ADD COLUMN IF NOT EXISTS password_changed_at TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+-- Hashed codes can't be turned back into plaintext ones, so pending verifications are dropped
+UPDATE users
+SET phone_verification_code = NULL, phone_verification_expires_at = NULL
+WHERE phone_verification_code IS NOT NULL;
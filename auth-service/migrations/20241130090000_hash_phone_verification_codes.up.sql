@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Verification codes are now stored hashed. Pending plaintext codes can't be hashed here, as the
+-- key is not in the database, so they are dropped and users have to request a new one.
+UPDATE users
+SET phone_verification_code = NULL, phone_verification_expires_at = NULL
+WHERE phone_verification_code IS NOT NULL;
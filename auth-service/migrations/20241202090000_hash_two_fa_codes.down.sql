@@ -0,0 +1,3 @@
+-- Add down migration script here
+-- Hashed codes can't be turned back into plaintext ones, so pending login attempts are dropped
+DELETE FROM two_fa_codes;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- 2FA codes are now stored hashed. Pending plaintext codes can't be hashed here, as the key is not
+-- in the database, so they are dropped and users have to log in again.
+DELETE FROM two_fa_codes;
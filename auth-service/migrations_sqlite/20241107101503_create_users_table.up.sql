@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- The same columns as the PostgreSQL users table after all of its migrations.
+-- Ids and timestamps are stored as text, booleans as integers.
+CREATE TABLE IF NOT EXISTS users(
+   id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL UNIQUE,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   password_reset_required BOOLEAN NOT NULL DEFAULT FALSE,
+   phone_number TEXT,
+   phone_number_verified BOOLEAN NOT NULL DEFAULT FALSE,
+   two_fa_channel TEXT NOT NULL DEFAULT 'email',
+   phone_verification_code TEXT,
+   phone_verification_expires_at TEXT,
+   token_version INTEGER NOT NULL DEFAULT 0,
+   display_name TEXT,
+   created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   last_login_at TEXT,
+   password_changed_at TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_idx ON users (LOWER(email));
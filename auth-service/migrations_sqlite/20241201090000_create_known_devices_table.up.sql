@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- Known devices live next to the users they belong to. Timestamps are stored as text.
+CREATE TABLE IF NOT EXISTS known_devices(
+   email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+   user_agent TEXT NOT NULL,
+   ip TEXT NOT NULL,
+   first_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   PRIMARY KEY (email, user_agent, ip)
+);
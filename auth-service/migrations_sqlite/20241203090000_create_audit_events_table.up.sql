@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- Ids and timestamps are stored as text, like in the users table
+CREATE TABLE IF NOT EXISTS audit_events(
+   id TEXT NOT NULL PRIMARY KEY,
+   kind TEXT NOT NULL,
+   email TEXT,
+   ip TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_events_email_idx ON audit_events (email);
+CREATE INDEX IF NOT EXISTS audit_events_kind_idx ON audit_events (kind);
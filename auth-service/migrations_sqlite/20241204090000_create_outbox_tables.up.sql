@@ -0,0 +1,35 @@
+-- Add up migration script here
+-- Payloads are stored as JSON text. Timestamps compared by the queries are always written by
+-- the service, so they all share the same RFC 3339 format and sort like the times themselves.
+CREATE TABLE IF NOT EXISTS outbox_events(
+   id TEXT NOT NULL PRIMARY KEY,
+   kind TEXT NOT NULL,
+   payload TEXT NOT NULL,
+   occurred_at TEXT NOT NULL,
+   dispatched_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_undispatched_idx
+   ON outbox_events (occurred_at) WHERE dispatched_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS webhook_endpoints(
+   id TEXT NOT NULL PRIMARY KEY,
+   url TEXT NOT NULL,
+   secret TEXT NOT NULL,
+   created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries(
+   id TEXT NOT NULL PRIMARY KEY,
+   event_id TEXT NOT NULL REFERENCES outbox_events (id) ON DELETE CASCADE,
+   endpoint_id TEXT NOT NULL REFERENCES webhook_endpoints (id) ON DELETE CASCADE,
+   status TEXT NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TEXT NOT NULL,
+   last_error TEXT,
+   delivered_at TEXT,
+   UNIQUE (event_id, endpoint_id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due_idx
+   ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS email_queue(
+   id TEXT NOT NULL PRIMARY KEY,
+   recipient TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   html_body TEXT NOT NULL,
+   text_body TEXT NOT NULL,
+   status TEXT NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TEXT NOT NULL,
+   last_error TEXT,
+   created_at TEXT NOT NULL,
+   sent_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS email_queue_due_idx
+   ON email_queue (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,17 @@
+-- Add up migration script here
+-- 2FA codes are stored hashed, as in PostgreSQL
+CREATE TABLE IF NOT EXISTS two_fa_codes(
+   email TEXT PRIMARY KEY,
+   login_attempt_id TEXT NOT NULL,
+   code TEXT NOT NULL,
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx ON two_fa_codes (expires_at);
+
+CREATE TABLE IF NOT EXISTS banned_tokens(
+   token TEXT PRIMARY KEY,
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS banned_tokens_expires_at_idx ON banned_tokens (expires_at);
@@ -1,33 +1,119 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
-use crate::domain::{data_stores::TwoFACodeStore, BannedTokenStore, EmailClient, UserStore};
+use crate::{
+    domain::{
+        data_stores::TwoFACodeStore, AuditLog, BannedTokenStore, BreachedPasswordChecker,
+        EmailClient, EmailQueue, KnownDeviceStore, OutboxStore, PasswordPolicy, SmsClient,
+        UserStore,
+    },
+    services::{capture_email_client::DevMailbox, hashing_executor::HashingExecutor},
+    utils::{
+        constants::{PASSWORD_RESET_RESEND_INTERVAL_SECONDS, SMS_RESEND_INTERVAL_SECONDS},
+        rate_limiter::RateLimiter,
+    },
+};
 
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
-pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
-pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
+pub type AuditLogType = Arc<dyn AuditLog + Send + Sync>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type BreachedPasswordCheckerType = Arc<RwLock<dyn BreachedPasswordChecker + Send + Sync>>;
+pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type EmailQueueType = Arc<dyn EmailQueue + Send + Sync>;
+pub type KnownDeviceStoreType = Arc<dyn KnownDeviceStore + Send + Sync>;
+pub type OutboxStoreType = Arc<dyn OutboxStore + Send + Sync>;
+pub type SmsClientType = Arc<RwLock<dyn SmsClient + Send + Sync>>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore + Send + Sync>;
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
+    // The user, banned token and 2FA code stores and the email client are used by concurrent
+    // requests at the same time, without a lock
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType,
+    // Emails are only enqueued while handling requests, the `EmailWorker` sends them
+    pub email_queue: EmailQueueType,
+    pub audit_log: AuditLogType,
+    pub outbox_store: OutboxStoreType,
+    pub known_device_store: KnownDeviceStoreType,
+    // Text messages are sent straight away, as 2FA codes are useless once they arrive late
+    pub sms_client: SmsClientType,
+    // Keeps a user from sending a stream of text messages, to their own number or anyone else's
+    pub sms_rate_limiter: Arc<RateLimiter>,
+    pub breached_password_checker: BreachedPasswordCheckerType,
+    // Limits how often refused logins email a fresh password reset link
+    pub password_reset_rate_limiter: Arc<RateLimiter>,
+    pub password_policy: Arc<PasswordPolicy>,
+    // When set, users logging in with a breached password must reset it first
+    pub check_breached_passwords_at_login: bool,
+    // Whether the local part of emails given by users is lowercased, like the domain always is
+    pub lowercase_email_local_part: bool,
+    // Only set when emails are captured instead of sent, it enables the `/dev/mailbox` route
+    pub dev_mailbox: Option<DevMailbox>,
+    // The executor the user store hashes passwords on, for its metrics
+    pub hashing_executor: Option<HashingExecutor>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType,
+        email_queue: EmailQueueType,
+        audit_log: AuditLogType,
+        outbox_store: OutboxStoreType,
+        known_device_store: KnownDeviceStoreType,
+        sms_client: SmsClientType,
+        breached_password_checker: BreachedPasswordCheckerType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
-            email_client,
+            email_queue,
+            audit_log,
+            outbox_store,
+            known_device_store,
+            sms_client,
+            sms_rate_limiter: Arc::new(RateLimiter::new(Duration::from_secs(
+                SMS_RESEND_INTERVAL_SECONDS,
+            ))),
+            breached_password_checker,
+            password_reset_rate_limiter: Arc::new(RateLimiter::new(Duration::from_secs(
+                PASSWORD_RESET_RESEND_INTERVAL_SECONDS,
+            ))),
+            password_policy: Arc::new(PasswordPolicy::default()),
+            check_breached_passwords_at_login: false,
+            lowercase_email_local_part: true,
+            dev_mailbox: None,
+            hashing_executor: None,
         }
     }
+
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = Arc::new(password_policy);
+        self
+    }
+
+    pub fn with_breached_password_check_at_login(mut self) -> Self {
+        self.check_breached_passwords_at_login = true;
+        self
+    }
+
+    pub fn with_case_sensitive_email_local_part(mut self) -> Self {
+        self.lowercase_email_local_part = false;
+        self
+    }
+
+    pub fn with_dev_mailbox(mut self, dev_mailbox: DevMailbox) -> Self {
+        self.dev_mailbox = Some(dev_mailbox);
+        self
+    }
+
+    pub fn with_hashing_executor(mut self, hashing_executor: HashingExecutor) -> Self {
+        self.hashing_executor = Some(hashing_executor);
+        self
+    }
 }
@@ -0,0 +1,206 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+// This trait represents the interface all concrete audit logs should implement
+#[async_trait::async_trait]
+pub trait AuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError>;
+    async fn query(&self, query: &AuditLogQuery) -> Result<Vec<AuditEvent>, AuditLogError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    Signup,
+    LoginSucceeded,
+    LoginFailed,
+    #[serde(rename = "two_fa_sent")]
+    TwoFASent,
+    #[serde(rename = "two_fa_verified")]
+    TwoFAVerified,
+    #[serde(rename = "two_fa_failed")]
+    TwoFAFailed,
+    Logout,
+    TokenRejected,
+    LoginReported,
+    PasswordReset,
+    BreachedPasswordDetected,
+    EmailChangeRequested,
+    EmailChanged,
+    EmailChangeCancelled,
+    AccountDeleted,
+}
+
+impl AuditEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::TwoFASent => "two_fa_sent",
+            Self::TwoFAVerified => "two_fa_verified",
+            Self::TwoFAFailed => "two_fa_failed",
+            Self::Logout => "logout",
+            Self::TokenRejected => "token_rejected",
+            Self::LoginReported => "login_reported",
+            Self::PasswordReset => "password_reset",
+            Self::BreachedPasswordDetected => "breached_password_detected",
+            Self::EmailChangeRequested => "email_change_requested",
+            Self::EmailChanged => "email_changed",
+            Self::EmailChangeCancelled => "email_change_cancelled",
+            Self::AccountDeleted => "account_deleted",
+        }
+    }
+}
+
+impl fmt::Display for AuditEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuditEventKind {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "signup" => Ok(Self::Signup),
+            "login_succeeded" => Ok(Self::LoginSucceeded),
+            "login_failed" => Ok(Self::LoginFailed),
+            "two_fa_sent" => Ok(Self::TwoFASent),
+            "two_fa_verified" => Ok(Self::TwoFAVerified),
+            "two_fa_failed" => Ok(Self::TwoFAFailed),
+            "logout" => Ok(Self::Logout),
+            "token_rejected" => Ok(Self::TokenRejected),
+            "login_reported" => Ok(Self::LoginReported),
+            "password_reset" => Ok(Self::PasswordReset),
+            "breached_password_detected" => Ok(Self::BreachedPasswordDetected),
+            "email_change_requested" => Ok(Self::EmailChangeRequested),
+            "email_changed" => Ok(Self::EmailChanged),
+            "email_change_cancelled" => Ok(Self::EmailChangeCancelled),
+            "account_deleted" => Ok(Self::AccountDeleted),
+            _ => Err(eyre!("{} is not a valid audit event kind", s)),
+        }
+    }
+}
+
+// Details about the HTTP request that triggered an audit event
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestMetadata {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub kind: AuditEventKind,
+    // The normalized email the event refers to, recorded even when no such user exists.
+    pub email: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, email: Option<String>, metadata: &RequestMetadata) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            email,
+            ip: metadata.ip.clone(),
+            user_agent: metadata.user_agent.clone(),
+            request_id: metadata.request_id.clone(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+// Filters and pagination for listing audit events, newest first
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogQuery {
+    pub kind: Option<AuditEventKind>,
+    pub email: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl AuditLogQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        if let Some(kind) = self.kind {
+            if event.kind != kind {
+                return false;
+            }
+        }
+        if let Some(email) = &self.email {
+            if event.email.as_ref() != Some(email) {
+                return false;
+            }
+        }
+        if let Some(from) = self.from {
+            if event.occurred_at < from {
+                return false;
+            }
+        }
+        if let Some(to) = self.to {
+            if event.occurred_at >= to {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_round_trips_through_str() {
+        let kinds = [
+            AuditEventKind::Signup,
+            AuditEventKind::LoginSucceeded,
+            AuditEventKind::LoginFailed,
+            AuditEventKind::TwoFASent,
+            AuditEventKind::TwoFAVerified,
+            AuditEventKind::TwoFAFailed,
+            AuditEventKind::Logout,
+            AuditEventKind::TokenRejected,
+            AuditEventKind::LoginReported,
+            AuditEventKind::PasswordReset,
+            AuditEventKind::BreachedPasswordDetected,
+            AuditEventKind::EmailChangeRequested,
+            AuditEventKind::EmailChanged,
+            AuditEventKind::EmailChangeCancelled,
+            AuditEventKind::AccountDeleted,
+        ];
+
+        for kind in kinds {
+            assert_eq!(kind.as_str().parse::<AuditEventKind>().unwrap(), kind);
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_kind_is_rejected() {
+        assert!("unknown".parse::<AuditEventKind>().is_err());
+    }
+}
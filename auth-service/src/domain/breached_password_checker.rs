@@ -0,0 +1,85 @@
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+use sha1::{Digest, Sha1};
+
+use super::Password;
+
+// Length of the hash prefix sent to range lookups, as defined by the Pwned Passwords API
+pub const HASH_PREFIX_LENGTH: usize = 5;
+
+// This trait represents the interface all concrete breached password checkers should implement.
+// Passwords are looked up by k-anonymity: only the first characters of their SHA-1 hash are
+// used to fetch the range of breached hashes sharing that prefix.
+#[async_trait::async_trait]
+pub trait BreachedPasswordChecker {
+    // Returns the range file of the prefix, as `SUFFIX:COUNT` lines
+    async fn get_range(&self, prefix: &str) -> Result<String>;
+
+    // Returns how many times the password was seen in breaches
+    async fn breach_count(&self, password: &Password) -> Result<u64> {
+        let (prefix, suffix) = split_sha1_hash(password);
+        let range = self.get_range(&prefix).await?;
+        Ok(find_breach_count(&range, &suffix))
+    }
+}
+
+// Splits the uppercase hex SHA-1 hash of the password into the prefix and the suffix used by
+// range lookups
+pub fn split_sha1_hash(password: &Password) -> (String, String) {
+    let hash = hex::encode_upper(Sha1::digest(password.as_ref().expose_secret().as_bytes()));
+    let (prefix, suffix) = hash.split_at(HASH_PREFIX_LENGTH);
+    (prefix.to_owned(), suffix.to_owned())
+}
+
+// Padding entries have a count of 0, so they never match as breached
+fn find_breach_count(range: &str, suffix: &str) -> u64 {
+    range
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(line_suffix, _)| line_suffix.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn password(s: &str) -> Password {
+        Password::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn hash_is_split_after_the_prefix() {
+        let (prefix, suffix) = split_sha1_hash(&password("password"));
+
+        assert_eq!(prefix, "5BAA6");
+        assert_eq!(suffix, "1E4C9B93F3F0682250B6CF8331B7EE68FD8");
+    }
+
+    #[test]
+    fn breach_count_is_read_from_the_matching_line() {
+        let range = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+                     1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n\
+                     1E4C9B93F3F0682250B6CF8331B7EE68FD9:0\r\n";
+
+        assert_eq!(
+            find_breach_count(range, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"),
+            10434004
+        );
+        assert_eq!(
+            find_breach_count(range, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"),
+            10434004
+        );
+        assert_eq!(
+            find_breach_count(range, "1E4C9B93F3F0682250B6CF8331B7EE68FD9"),
+            0
+        );
+        assert_eq!(
+            find_breach_count(range, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"),
+            0
+        );
+    }
+}
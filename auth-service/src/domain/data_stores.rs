@@ -1,21 +1,74 @@
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
 use thiserror::Error;
 
-use super::{Email, Password, User};
+use super::{
+    DisplayName, Email, ImportedUser, Password, PhoneNumber, RequestMetadata, TwoFAChannel, User,
+    UserId,
+};
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
+    // Stores the password hash as is, it must be in a format `validate_user` can verify
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError>;
+    // Also revokes the tokens of the user, which were issued for the old email
+    async fn change_email(&self, id: &UserId, new_email: Email) -> Result<(), UserStoreError>;
+    // Revokes every token issued to the user so far
+    async fn revoke_tokens(&self, id: &UserId) -> Result<(), UserStoreError>;
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError>;
+    // Replaces the phone number of the user with an unverified one, to be confirmed with
+    // `verification_code` before `expires_at`. 2FA codes go back to being sent by email.
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_code: TwoFACode,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError>;
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        verification_code: &TwoFACode,
+    ) -> Result<(), UserStoreError>;
+    // SMS can only be chosen once the phone number is verified
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError>;
+    async fn set_display_name(
+        &self,
+        id: &UserId,
+        display_name: Option<DisplayName>,
+    ) -> Result<(), UserStoreError>;
+    // Doesn't count as a change of the user, `updated_at` is left alone
+    async fn set_last_login_at(
+        &self,
+        id: &UserId,
+        last_login_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
 }
 
@@ -27,6 +80,13 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Invalid verification code")]
+    InvalidVerificationCode,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    // Passwords can't be hashed right now, too many are in progress
+    #[error("Password hashing overloaded")]
+    HashingOverloaded,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -38,6 +98,9 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::InvalidVerificationCode, Self::InvalidVerificationCode)
+                | (Self::PhoneNumberNotVerified, Self::PhoneNumberNotVerified)
+                | (Self::HashingOverloaded, Self::HashingOverloaded)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -49,26 +112,85 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
+// This trait represents the interface all concrete known device stores should implement
+#[async_trait::async_trait]
+pub trait KnownDeviceStore {
+    // Remembers the device for the user and reports whether it was seen before
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum KnownDeviceStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// A device is identified by the user agent and IP address it logs in from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Device {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Device {
+    pub fn user_agent_or_unknown(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or("unknown")
+    }
+
+    pub fn ip_or_unknown(&self) -> &str {
+        self.ip.as_deref().unwrap_or("unknown")
+    }
+}
+
+impl From<&RequestMetadata> for Device {
+    fn from(metadata: &RequestMetadata) -> Self {
+        Self {
+            user_agent: metadata.user_agent.clone(),
+            ip: metadata.ip.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStatus {
+    // The device was seen before
+    Known,
+    // The user had no known devices yet
+    First,
+    // The user has other known devices, but not this one
+    New,
+}
+
 // This trait represents the interface all concrete 2FA code stores should implement
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
-    async fn get_code(
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Checks the code against the pending login attempt and removes it when it matches. Only one
+    // of concurrent callers with the right code succeeds; a wrong code leaves it in place.
+    async fn consume_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Incorrect 2FA code")]
+    IncorrectCode,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -78,6 +200,7 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::IncorrectCode, Self::IncorrectCode)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -135,7 +258,8 @@ impl TwoFACode {
 impl Default for TwoFACode {
     fn default() -> Self {
         let mut rng = rand::thread_rng();
-        let code: u32 = rng.gen_range(0..1000000);
+        // Stays within the range accepted by `parse`
+        let code: u32 = rng.gen_range(100_000..1_000_000);
         TwoFACode(Secret::new(format!("{:06}", code)))
     }
 }
@@ -0,0 +1,64 @@
+use color_eyre::eyre::{eyre, Result};
+
+// The name users choose to be shown as, e.g. in greetings. Surrounding spaces are trimmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayName(String);
+
+impl DisplayName {
+    pub const MAX_LENGTH: usize = 100;
+
+    pub fn parse(s: &str) -> Result<DisplayName> {
+        let s = s.trim();
+        let length = s.chars().count();
+
+        if length == 0 || length > Self::MAX_LENGTH {
+            return Err(eyre!(
+                "Display names must be between 1 and {} characters long.",
+                Self::MAX_LENGTH
+            ));
+        }
+        if s.chars().any(char::is_control) {
+            return Err(eyre!("Display names can't contain control characters."));
+        }
+
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl AsRef<str> for DisplayName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisplayName;
+
+    #[test]
+    fn names_are_trimmed() {
+        let name = DisplayName::parse("  Ada Lovelace ").unwrap();
+        assert_eq!(name.as_ref(), "Ada Lovelace");
+        assert_eq!(DisplayName::parse("Zoë").unwrap().as_ref(), "Zoë");
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let too_long = "a".repeat(DisplayName::MAX_LENGTH + 1);
+        let test_cases = ["", "   ", "Ada\nLovelace", "\u{0}", too_long.as_str()];
+
+        for test_case in test_cases {
+            assert!(
+                DisplayName::parse(test_case).is_err(),
+                "Accepted {:?}",
+                test_case
+            );
+        }
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let name = "é".repeat(DisplayName::MAX_LENGTH);
+        assert!(DisplayName::parse(&name).is_ok());
+    }
+}
@@ -22,15 +22,39 @@ impl Hash for Email {
 impl Eq for Email {}
 
 impl Email {
+    // Emails are stored and compared in their normalized form: trimmed, with a lowercase domain
+    // and a lowercase local part
     pub fn parse(s: Secret<String>) -> Result<Email> {
-        if validate_email(s.expose_secret()) {
-            Ok(Self(s))
-        } else {
-            Err(eyre!(format!(
+        Self::parse_with(s, true)
+    }
+
+    // Like `parse`, but the local part only gets lowercased when asked to. Routes pass on the
+    // setting of the deployment. Stored emails are normalized already, so they are read back
+    // keeping the case they were stored with.
+    pub fn parse_with(s: Secret<String>, lowercase_local_part: bool) -> Result<Email> {
+        let email = s.expose_secret().trim();
+        if !validate_email(email) {
+            return Err(eyre!(format!(
                 "{} is not a valid email.",
                 s.expose_secret()
-            )))
+            )));
         }
+
+        // The local part may itself contain a quoted @
+        let (local_part, domain) = email
+            .rsplit_once('@')
+            .ok_or_else(|| eyre!(format!("{} is not a valid email.", email)))?;
+        let local_part = if lowercase_local_part {
+            local_part.to_lowercase()
+        } else {
+            local_part.to_owned()
+        };
+
+        Ok(Self(Secret::new(format!(
+            "{}@{}",
+            local_part,
+            domain.to_lowercase()
+        ))))
     }
 }
 
@@ -46,7 +70,7 @@ mod tests {
 
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
-    use secrecy::Secret;
+    use secrecy::{ExposeSecret, Secret};
 
     #[test]
     fn empty_string_is_rejected() {
@@ -64,6 +88,25 @@ mod tests {
         assert!(Email::parse(email).is_err());
     }
 
+    #[test]
+    fn email_is_normalized() {
+        let email = Email::parse(Secret::new("  Ursula.Le@Domain.COM ".to_string())).unwrap();
+        assert_eq!(email.as_ref().expose_secret(), "ursula.le@domain.com");
+    }
+
+    #[test]
+    fn emails_differing_in_case_are_equal() {
+        let lowercase = Email::parse(Secret::new("ursula@domain.com".to_string())).unwrap();
+        let mixed_case = Email::parse(Secret::new("Ursula@Domain.com".to_string())).unwrap();
+        assert_eq!(lowercase, mixed_case);
+    }
+
+    #[test]
+    fn local_part_case_can_be_kept() {
+        let email = Email::parse_with(Secret::new("Ursula@Domain.COM".to_string()), false).unwrap();
+        assert_eq!(email.as_ref().expose_secret(), "Ursula@domain.com");
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{Email, EmailMessage};
+
+// This trait represents the interface all concrete email queues should implement.
+// Routes enqueue messages and return straight away; the `EmailWorker` sends them in the background.
+#[async_trait::async_trait]
+pub trait EmailQueue {
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<Uuid, EmailQueueError>;
+    // Claims up to `limit` due emails, hiding them from other workers for `lease`
+    async fn claim_due_emails(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<QueuedEmail>, EmailQueueError>;
+    async fn mark_sent(&self, email_id: Uuid) -> Result<(), EmailQueueError>;
+    // Records a failed attempt. Without a next attempt time the email is dead-lettered.
+    async fn mark_failed(
+        &self,
+        email_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailQueueError>;
+    async fn get_status(&self, email_id: Uuid) -> Result<QueuedEmailStatus, EmailQueueError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailQueueError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailQueueError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EmailNotFound, Self::EmailNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: Uuid,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    // Number of attempts made before this one
+    pub attempts: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuedEmailStatus {
+    Pending,
+    Sent,
+    Dead,
+}
+
+impl QueuedEmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Dead => "dead",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead" => Ok(Self::Dead),
+            _ => Err(eyre!("{} is not a valid email status", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_round_trips_through_str() {
+        let statuses = [
+            QueuedEmailStatus::Pending,
+            QueuedEmailStatus::Sent,
+            QueuedEmailStatus::Dead,
+        ];
+
+        for status in statuses {
+            assert_eq!(QueuedEmailStatus::parse(status.as_str()).unwrap(), status);
+        }
+    }
+}
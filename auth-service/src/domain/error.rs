@@ -1,6 +1,8 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::PasswordPolicyViolation;
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
@@ -13,6 +15,36 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Malformed token request")]
+    MalformedToken,
+    #[error("Invalid client credentials")]
+    InvalidClientCredentials,
+    #[error("Invalid admin API key")]
+    InvalidAdminApiKey,
+    #[error("CSRF check failed")]
+    CsrfCheckFailed,
+    #[error("Invalid webhook URL")]
+    InvalidWebhookUrl,
+    #[error("Import batch too large")]
+    ImportBatchTooLarge,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Breached password")]
+    BreachedPassword,
+    #[error("Password policy violated")]
+    PasswordPolicyViolated(Vec<PasswordPolicyViolation>),
+    #[error("Invalid phone number")]
+    InvalidPhoneNumber,
+    #[error("Invalid verification code")]
+    InvalidVerificationCode,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("Invalid display name")]
+    InvalidDisplayName,
+    #[error("Service overloaded")]
+    ServiceOverloaded,
+    #[error("Too many requests")]
+    TooManyRequests,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -1,13 +1,27 @@
+pub mod audit_log;
+pub mod breached_password_checker;
 pub mod data_stores;
+mod display_name;
 pub mod email;
 pub mod email_client;
+pub mod email_queue;
 mod error;
+pub mod outbox;
 mod password;
+mod phone_number;
+pub mod sms_client;
 mod user;
 
+pub use audit_log::*;
+pub use breached_password_checker::*;
 pub use data_stores::*;
+pub use display_name::DisplayName;
 pub use email::Email;
 pub use email_client::*;
+pub use email_queue::*;
 pub use error::AuthAPIError;
-pub use password::Password;
-pub use user::User;
+pub use outbox::*;
+pub use password::{Password, PasswordPolicy, PasswordPolicyViolation, PasswordRule};
+pub use phone_number::PhoneNumber;
+pub use sms_client::*;
+pub use user::{ImportedUser, TwoFAChannel, User, UserId};
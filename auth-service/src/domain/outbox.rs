@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+// This trait represents the interface all concrete outbox stores should implement.
+// Domain events are written to the outbox by the `UserStore`, in the same transaction as the
+// change they describe; the outbox store only takes care of delivering them to webhooks.
+#[async_trait::async_trait]
+pub trait OutboxStore {
+    async fn add_endpoint(
+        &self,
+        url: String,
+        secret: Secret<String>,
+    ) -> Result<WebhookEndpoint, OutboxStoreError>;
+    async fn list_endpoints(&self) -> Result<Vec<WebhookEndpoint>, OutboxStoreError>;
+    // Schedules a delivery to every registered endpoint for each event that was not fanned out yet.
+    // Returns the number of deliveries created.
+    async fn fan_out_events(&self) -> Result<u64, OutboxStoreError>;
+    // Claims up to `limit` due deliveries, hiding them from other dispatchers for `lease`
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<WebhookDelivery>, OutboxStoreError>;
+    async fn mark_delivered(&self, delivery_id: Uuid) -> Result<(), OutboxStoreError>;
+    // Records a failed attempt. Without a next attempt time the delivery is dead-lettered.
+    async fn mark_failed(
+        &self,
+        delivery_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), OutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OutboxStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DomainEventKind {
+    #[serde(rename = "user.signed_up")]
+    UserSignedUp,
+    #[serde(rename = "user.deleted")]
+    UserDeleted,
+    #[serde(rename = "user.password_changed")]
+    PasswordChanged,
+    #[serde(rename = "user.email_changed")]
+    EmailChanged,
+}
+
+impl DomainEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserSignedUp => "user.signed_up",
+            Self::UserDeleted => "user.deleted",
+            Self::PasswordChanged => "user.password_changed",
+            Self::EmailChanged => "user.email_changed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "user.signed_up" => Ok(Self::UserSignedUp),
+            "user.deleted" => Ok(Self::UserDeleted),
+            "user.password_changed" => Ok(Self::PasswordChanged),
+            "user.email_changed" => Ok(Self::EmailChanged),
+            _ => Err(eyre!("{} is not a valid domain event kind", s)),
+        }
+    }
+}
+
+// The body POSTed to webhook endpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub kind: DomainEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+impl DomainEvent {
+    pub fn new(kind: DomainEventKind, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            occurred_at: Utc::now(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_url: String,
+    pub endpoint_secret: Secret<String>,
+    // Number of attempts made before this one
+    pub attempts: i32,
+    pub event: DomainEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Dead,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_round_trips_through_str() {
+        let kinds = [
+            DomainEventKind::UserSignedUp,
+            DomainEventKind::UserDeleted,
+            DomainEventKind::PasswordChanged,
+            DomainEventKind::EmailChanged,
+        ];
+
+        for kind in kinds {
+            assert_eq!(DomainEventKind::parse(kind.as_str()).unwrap(), kind);
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.as_str())
+            );
+        }
+    }
+}
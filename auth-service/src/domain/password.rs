@@ -1,5 +1,11 @@
+use std::collections::HashSet;
+
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use zxcvbn::zxcvbn;
+
+use super::Email;
 
 #[derive(Clone, Debug)]
 pub struct Password(Secret<String>);
@@ -20,8 +26,9 @@ impl Password {
     }
 }
 
+// Any other length is up to the `PasswordPolicy`, which only applies to new passwords
 fn validate_password(s: &Secret<String>) -> bool {
-    s.expose_secret().len() >= 8
+    !s.expose_secret().is_empty()
 }
 
 impl AsRef<Secret<String>> for Password {
@@ -30,9 +37,145 @@ impl AsRef<Secret<String>> for Password {
     }
 }
 
+// Rules a new password must follow. Checked on signup and password changes only, so existing
+// passwords keep working when the policy gets stricter.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    // Bounds the work done by Argon2 and the strength estimator
+    pub max_length: usize,
+    // Minimum zxcvbn score, from 0 (too guessable) to 4 (very unguessable)
+    pub min_strength: u8,
+    // Passwords refused for this deployment, lowercase
+    pub deny_list: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            min_strength: 2,
+            deny_list: HashSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordRule {
+    MinLength,
+    MaxLength,
+    Strength,
+    ContainsEmail,
+    DenyList,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicyViolation {
+    pub rule: PasswordRule,
+    pub message: String,
+}
+
+// Shorter local parts would match too many passwords by chance
+const MIN_EMAIL_LOCAL_PART_LENGTH: usize = 3;
+
+impl PasswordPolicy {
+    pub fn with_deny_list<I, S>(mut self, passwords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.deny_list = passwords
+            .into_iter()
+            .map(|password| password.as_ref().trim().to_lowercase())
+            .filter(|password| !password.is_empty())
+            .collect();
+        self
+    }
+
+    pub fn exceeds_max_length(&self, password: &Secret<String>) -> bool {
+        password.expose_secret().chars().count() > self.max_length
+    }
+
+    // Returns every rule the password breaks, so the user can fix them all at once
+    pub fn check(
+        &self,
+        password: &Secret<String>,
+        email: &Email,
+    ) -> Result<(), Vec<PasswordPolicyViolation>> {
+        let password = password.expose_secret();
+        let length = password.chars().count();
+        let mut violations = Vec::new();
+
+        if length < self.min_length {
+            violations.push(PasswordPolicyViolation {
+                rule: PasswordRule::MinLength,
+                message: format!(
+                    "Password must be at least {} characters long",
+                    self.min_length
+                ),
+            });
+        }
+
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation {
+                rule: PasswordRule::MaxLength,
+                message: format!(
+                    "Password must be at most {} characters long",
+                    self.max_length
+                ),
+            });
+        }
+
+        let email = email.as_ref().expose_secret().to_lowercase();
+        let local_part = email.split('@').next().unwrap_or_default();
+        let lowercase_password = password.to_lowercase();
+
+        if local_part.chars().count() >= MIN_EMAIL_LOCAL_PART_LENGTH
+            && lowercase_password.contains(local_part)
+        {
+            violations.push(PasswordPolicyViolation {
+                rule: PasswordRule::ContainsEmail,
+                message: "Password must not contain your email address".to_owned(),
+            });
+        }
+
+        if self.deny_list.contains(&lowercase_password) {
+            violations.push(PasswordPolicyViolation {
+                rule: PasswordRule::DenyList,
+                message: "Password is not allowed".to_owned(),
+            });
+        }
+
+        // Overly long passwords are not scored, they are refused anyway
+        if length <= self.max_length
+            && strength(password, &[email.as_str(), local_part]) < self.min_strength
+        {
+            violations.push(PasswordPolicyViolation {
+                rule: PasswordRule::Strength,
+                message: "Password is too easy to guess".to_owned(),
+            });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+// Blank passwords can't be scored, they are the easiest to guess anyway
+fn strength(password: &str, user_inputs: &[&str]) -> u8 {
+    zxcvbn(password, user_inputs)
+        .map(|entropy| entropy.score())
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Password;
+    use super::{Email, Password, PasswordPolicy, PasswordRule};
 
     use fake::faker::internet::en::Password as FakePassword;
     use fake::Fake;
@@ -44,9 +187,9 @@ mod tests {
         assert!(Password::parse(password).is_err());
     }
     #[test]
-    fn string_less_than_8_characters_is_rejected() {
+    fn short_string_is_left_to_the_policy() {
         let password = Secret::new("1234567".to_string());
-        assert!(Password::parse(password).is_err());
+        assert!(Password::parse(password).is_ok());
     }
 
     #[derive(Debug, Clone)]
@@ -62,4 +205,134 @@ mod tests {
     fn valid_passwords_are_parsed_successfully(valid_password: ValidPasswordFixture) -> bool {
         Password::parse(valid_password.0).is_ok()
     }
+
+    fn email() -> Email {
+        Email::parse(Secret::new("jane.doe@example.com".to_owned())).unwrap()
+    }
+
+    fn violated_rules(policy: &PasswordPolicy, password: &str) -> Vec<PasswordRule> {
+        match policy.check(&Secret::new(password.to_owned()), &email()) {
+            Ok(()) => vec![],
+            Err(violations) => violations.into_iter().map(|v| v.rule).collect(),
+        }
+    }
+
+    #[test]
+    fn strong_password_is_accepted() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            violated_rules(&policy, "correct horse battery staple"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn length_is_bounded() {
+        let policy = PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            violated_rules(&policy, "Xq7#pL2"),
+            vec![PasswordRule::MinLength]
+        );
+        assert_eq!(
+            violated_rules(&policy, &"Xq7#pL2!".repeat(17)),
+            vec![PasswordRule::MaxLength]
+        );
+        assert_eq!(violated_rules(&policy, &"Xq7#pL2!".repeat(16)), vec![]);
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let policy = PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        };
+
+        // 4 characters, but 8 bytes
+        assert_eq!(
+            violated_rules(&policy, "éèêë"),
+            vec![PasswordRule::MinLength]
+        );
+    }
+
+    #[test]
+    fn guessable_password_is_rejected() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(
+            violated_rules(&policy, "password"),
+            vec![PasswordRule::Strength]
+        );
+        assert_eq!(
+            violated_rules(&policy, "12345678"),
+            vec![PasswordRule::Strength]
+        );
+    }
+
+    #[test]
+    fn password_containing_email_local_part_is_rejected() {
+        let policy = PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            violated_rules(&policy, "Jane.Doe-2024!"),
+            vec![PasswordRule::ContainsEmail]
+        );
+    }
+
+    #[test]
+    fn short_email_local_part_is_ignored() {
+        let policy = PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        };
+        let email = Email::parse(Secret::new("jd@example.com".to_owned())).unwrap();
+
+        assert!(policy
+            .check(&Secret::new("jdjdjdjdjd".to_owned()), &email)
+            .is_ok());
+    }
+
+    #[test]
+    fn deny_listed_password_is_rejected_ignoring_case() {
+        let policy = PasswordPolicy {
+            min_strength: 0,
+            ..Default::default()
+        }
+        .with_deny_list(["CompanyName2024", " ", ""]);
+
+        assert_eq!(policy.deny_list.len(), 1);
+        assert_eq!(
+            violated_rules(&policy, "companyname2024"),
+            vec![PasswordRule::DenyList]
+        );
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let policy = PasswordPolicy::default().with_deny_list(["jane"]);
+        let email = Email::parse(Secret::new("jane@example.com".to_owned())).unwrap();
+
+        let rules: Vec<PasswordRule> = policy
+            .check(&Secret::new("jane".to_owned()), &email)
+            .unwrap_err()
+            .into_iter()
+            .map(|v| v.rule)
+            .collect();
+
+        assert_eq!(
+            rules,
+            vec![
+                PasswordRule::MinLength,
+                PasswordRule::ContainsEmail,
+                PasswordRule::DenyList,
+                PasswordRule::Strength,
+            ]
+        );
+    }
 }
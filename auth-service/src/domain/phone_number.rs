@@ -0,0 +1,83 @@
+use std::hash::Hash;
+
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+
+// A phone number in E.164 format, e.g. +40712345678
+#[derive(Debug, Clone)]
+pub struct PhoneNumber(Secret<String>);
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state);
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+impl PhoneNumber {
+    pub fn parse(s: Secret<String>) -> Result<PhoneNumber> {
+        let is_valid = match s.expose_secret().strip_prefix('+') {
+            Some(digits) => {
+                (8..=15).contains(&digits.len())
+                    && digits.chars().all(|c| c.is_ascii_digit())
+                    && !digits.starts_with('0')
+            }
+            None => false,
+        };
+
+        if is_valid {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("Phone numbers must be in E.164 format."))
+        }
+    }
+}
+
+impl AsRef<Secret<String>> for PhoneNumber {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhoneNumber;
+
+    use secrecy::Secret;
+
+    fn parse(s: &str) -> bool {
+        PhoneNumber::parse(Secret::new(s.to_owned())).is_ok()
+    }
+
+    #[test]
+    fn e164_numbers_are_accepted() {
+        assert!(parse("+40712345678"));
+        assert!(parse("+14155552671"));
+        assert!(parse("+12345678"));
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        let test_cases = [
+            "",
+            "+",
+            "0712345678",
+            "+0712345678",
+            "+4071234567a",
+            "+40 712 345 678",
+            "+1234567",
+            "+1234567890123456",
+        ];
+
+        for test_case in test_cases {
+            assert!(!parse(test_case), "Accepted {:?}", test_case);
+        }
+    }
+}
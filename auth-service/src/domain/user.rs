@@ -1,18 +1,186 @@
-use super::{Email, Password};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{DisplayName, Email, Password, PhoneNumber};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
+    pub id: UserId,
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    // Set when the account may be compromised. Logging in is refused until the password is reset.
+    pub password_reset_required: bool,
+    pub phone_number: Option<PhoneNumber>,
+    // Set once the user entered the code sent by SMS to `phone_number`
+    pub phone_number_verified: bool,
+    pub two_fa_channel: TwoFAChannel,
+    // Tokens issued with an older version are revoked, e.g. once the email changed
+    pub token_version: i32,
+    pub display_name: Option<DisplayName>,
+    pub created_at: DateTime<Utc>,
+    // Last time the user changed their account, logins aside
+    pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    // Unknown for users imported with their password hash
+    pub password_changed_at: Option<DateTime<Utc>>,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+        let now = Utc::now();
         User {
+            id: UserId::default(),
             email,
             password,
             requires_2fa,
+            password_reset_required: false,
+            phone_number: None,
+            phone_number_verified: false,
+            two_fa_channel: TwoFAChannel::Email,
+            token_version: 0,
+            display_name: None,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+            password_changed_at: Some(now),
+        }
+    }
+
+    // The phone number 2FA codes are sent to, if the user chose SMS and verified their number
+    pub fn sms_two_fa_phone_number(&self) -> Option<&PhoneNumber> {
+        match self.two_fa_channel {
+            TwoFAChannel::Sms if self.phone_number_verified => self.phone_number.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+// Identifies a user for good, unlike their email address. This is what tokens and domain
+// events refer to users by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn parse(id: &str) -> Result<Self> {
+        let id = Uuid::parse_str(id).map_err(|_| eyre!("Invalid user id"))?;
+        Ok(Self(id))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// A user migrated from another system, along with the password hash it computed. Hashes in
+// other formats than Argon2id are replaced on the first successful login.
+#[derive(Clone, Debug)]
+pub struct ImportedUser {
+    pub id: UserId,
+    pub email: Email,
+    pub password_hash: Secret<String>,
+    pub requires_2fa: bool,
+}
+
+// How 2FA codes are delivered to the user
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            _ => Err(eyre!("{} is not a valid 2FA channel", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn user() -> User {
+        User::new(
+            Email::parse(Secret::new("test@email.com".to_owned())).unwrap(),
+            Password::parse(Secret::new("password123".to_owned())).unwrap(),
+            true,
+        )
+    }
+
+    #[test]
+    fn two_fa_codes_are_sent_by_sms_only_to_verified_numbers() {
+        let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_owned())).unwrap();
+
+        let mut user = user();
+        assert_eq!(user.sms_two_fa_phone_number(), None);
+
+        user.phone_number = Some(phone_number.clone());
+        user.two_fa_channel = TwoFAChannel::Sms;
+        assert_eq!(user.sms_two_fa_phone_number(), None);
+
+        user.phone_number_verified = true;
+        assert_eq!(user.sms_two_fa_phone_number(), Some(&phone_number));
+
+        user.two_fa_channel = TwoFAChannel::Email;
+        assert_eq!(user.sms_two_fa_phone_number(), None);
+    }
+
+    #[test]
+    fn new_users_get_distinct_ids() {
+        assert_ne!(user().id, user().id);
+        let id = user().id;
+        assert_eq!(UserId::parse(&id.to_string()).unwrap(), id);
+        assert!(UserId::parse("test@email.com").is_err());
+    }
+
+    #[test]
+    fn channel_round_trips_through_str() {
+        for channel in [TwoFAChannel::Email, TwoFAChannel::Sms] {
+            assert_eq!(TwoFAChannel::parse(channel.as_str()).unwrap(), channel);
+            assert_eq!(
+                serde_json::to_value(channel).unwrap(),
+                serde_json::json!(channel.as_str())
+            );
         }
     }
 }
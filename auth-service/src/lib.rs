@@ -1,21 +1,38 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{get, patch, post},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
-use redis::{Client, RedisResult};
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use domain::{AuthAPIError, PasswordPolicyViolation};
+use redis::{aio::ConnectionManager, Client, RedisResult};
+use routes::{
+    cancel_email_change, confirm_email_change, delete_account, get_dev_mailbox, get_metrics,
+    get_profile, import_users, introspect, list_audit_events, list_webhook_endpoints, login,
+    logout, register_webhook_endpoint, report_login, request_email_change, reset_password,
+    set_phone_number, set_two_fa_channel, signup, update_profile, verify_2fa, verify_phone_number,
+    verify_token,
+};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
+use utils::{
+    constants::{CSRF_HEADER_NAME, REQUEST_ID_HEADER_NAME},
+    csrf::{csrf_protection, CsrfConfig},
+    tracing::{make_span_with_request_id, on_request, on_response},
+};
 
 pub mod app_state;
 pub mod domain;
@@ -25,43 +42,94 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
+        let allowed_origins: Vec<HeaderValue> = vec![
             "http://localhost:8000".parse()?,
             "http://159.89.93.173:8000".parse()?,
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST, PATCH and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+            // Allow the CSRF token header to be sent cross-origin
+            .allow_headers([HeaderName::from_static(CSRF_HEADER_NAME)])
             // Allow cookies to be included in requests
             .allow_credentials(true)
-            .allow_origin(allowed_origins);
+            .allow_origin(allowed_origins.clone());
+
+        let csrf_config = CsrfConfig::new(allowed_origins);
+
+        let request_id_header = HeaderName::from_static(REQUEST_ID_HEADER_NAME);
+
+        // Routes that act on the user authenticated by the JWT cookie. The CSRF check only applies
+        // to the state-changing ones.
+        let cookie_authenticated_routes = Router::new()
+            .route("/logout", post(logout))
+            .route("/email", post(request_email_change))
+            .route(
+                "/me",
+                get(get_profile)
+                    .patch(update_profile)
+                    .delete(delete_account),
+            )
+            .route("/phone-number", post(set_phone_number))
+            .route("/phone-number/verify", post(verify_phone_number))
+            .route("/two-fa-channel", post(set_two_fa_channel))
+            .route_layer(middleware::from_fn_with_state(csrf_config, csrf_protection));
 
-        let router = Router::new()
+        let mut router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(signup))
             .route("/login", post(login))
-            .route("/logout", post(logout))
             .route("/verify-2fa", post(verify_2fa))
             .route("/verify-token", post(verify_token))
+            .route("/report-login", post(report_login))
+            .route("/reset-password", post(reset_password))
+            .route("/email/confirm", post(confirm_email_change))
+            .route("/email/cancel", post(cancel_email_change))
+            .route("/introspect", post(introspect))
+            .route("/admin/audit-events", get(list_audit_events))
+            .route("/admin/users/import", post(import_users))
+            .route("/admin/metrics", get(get_metrics))
+            .route(
+                "/admin/webhooks",
+                get(list_webhook_endpoints).post(register_webhook_endpoint),
+            )
+            .merge(cookie_authenticated_routes);
+
+        // Development only: emails are captured instead of sent
+        if app_state.dev_mailbox.is_some() {
+            tracing::warn!("emails are captured, not sent, and can be read at /dev/mailbox");
+            router = router.route("/dev/mailbox", get(get_dev_mailbox));
+        }
+
+        let router = router
             .with_state(app_state)
             .layer(cors)
+            .layer(PropagateRequestIdLayer::new(request_id_header.clone()))
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            // Outermost, so that the request id is set before the request is traced
+            .layer(SetRequestIdLayer::new(request_id_header, MakeRequestUuid));
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -75,11 +143,15 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    // Lists every rule a rejected password broke
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<PasswordPolicyViolation>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let mut violations = Vec::new();
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -88,12 +160,55 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            // The error code RFC 7662 asks introspection to use
+            AuthAPIError::MalformedToken => (StatusCode::BAD_REQUEST, "invalid_request"),
+            AuthAPIError::InvalidClientCredentials => {
+                (StatusCode::UNAUTHORIZED, "Invalid client credentials")
+            }
+            AuthAPIError::InvalidAdminApiKey => (StatusCode::UNAUTHORIZED, "Invalid admin API key"),
+            AuthAPIError::CsrfCheckFailed => (StatusCode::FORBIDDEN, "CSRF check failed"),
+            AuthAPIError::InvalidWebhookUrl => (StatusCode::BAD_REQUEST, "Invalid webhook URL"),
+            AuthAPIError::ImportBatchTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Too many users in one import, 1000 at most",
+            ),
+            AuthAPIError::PasswordResetRequired => {
+                (StatusCode::FORBIDDEN, "Password reset required")
+            }
+            AuthAPIError::BreachedPassword => (
+                StatusCode::BAD_REQUEST,
+                "Password found in a data breach, choose another one",
+            ),
+            AuthAPIError::PasswordPolicyViolated(password_violations) => {
+                violations = password_violations;
+                (
+                    StatusCode::BAD_REQUEST,
+                    "Password does not meet the requirements",
+                )
+            }
+            AuthAPIError::InvalidPhoneNumber => (StatusCode::BAD_REQUEST, "Invalid phone number"),
+            AuthAPIError::InvalidVerificationCode => {
+                (StatusCode::BAD_REQUEST, "Invalid verification code")
+            }
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
+            AuthAPIError::InvalidDisplayName => (StatusCode::BAD_REQUEST, "Invalid display name"),
+            AuthAPIError::ServiceOverloaded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many requests in progress, try again later",
+            ),
+            AuthAPIError::TooManyRequests => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, try again later",
+            ),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            violations,
         });
         (status, body).into_response()
     }
@@ -106,11 +221,37 @@ pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Err
         .await
 }
 
+// Creates the database file if it is missing. In WAL mode reads go on while a write is underway.
+#[cfg(feature = "sqlite")]
+pub async fn get_sqlite_pool(url: &str) -> Result<sqlx::SqlitePool, sqlx::Error> {
+    use std::str::FromStr;
+
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
 }
 
+// A multiplexed async connection, shared by cloning it, which reconnects on its own when lost
+pub async fn get_redis_connection_manager(
+    redis_hostname: String,
+) -> RedisResult<ConnectionManager> {
+    get_redis_client(redis_hostname)?
+        .get_connection_manager()
+        .await
+}
+
 fn log_error_chain(e: &(dyn Error + 'static)) {
     let separator =
         "\n-----------------------------------------------------------------------------------\n";
@@ -1,23 +1,65 @@
 use std::sync::Arc;
 
 use auth_service::{
-    app_state::AppState,
-    domain::Email,
-    get_postgres_pool, get_redis_client,
+    app_state::{
+        AppState, AuditLogType, BannedTokenStoreType, BreachedPasswordCheckerType, EmailClientType,
+        EmailQueueType, KnownDeviceStoreType, OutboxStoreType, SmsClientType, TwoFACodeStoreType,
+        UserStoreType,
+    },
+    domain::{Email, PhoneNumber},
+    get_postgres_pool, get_redis_connection_manager,
     services::{
+        capture_email_client::{CaptureEmailClient, DevMailbox},
         data_stores::{
+            postgres_audit_log::PostgresAuditLog,
+            postgres_banned_token_store::PostgresBannedTokenStore,
+            postgres_email_queue::PostgresEmailQueue,
+            postgres_known_device_store::PostgresKnownDeviceStore,
+            postgres_outbox_store::PostgresOutboxStore,
+            postgres_two_fa_code_store::PostgresTwoFACodeStore,
             postgres_user_store::PostgresUserStore,
             redis_banned_token_store::RedisBannedTokenStore,
             redis_two_fa_code_store::RedisTwoFACodeStore,
         },
+        email_worker::{EmailWorker, EmailWorkerConfig},
+        expired_entries_purger::{
+            ExpiredEntriesPurger, ExpiredEntriesPurgerConfig, ExpiringEntries,
+        },
+        hashing_executor::HashingExecutor,
+        hibp_password_checker::HibpPasswordChecker,
+        local_password_checker::LocalPasswordChecker,
+        mock_sms_client::MockSmsClient,
+        noop_password_checker::NoopPasswordChecker,
         postmark_email_client::PostmarkEmailClient,
+        smtp_email_client::SmtpEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_dispatcher::{WebhookDispatcher, WebhookDispatcherConfig},
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        constants::{
+            prod, BreachedPasswordCheckerProvider, EmailProvider, SmsProvider, TokenStoreBackend,
+            UserStoreBackend, ARGON2_CONFIG, BREACHED_PASSWORD_CHECKER,
+            CHECK_BREACHED_PASSWORDS_AT_LOGIN, DATABASE_URL, EMAIL_LOWERCASE_LOCAL_PART,
+            EMAIL_PROVIDER, HASHING_EXECUTOR_CONFIG, PASSWORD_POLICY, POSTMARK_AUTH_TOKEN,
+            PWNED_PASSWORDS_DIR, REDIS_HOST_NAME, SMS_PROVIDER, SMTP_CONFIG, TOKEN_STORE_BACKEND,
+            TWILIO_ACCOUNT_SID, TWILIO_AUTH_TOKEN, TWILIO_FROM_NUMBER, USER_STORE_BACKEND,
+        },
         tracing::init_tracing,
     },
     Application,
 };
+#[cfg(feature = "sqlite")]
+use auth_service::{
+    get_sqlite_pool,
+    services::data_stores::{
+        sqlite_audit_log::SqliteAuditLog, sqlite_banned_token_store::SqliteBannedTokenStore,
+        sqlite_email_queue::SqliteEmailQueue, sqlite_known_device_store::SqliteKnownDeviceStore,
+        sqlite_outbox_store::SqliteOutboxStore, sqlite_two_fa_code_store::SqliteTwoFACodeStore,
+        sqlite_user_store::SqliteUserStore,
+    },
+    utils::constants::SQLITE_DATABASE_URL,
+};
+use redis::aio::ConnectionManager;
 use reqwest::Client;
 use secrecy::Secret;
 use sqlx::PgPool;
@@ -28,20 +70,38 @@ async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
 
-    let pg_pool = configure_postgresql().await;
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let redis_conn = Arc::new(RwLock::new(configure_redis()));
+    let hashing_executor = HashingExecutor::new(*HASHING_EXECUTOR_CONFIG);
+    let stores = configure_stores(hashing_executor.clone()).await;
 
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+    let (email_client, dev_mailbox) = configure_email_client();
+    let sms_client = configure_sms_client();
+    let breached_password_checker = configure_breached_password_checker();
 
-    let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
-    let app_state = AppState::new(
-        user_store,
-        banned_token_store,
-        two_fa_code_store,
-        email_client,
-    );
+    tokio::spawn(configure_email_worker(stores.email_queue.clone(), email_client).run());
+    tokio::spawn(configure_webhook_dispatcher(stores.outbox_store.clone()).run());
+
+    let mut app_state = AppState::new(
+        stores.user_store,
+        stores.banned_token_store,
+        stores.two_fa_code_store,
+        stores.email_queue,
+        stores.audit_log,
+        stores.outbox_store,
+        stores.known_device_store,
+        sms_client,
+        breached_password_checker,
+    )
+    .with_password_policy(PASSWORD_POLICY.clone())
+    .with_hashing_executor(hashing_executor);
+    if *CHECK_BREACHED_PASSWORDS_AT_LOGIN {
+        app_state = app_state.with_breached_password_check_at_login();
+    }
+    if !*EMAIL_LOWERCASE_LOCAL_PART {
+        app_state = app_state.with_case_sensitive_email_local_part();
+    }
+    if let Some(dev_mailbox) = dev_mailbox {
+        app_state = app_state.with_dev_mailbox(dev_mailbox);
+    }
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
@@ -65,13 +125,192 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+// Everything the service keeps, in the database selected with `USER_STORE_BACKEND`
+struct Stores {
+    user_store: UserStoreType,
+    known_device_store: KnownDeviceStoreType,
+    audit_log: AuditLogType,
+    outbox_store: OutboxStoreType,
+    email_queue: EmailQueueType,
+    banned_token_store: BannedTokenStoreType,
+    two_fa_code_store: TwoFACodeStoreType,
+}
+
+async fn configure_stores(hashing_executor: HashingExecutor) -> Stores {
+    match *USER_STORE_BACKEND {
+        UserStoreBackend::Postgres => configure_postgres_stores(hashing_executor).await,
+        UserStoreBackend::Sqlite => configure_sqlite_stores(hashing_executor).await,
+    }
+}
+
+async fn configure_postgres_stores(hashing_executor: HashingExecutor) -> Stores {
+    let pg_pool = configure_postgresql().await;
+
+    let user_store = PostgresUserStore::new(pg_pool.clone(), hashing_executor)
+        .with_argon2_config(*ARGON2_CONFIG);
+    if *EMAIL_LOWERCASE_LOCAL_PART {
+        user_store
+            .lowercase_stored_emails()
+            .await
+            .expect("Failed to lowercase stored emails");
+    }
+
+    // Only connects to Redis when it is the selected backend. The Postgres stores come with a
+    // background task deleting their expired entries.
+    let (banned_token_store, two_fa_code_store): (BannedTokenStoreType, TwoFACodeStoreType) =
+        match *TOKEN_STORE_BACKEND {
+            TokenStoreBackend::Redis => configure_redis_token_stores().await,
+            TokenStoreBackend::Postgres => {
+                tokio::spawn(
+                    configure_expired_entries_purger(
+                        PostgresTwoFACodeStore::new(pg_pool.clone()),
+                        PostgresBannedTokenStore::new(pg_pool.clone()),
+                    )
+                    .run(),
+                );
+                (
+                    Arc::new(PostgresBannedTokenStore::new(pg_pool.clone())),
+                    Arc::new(PostgresTwoFACodeStore::new(pg_pool.clone())),
+                )
+            }
+            TokenStoreBackend::Sqlite => {
+                panic!("TOKEN_STORE_BACKEND=sqlite needs USER_STORE_BACKEND=sqlite.")
+            }
+        };
+
+    Stores {
+        user_store: Arc::new(user_store),
+        known_device_store: Arc::new(PostgresKnownDeviceStore::new(pg_pool.clone())),
+        audit_log: Arc::new(PostgresAuditLog::new(pg_pool.clone())),
+        outbox_store: Arc::new(PostgresOutboxStore::new(pg_pool.clone())),
+        email_queue: Arc::new(PostgresEmailQueue::new(pg_pool)),
+        banned_token_store,
+        two_fa_code_store,
+    }
+}
+
+// Keeps everything in one SQLite file, so no Postgres server is needed
+#[cfg(feature = "sqlite")]
+async fn configure_sqlite_stores(hashing_executor: HashingExecutor) -> Stores {
+    let sqlite_pool = get_sqlite_pool(&SQLITE_DATABASE_URL)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+
+    let user_store = SqliteUserStore::new(sqlite_pool.clone(), hashing_executor)
+        .with_argon2_config(*ARGON2_CONFIG);
+    if *EMAIL_LOWERCASE_LOCAL_PART {
+        user_store
+            .lowercase_stored_emails()
+            .await
+            .expect("Failed to lowercase stored emails");
+    }
+
+    let (banned_token_store, two_fa_code_store): (BannedTokenStoreType, TwoFACodeStoreType) =
+        match *TOKEN_STORE_BACKEND {
+            TokenStoreBackend::Redis => configure_redis_token_stores().await,
+            TokenStoreBackend::Sqlite => {
+                tokio::spawn(
+                    configure_expired_entries_purger(
+                        SqliteTwoFACodeStore::new(sqlite_pool.clone()),
+                        SqliteBannedTokenStore::new(sqlite_pool.clone()),
+                    )
+                    .run(),
+                );
+                (
+                    Arc::new(SqliteBannedTokenStore::new(sqlite_pool.clone())),
+                    Arc::new(SqliteTwoFACodeStore::new(sqlite_pool.clone())),
+                )
+            }
+            TokenStoreBackend::Postgres => {
+                panic!("TOKEN_STORE_BACKEND=postgres needs USER_STORE_BACKEND=postgres.")
+            }
+        };
+
+    Stores {
+        user_store: Arc::new(user_store),
+        known_device_store: Arc::new(SqliteKnownDeviceStore::new(sqlite_pool.clone())),
+        audit_log: Arc::new(SqliteAuditLog::new(sqlite_pool.clone())),
+        outbox_store: Arc::new(SqliteOutboxStore::new(sqlite_pool.clone())),
+        email_queue: Arc::new(SqliteEmailQueue::new(sqlite_pool)),
+        banned_token_store,
+        two_fa_code_store,
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+async fn configure_sqlite_stores(_hashing_executor: HashingExecutor) -> Stores {
+    panic!("USER_STORE_BACKEND=sqlite needs the auth service built with the `sqlite` feature.");
+}
+
+async fn configure_redis_token_stores() -> (BannedTokenStoreType, TwoFACodeStoreType) {
+    let redis_conn = configure_redis().await;
+    (
+        Arc::new(RedisBannedTokenStore::new(redis_conn.clone())),
+        Arc::new(RedisTwoFACodeStore::new(redis_conn)),
+    )
+}
+
+fn configure_expired_entries_purger(
+    two_fa_code_store: impl ExpiringEntries + 'static,
+    banned_token_store: impl ExpiringEntries + 'static,
+) -> ExpiredEntriesPurger {
+    let config = ExpiredEntriesPurgerConfig {
+        interval: prod::expired_entries_purger::INTERVAL,
+    };
+
+    ExpiredEntriesPurger::new(two_fa_code_store, banned_token_store, config)
+}
+
+async fn configure_redis() -> ConnectionManager {
+    get_redis_connection_manager(REDIS_HOST_NAME.to_owned())
+        .await
         .expect("Failed to get Redis connection")
 }
 
+fn configure_email_worker(
+    email_queue: EmailQueueType,
+    email_client: EmailClientType,
+) -> EmailWorker {
+    let config = EmailWorkerConfig {
+        poll_interval: prod::email_worker::POLL_INTERVAL,
+        ..Default::default()
+    };
+
+    EmailWorker::new(email_queue, email_client, config)
+}
+
+fn configure_webhook_dispatcher(outbox_store: OutboxStoreType) -> WebhookDispatcher {
+    let http_client = Client::builder()
+        .timeout(prod::webhook_dispatcher::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    let config = WebhookDispatcherConfig {
+        poll_interval: prod::webhook_dispatcher::POLL_INTERVAL,
+        ..Default::default()
+    };
+
+    WebhookDispatcher::new(outbox_store, http_client, config)
+}
+
+// Captured emails are also returned as a mailbox, to be exposed at `/dev/mailbox`
+fn configure_email_client() -> (EmailClientType, Option<DevMailbox>) {
+    match *EMAIL_PROVIDER {
+        EmailProvider::Postmark => (Arc::new(configure_postmark_email_client()), None),
+        EmailProvider::Smtp => (Arc::new(configure_smtp_email_client()), None),
+        EmailProvider::Capture => {
+            let dev_mailbox = DevMailbox::default();
+            let email_client = CaptureEmailClient::new(dev_mailbox.clone());
+            (Arc::new(email_client), Some(dev_mailbox))
+        }
+    }
+}
+
 fn configure_postmark_email_client() -> PostmarkEmailClient {
     let http_client = Client::builder()
         .timeout(prod::email_client::TIMEOUT)
@@ -85,3 +324,55 @@ fn configure_postmark_email_client() -> PostmarkEmailClient {
         http_client,
     )
 }
+
+fn configure_smtp_email_client() -> SmtpEmailClient {
+    SmtpEmailClient::new(
+        SMTP_CONFIG.clone(),
+        Email::parse(Secret::new(prod::email_client::SENDER.to_owned())).unwrap(),
+    )
+    .expect("Failed to build SMTP email client")
+}
+
+fn configure_sms_client() -> SmsClientType {
+    match *SMS_PROVIDER {
+        SmsProvider::Twilio => Arc::new(RwLock::new(configure_twilio_sms_client())),
+        SmsProvider::Log => Arc::new(RwLock::new(MockSmsClient)),
+    }
+}
+
+fn configure_twilio_sms_client() -> TwilioSmsClient {
+    let http_client = Client::builder()
+        .timeout(prod::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        prod::sms_client::BASE_URL.to_owned(),
+        TWILIO_ACCOUNT_SID.to_owned(),
+        TWILIO_AUTH_TOKEN.to_owned(),
+        PhoneNumber::parse(Secret::new(TWILIO_FROM_NUMBER.to_owned()))
+            .expect("TWILIO_FROM_NUMBER must be in E.164 format."),
+        http_client,
+    )
+}
+
+fn configure_breached_password_checker() -> BreachedPasswordCheckerType {
+    match *BREACHED_PASSWORD_CHECKER {
+        BreachedPasswordCheckerProvider::Hibp => {
+            let http_client = Client::builder()
+                .timeout(prod::password_checker::TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client");
+
+            Arc::new(RwLock::new(HibpPasswordChecker::new(
+                prod::password_checker::BASE_URL.to_owned(),
+                http_client,
+            )))
+        }
+        BreachedPasswordCheckerProvider::Local => Arc::new(RwLock::new(
+            LocalPasswordChecker::new(PWNED_PASSWORDS_DIR.to_owned())
+                .expect("Failed to open the Pwned Passwords dataset"),
+        )),
+        BreachedPasswordCheckerProvider::Disabled => Arc::new(RwLock::new(NoopPasswordChecker)),
+    }
+}
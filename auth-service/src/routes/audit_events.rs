@@ -0,0 +1,73 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventKind, AuditLogQuery, AuthAPIError, Email},
+    utils::extractors::AdminApiKey,
+};
+
+#[tracing::instrument(name = "List audit events", skip_all)]
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    _: AdminApiKey,
+    Query(params): Query<ListAuditEventsParams>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let query = AuditLogQuery {
+        kind: params.kind,
+        // Events are recorded with normalized emails
+        email: params.email.map(|email| {
+            Email::parse_with(Secret::new(email.clone()), state.lowercase_email_local_part)
+                .map(|email| email.as_ref().expose_secret().to_owned())
+                .unwrap_or(email)
+        }),
+        from: params.from,
+        to: params.to,
+        limit: page_size,
+        offset: (page - 1) * page_size,
+    };
+
+    let events = state
+        .audit_log
+        .query(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListAuditEventsResponse {
+        events,
+        page,
+        page_size,
+    }))
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct ListAuditEventsParams {
+    pub kind: Option<AuditEventKind>,
+    pub email: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListAuditEventsResponse {
+    pub events: Vec<AuditEvent>,
+    pub page: i64,
+    pub page_size: i64,
+}
@@ -0,0 +1,226 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, Email, Password, RequestMetadata, UserStoreError},
+    utils::{
+        audit::record_audit_event,
+        auth::{
+            generate_email_change_token, get_token_user, validate_action_token, TokenPurpose,
+            EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+        },
+        constants::AUTH_SERVICE_URL,
+        email_templates::{
+            EmailChangeConfirmEmail, EmailChangeNoticeEmail, EmailInUseEmail, EmailTemplate,
+        },
+        extractors::AuthenticatedUser,
+    },
+};
+
+// Starts changing the email of the user, who confirms with their password. The new address gets
+// a confirmation link and the current one a notice with a cancel link. Nothing changes until the
+// new address is confirmed.
+#[tracing::instrument(name = "Request email change", skip_all)]
+pub async fn request_email_change(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    authenticated_user: AuthenticatedUser,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let new_email = Email::parse_with(request.new_email, state.lowercase_email_local_part)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if state.password_policy.exceeds_max_length(&request.password) {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match state
+        .user_store
+        .validate_user(&authenticated_user.email, &password)
+        .await
+    {
+        Ok(()) => {}
+        Err(UserStoreError::HashingOverloaded) => return Err(AuthAPIError::ServiceOverloaded),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The response must not tell whether the address belongs to an account, which includes the
+    // current email of the user. Its owner is told instead, and nothing changes.
+    if let Ok(owner) = state.user_store.get_user(&new_email).await {
+        let message = EmailInUseEmail
+            .render()
+            .map_err(AuthAPIError::UnexpectedError)?;
+        state
+            .email_queue
+            .enqueue(&owner.email, &message)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        return Ok(StatusCode::OK);
+    }
+
+    let user = state
+        .user_store
+        .get_user_by_id(&authenticated_user.user_id)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let confirm_token = generate_email_change_token(&user, TokenPurpose::EmailChange, &new_email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let cancel_token =
+        generate_email_change_token(&user, TokenPurpose::EmailChangeCancel, &new_email)
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+    let confirm_message = EmailChangeConfirmEmail {
+        confirm_link: &format!(
+            "{}/?confirm-email={}",
+            AUTH_SERVICE_URL.as_str(),
+            confirm_token
+        ),
+        expires_in_minutes: (EMAIL_CHANGE_TOKEN_TTL_SECONDS / 60) as u64,
+    }
+    .render()
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    let notice_message = EmailChangeNoticeEmail {
+        new_email: new_email.as_ref().expose_secret(),
+        cancel_link: &format!(
+            "{}/?cancel-email-change={}",
+            AUTH_SERVICE_URL.as_str(),
+            cancel_token
+        ),
+    }
+    .render()
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .email_queue
+        .enqueue(&new_email, &confirm_message)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .email_queue
+        .enqueue(&user.email, &notice_message)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::EmailChangeRequested,
+        Some(user.email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+// Handles the confirmation link sent to the new address. Changing the email revokes every token
+// of the user, including the cancel link, and drops any 2FA code pending for the old address.
+#[tracing::instrument(name = "Confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    Json(request): Json<EmailChangeTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_action_token(
+        request.token.expose_secret(),
+        TokenPurpose::EmailChange,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_token_user(&state, &claims.sub, claims.ver).await?;
+
+    let new_email = claims
+        .new_email
+        // Normalized before it went into the token
+        .and_then(|email| Email::parse_with(Secret::new(email), false).ok())
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    state
+        .user_store
+        .change_email(&user.id, new_email.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // The email has changed by now, so the request succeeds regardless. A code left behind can't
+    // be used, as no account has the old email anymore, and it expires on its own.
+    if let Err(e) = state.two_fa_code_store.remove_code(&user.email).await {
+        tracing::warn!(error = ?e, "failed to remove the 2FA code of the old email");
+    }
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::EmailChanged,
+        Some(new_email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+// Handles the cancel link sent to the current address. As the request may not come from the
+// user, every token of the user is revoked, which also voids the confirmation link.
+#[tracing::instrument(name = "Cancel email change", skip_all)]
+pub async fn cancel_email_change(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    Json(request): Json<EmailChangeTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_action_token(
+        request.token.expose_secret(),
+        TokenPurpose::EmailChangeCancel,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_token_user(&state, &claims.sub, claims.ver).await?;
+
+    state
+        .user_store
+        .revoke_tokens(&user.id)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::EmailChangeCancelled,
+        Some(user.email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailChangeTokenRequest {
+    pub token: Secret<String>,
+}
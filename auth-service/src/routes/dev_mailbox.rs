@@ -0,0 +1,58 @@
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState, domain::AuthAPIError, services::capture_email_client::CapturedEmail,
+};
+
+// Lists the emails captured by the dev mailbox, as HTML for browsers and as JSON otherwise.
+// The route is only registered when emails are captured instead of sent.
+#[tracing::instrument(name = "Get dev mailbox", skip_all)]
+pub async fn get_dev_mailbox(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<DevMailboxParams>,
+) -> Result<Response, AuthAPIError> {
+    let Some(mailbox) = state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let emails = mailbox.emails(params.recipient.as_deref()).await;
+
+    let accepts_html = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/html"));
+
+    if accepts_html {
+        let page = DevMailboxTemplate { emails: &emails }
+            .render()
+            .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))?;
+        return Ok(Html(page).into_response());
+    }
+
+    Ok(Json(DevMailboxResponse { emails }).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct DevMailboxParams {
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevMailboxResponse {
+    pub emails: Vec<CapturedEmail>,
+}
+
+#[derive(Template)]
+#[template(path = "dev_mailbox.html")]
+struct DevMailboxTemplate<'a> {
+    emails: &'a [CapturedEmail],
+}
@@ -0,0 +1,111 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, ImportedUser, UserId, UserStoreError},
+    utils::{
+        constants::ARGON2_CONFIG,
+        extractors::AdminApiKey,
+        password_hashing::{exceeds_import_cost_limits, is_supported_password_hash},
+    },
+};
+
+// Adds users migrated from another system, keeping their password hashes.
+// Users that can't be imported are skipped and listed in the response, the others are imported.
+#[tracing::instrument(name = "Import users", skip_all)]
+pub async fn import_users(
+    State(state): State<AppState>,
+    _: AdminApiKey,
+    Json(request): Json<ImportUsersRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if request.users.len() > MAX_IMPORT_BATCH_SIZE {
+        return Err(AuthAPIError::ImportBatchTooLarge);
+    }
+
+    let mut imported = 0;
+    let mut skipped = Vec::new();
+
+    for user in request.users {
+        let reason = match parse_imported_user(&user, state.lowercase_email_local_part) {
+            Ok(imported_user) => match state.user_store.import_user(imported_user).await {
+                Ok(()) => {
+                    imported += 1;
+                    continue;
+                }
+                Err(UserStoreError::UserAlreadyExists) => SkipReason::UserAlreadyExists,
+                Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+            },
+            Err(reason) => reason,
+        };
+
+        skipped.push(SkippedUser {
+            email: user.email.expose_secret().to_owned(),
+            reason,
+        });
+    }
+
+    Ok(Json(ImportUsersResponse { imported, skipped }))
+}
+
+const MAX_IMPORT_BATCH_SIZE: usize = 1000;
+
+fn parse_imported_user(
+    user: &ImportUserRequest,
+    lowercase_email_local_part: bool,
+) -> Result<ImportedUser, SkipReason> {
+    let email = Email::parse_with(user.email.clone(), lowercase_email_local_part)
+        .map_err(|_| SkipReason::InvalidEmail)?;
+
+    if !is_supported_password_hash(&user.password_hash) {
+        return Err(SkipReason::UnsupportedPasswordHash);
+    }
+
+    if exceeds_import_cost_limits(&user.password_hash, &ARGON2_CONFIG) {
+        return Err(SkipReason::ExcessivePasswordHashCost);
+    }
+
+    Ok(ImportedUser {
+        id: UserId::default(),
+        email,
+        password_hash: user.password_hash.clone(),
+        requires_2fa: user.requires_2fa,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ImportUsersRequest {
+    pub users: Vec<ImportUserRequest>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportUserRequest {
+    pub email: Secret<String>,
+    // Argon2 or PBKDF2-SHA256 PHC string, or bcrypt hash
+    #[serde(rename = "passwordHash")]
+    pub password_hash: Secret<String>,
+    #[serde(rename = "requires2FA", default)]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportUsersResponse {
+    pub imported: usize,
+    pub skipped: Vec<SkippedUser>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkippedUser {
+    pub email: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    InvalidEmail,
+    UnsupportedPasswordHash,
+    ExcessivePasswordHashCost,
+    UserAlreadyExists,
+}
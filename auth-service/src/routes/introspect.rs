@@ -0,0 +1,86 @@
+use axum::{extract::State, response::IntoResponse, Form, Json};
+use axum_extra::{
+    headers::{authorization::Basic, Authorization},
+    TypedHeader,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::auth::{get_token_user, validate_client_credentials, validate_token, Claims},
+};
+
+// Token introspection as described in RFC 7662.
+// Callers authenticate with HTTP Basic client credentials and send the token form-encoded.
+// Only access tokens are issued, so a `token_type_hint` is ignored as the RFC allows.
+#[tracing::instrument(name = "Introspect", skip_all)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    credentials: Option<TypedHeader<Authorization<Basic>>>,
+    Form(request): Form<IntrospectRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let credentials = match credentials {
+        Some(TypedHeader(Authorization(credentials))) => credentials,
+        None => return Err(AuthAPIError::InvalidClientCredentials),
+    };
+
+    if !validate_client_credentials(credentials.username(), credentials.password()) {
+        return Err(AuthAPIError::InvalidClientCredentials);
+    }
+
+    // RFC 7662 requires the token, a request without one is an `invalid_request`
+    let token = request.token.ok_or(AuthAPIError::MalformedToken)?;
+
+    // Any token we can't validate is simply reported as inactive
+    let response = match validate_token(&token, state.banned_token_store.clone()).await {
+        Ok(claims) => match get_token_user(&state, &claims.sub, claims.ver).await {
+            Ok(_) => IntrospectResponse::from(claims),
+            Err(AuthAPIError::InvalidToken) => IntrospectResponse::inactive(),
+            Err(e) => return Err(e),
+        },
+        Err(_) => IntrospectResponse::inactive(),
+    };
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
+    // Optional here so that a missing token gets a 400 rather than the extractor's 422
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+}
+
+impl IntrospectResponse {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Claims> for IntrospectResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            scope: Some(claims.scope),
+            roles: Some(claims.roles),
+        }
+    }
+}
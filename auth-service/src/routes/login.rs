@@ -1,5 +1,6 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
@@ -7,75 +8,142 @@ use crate::{
     app_state::AppState,
     domain::{
         data_stores::{LoginAttemptId, TwoFACode},
-        AuthAPIError, Email, Password,
+        AuditEventKind, AuthAPIError, Email, Password, PhoneNumber, RequestMetadata, User,
+        UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_auth_cookie, generate_csrf_cookie, generate_session_id},
+        breached_passwords::{
+            flag_breached_password, is_password_breached, resend_password_reset_link,
+        },
+        constants::TWO_FA_CODE_TTL_SECONDS,
+        devices::notify_if_new_device,
+        email_templates::{EmailTemplate, TwoFACodeEmail},
     },
-    utils::auth::generate_auth_cookie,
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let email = match Email::parse(Secret::new(request.email)) {
-        Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
+    let email =
+        match Email::parse_with(Secret::new(request.email), state.lowercase_email_local_part) {
+            Ok(email) => email,
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        };
+
+    // Overly long passwords are refused before reaching Argon2
+    if state.password_policy.exceeds_max_length(&request.password) {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    }
 
     let password = match Password::parse(request.password) {
         Ok(password) => password,
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = &state.user_store.read().await;
+    if let Err(e) = state.user_store.validate_user(&email, &password).await {
+        // Not a failed attempt, the password was not checked
+        match e {
+            UserStoreError::HashingOverloaded => {
+                return (jar, Err(AuthAPIError::ServiceOverloaded))
+            }
+            UserStoreError::UnexpectedError(e) => {
+                return (jar, Err(AuthAPIError::UnexpectedError(e)))
+            }
+            _ => {}
+        }
 
-    if user_store.validate_user(&email, &password).await.is_err() {
+        record_audit_event(
+            &state.audit_log,
+            AuditEventKind::LoginFailed,
+            Some(email.as_ref().expose_secret().to_owned()),
+            &metadata,
+        )
+        .await;
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    let user = match user_store.get_user(&email).await {
+    let user = match state.user_store.get_user(&email).await {
         Ok(user) => user,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    let password_reset_required = if user.password_reset_required {
+        if let Err(e) = resend_password_reset_link(&state, &user).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
+        true
+    } else if state.check_breached_passwords_at_login
+        && is_password_breached(&state, &password).await
+    {
+        if let Err(e) = flag_breached_password(&state, &user, &metadata).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
+        true
+    } else {
+        false
+    };
+
+    if password_reset_required {
+        record_audit_event(
+            &state.audit_log,
+            AuditEventKind::LoginFailed,
+            Some(email.as_ref().expose_secret().to_owned()),
+            &metadata,
+        )
+        .await;
+        return (jar, Err(AuthAPIError::PasswordResetRequired));
+    }
+
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        true => handle_2fa(&user, &state, &metadata, jar).await,
+        false => handle_no_2fa(&user, &state, &metadata, jar).await,
     }
 }
 
 #[tracing::instrument(name = "Handle2FA", skip_all)]
 async fn handle_2fa(
-    email: &Email,
+    user: &User,
     state: &AppState,
+    metadata: &RequestMetadata,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
+    let email = &user.email;
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
     if let Err(e) = state
         .two_fa_code_store
-        .write()
-        .await
         .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
     {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let email_client = &state.email_client.read().await;
-    let message = format!(
-        "Your 2FA code is {}",
-        two_fa_code.as_ref().expose_secret().to_string()
-    );
-    if let Err(e) = email_client.send_email(&email, "2FA code", &message).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    let outcome = match user.sms_two_fa_phone_number() {
+        Some(phone_number) => send_2fa_code_by_sms(state, phone_number, &two_fa_code).await,
+        None => enqueue_2fa_code_email(state, email, &two_fa_code, metadata).await,
+    };
+    if let Err(e) = outcome {
+        return (jar, Err(e));
     }
 
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::TwoFASent,
+        Some(email.as_ref().expose_secret().to_owned()),
+        metadata,
+    )
+    .await;
+
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().expose_secret().to_string(),
@@ -84,20 +152,88 @@ async fn handle_2fa(
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
+async fn send_2fa_code_by_sms(
+    state: &AppState,
+    phone_number: &PhoneNumber,
+    two_fa_code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let content = format!(
+        "Your 2FA code is {}. It expires in {} minutes.",
+        two_fa_code.as_ref().expose_secret(),
+        TWO_FA_CODE_TTL_SECONDS / 60
+    );
+
+    state
+        .sms_client
+        .read()
+        .await
+        .send_sms(phone_number, &content)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+async fn enqueue_2fa_code_email(
+    state: &AppState,
+    email: &Email,
+    two_fa_code: &TwoFACode,
+    metadata: &RequestMetadata,
+) -> Result<(), AuthAPIError> {
+    let message = TwoFACodeEmail {
+        code: two_fa_code.as_ref().expose_secret(),
+        expires_in_minutes: TWO_FA_CODE_TTL_SECONDS / 60,
+        ip: metadata.ip.as_deref().unwrap_or("unknown"),
+    }
+    .render()
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    // The email is sent in the background, so a slow or failing provider doesn't hold up the login
+    state
+        .email_queue
+        .enqueue(email, &message)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(())
+}
+
 #[tracing::instrument(name = "HandleNo2FA", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    state: &AppState,
+    metadata: &RequestMetadata,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(&email) {
+    let email = &user.email;
+    let session_id = generate_session_id();
+
+    let auth_cookie = match generate_auth_cookie(user, &session_id) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(generate_csrf_cookie());
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::LoginSucceeded,
+        Some(email.as_ref().expose_secret().to_owned()),
+        metadata,
+    )
+    .await;
+
+    // The login goes through even if its time can't be recorded
+    if let Err(e) = state
+        .user_store
+        .set_last_login_at(&user.id, Utc::now())
+        .await
+    {
+        tracing::error!(error = ?e, "failed to record last login time");
+    }
+
+    notify_if_new_device(state, user, &session_id, metadata).await;
 
     (
         updated_jar,
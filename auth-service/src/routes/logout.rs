@@ -1,41 +1,56 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
-use axum_extra::extract::{cookie, CookieJar};
-use secrecy::Secret;
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuditEventKind, AuthAPIError, RequestMetadata},
+    utils::{
+        audit::record_audit_event,
+        auth::{
+            create_auth_removal_cookie, create_csrf_removal_cookie, get_token_user, validate_token,
+        },
+        extractors::AuthToken,
+    },
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
+    AuthToken(token): AuthToken,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
-        None => return (jar, Err(AuthAPIError::MissingToken)),
+    let claims = match validate_token(token.expose_secret(), state.banned_token_store.clone()).await
+    {
+        Ok(claims) => claims,
+        Err(_) => {
+            record_audit_event(
+                &state.audit_log,
+                AuditEventKind::TokenRejected,
+                None,
+                &metadata,
+            )
+            .await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
     };
 
-    let token = cookie.value().to_owned();
-
-    match validate_token(&token, state.banned_token_store.clone()).await {
-        Ok(_) => (),
-        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    if let Err(e) = state.banned_token_store.add_token(token).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if let Err(e) = state
-        .banned_token_store
-        .write()
-        .await
-        .add_token(Secret::new(token.to_string()))
+    let jar = jar
+        .remove(create_auth_removal_cookie())
+        .remove(create_csrf_removal_cookie());
+
+    // Audit events refer to users by email, the token only has their id
+    let email = get_token_user(&state, &claims.sub, claims.ver)
         .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
+        .ok()
+        .map(|user| user.email.as_ref().expose_secret().to_owned());
 
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    record_audit_event(&state.audit_log, AuditEventKind::Logout, email, &metadata).await;
 
     (jar, Ok(StatusCode::OK))
 }
@@ -0,0 +1,26 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState, domain::AuthAPIError, services::hashing_executor::HashingExecutorMetrics,
+    utils::extractors::AdminApiKey,
+};
+
+#[tracing::instrument(name = "Get metrics", skip_all)]
+pub async fn get_metrics(
+    State(state): State<AppState>,
+    _: AdminApiKey,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    Ok(Json(MetricsResponse {
+        password_hashing: state
+            .hashing_executor
+            .as_ref()
+            .map(|executor| executor.metrics()),
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricsResponse {
+    // Missing when the user store doesn't hash passwords on an executor
+    pub password_hashing: Option<HashingExecutorMetrics>,
+}
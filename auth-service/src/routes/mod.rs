@@ -1,11 +1,33 @@
+mod audit_events;
+mod change_email;
+mod dev_mailbox;
+mod import_users;
+mod introspect;
 mod login;
 mod logout;
+mod metrics;
+mod phone_number;
+mod profile;
+mod report_login;
+mod reset_password;
 mod signup;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
 
+pub use audit_events::*;
+pub use change_email::*;
+pub use dev_mailbox::*;
+pub use import_users::*;
+pub use introspect::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use phone_number::*;
+pub use profile::*;
+pub use report_login::*;
+pub use reset_password::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
+pub use webhooks::*;
@@ -0,0 +1,119 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::{Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, PhoneNumber, TwoFAChannel, TwoFACode, UserStoreError},
+    utils::{constants::TWO_FA_CODE_TTL_SECONDS, extractors::AuthenticatedUser},
+};
+
+// Sets the phone number of the user, who must then confirm it with the code sent to it by SMS
+#[tracing::instrument(name = "Set phone number", skip_all)]
+pub async fn set_phone_number(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<SetPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let phone_number =
+        PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidPhoneNumber)?;
+
+    let phone_number_key = format!("phone_number:{}", phone_number.as_ref().expose_secret());
+    let user_key = format!("user:{}", user.user_id.as_ref());
+    if !state
+        .sms_rate_limiter
+        .try_acquire(&[&user_key, &phone_number_key])
+    {
+        return Err(AuthAPIError::TooManyRequests);
+    }
+
+    let verification_code = TwoFACode::default();
+    let expires_at = Utc::now() + Duration::seconds(TWO_FA_CODE_TTL_SECONDS as i64);
+
+    state
+        .user_store
+        .set_phone_number(
+            &user.email,
+            phone_number.clone(),
+            verification_code.clone(),
+            expires_at,
+        )
+        .await
+        .map_err(map_user_store_error)?;
+
+    let content = format!(
+        "Your verification code is {}. It expires in {} minutes.",
+        verification_code.as_ref().expose_secret(),
+        TWO_FA_CODE_TTL_SECONDS / 60
+    );
+
+    state
+        .sms_client
+        .read()
+        .await
+        .send_sms(&phone_number, &content)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[tracing::instrument(name = "Verify phone number", skip_all)]
+pub async fn verify_phone_number(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidVerificationCode)?;
+
+    state
+        .user_store
+        .verify_phone_number(&user.email, &code)
+        .await
+        .map_err(map_user_store_error)?;
+
+    Ok(StatusCode::OK)
+}
+
+// Chooses how 2FA codes are delivered. SMS is only allowed once the phone number is verified.
+#[tracing::instrument(name = "Set 2FA channel", skip_all)]
+pub async fn set_two_fa_channel(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<SetTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    state
+        .user_store
+        .set_two_fa_channel(&user.email, request.channel)
+        .await
+        .map_err(map_user_store_error)?;
+
+    Ok(StatusCode::OK)
+}
+
+// The token is valid but its user may have been deleted since it was issued
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+        UserStoreError::InvalidVerificationCode => AuthAPIError::InvalidVerificationCode,
+        UserStoreError::PhoneNumberNotVerified => AuthAPIError::PhoneNumberNotVerified,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SetTwoFAChannelRequest {
+    pub channel: TwoFAChannel,
+}
@@ -0,0 +1,182 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEventKind, AuthAPIError, DisplayName, Password, RequestMetadata, TwoFAChannel, User,
+        UserId, UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{create_auth_removal_cookie, create_csrf_removal_cookie},
+        extractors::AuthenticatedUser,
+    },
+};
+
+#[tracing::instrument(name = "Get profile", skip_all)]
+pub async fn get_profile(
+    State(state): State<AppState>,
+    authenticated_user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = get_user(&state, &authenticated_user.user_id).await?;
+
+    Ok(Json(ProfileResponse::from(user)))
+}
+
+// Updates the fields present in the request, `null` clears them. Responds with the whole profile.
+#[tracing::instrument(name = "Update profile", skip_all)]
+pub async fn update_profile(
+    State(state): State<AppState>,
+    authenticated_user: AuthenticatedUser,
+    Json(request): Json<UpdateProfileRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if let Some(display_name) = request.display_name {
+        let display_name = display_name
+            .map(|display_name| DisplayName::parse(&display_name))
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidDisplayName)?;
+
+        state
+            .user_store
+            .set_display_name(&authenticated_user.user_id, display_name)
+            .await
+            .map_err(map_user_store_error)?;
+    }
+
+    let user = get_user(&state, &authenticated_user.user_id).await?;
+
+    Ok(Json(ProfileResponse::from(user)))
+}
+
+// Deletes the account of the logged-in user, who confirms with their password. Subscribers learn
+// about it through the `user.deleted` webhook, and the user's tokens stop working with it.
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+    authenticated_user: AuthenticatedUser,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = authenticated_user.email;
+
+    if state.password_policy.exceeds_max_length(&request.password) {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    }
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    match state.user_store.validate_user(&email, &password).await {
+        Ok(()) => {}
+        Err(UserStoreError::HashingOverloaded) => {
+            return (jar, Err(AuthAPIError::ServiceOverloaded))
+        }
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(UserStoreError::InvalidCredentials) => {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = state.user_store.delete_user(&email).await {
+        return (jar, Err(map_user_store_error(e)));
+    }
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::AccountDeleted,
+        Some(email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    let jar = jar
+        .remove(create_auth_removal_cookie())
+        .remove(create_csrf_removal_cookie());
+
+    (jar, Ok(StatusCode::OK))
+}
+
+async fn get_user(state: &AppState, id: &UserId) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .get_user_by_id(id)
+        .await
+        .map_err(map_user_store_error)
+}
+
+// The token is valid but its user may have been deleted since
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Tells a missing field (`None`) apart from an explicit `null` (`Some(None)`)
+fn deserialize_present<'de, D>(deserializer: D) -> Result<Option<Option<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub struct UpdateProfileRequest {
+    #[serde(
+        rename = "displayName",
+        default,
+        deserialize_with = "deserialize_present"
+    )]
+    pub display_name: Option<Option<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileResponse {
+    pub id: UserId,
+    pub email: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAChannel")]
+    pub two_fa_channel: TwoFAChannel,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "lastLoginAt")]
+    pub last_login_at: Option<DateTime<Utc>>,
+    #[serde(rename = "passwordChangedAt")]
+    pub password_changed_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for ProfileResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            email: user.email.as_ref().expose_secret().to_owned(),
+            display_name: user
+                .display_name
+                .map(|display_name| display_name.as_ref().to_owned()),
+            requires_2fa: user.requires_2fa,
+            two_fa_channel: user.two_fa_channel,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_login_at: user.last_login_at,
+            password_changed_at: user.password_changed_at,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, RequestMetadata, UserStoreError},
+    utils::{
+        audit::record_audit_event,
+        auth::{
+            generate_action_token, get_token_user, revoke_session, validate_action_token,
+            TokenPurpose, PASSWORD_RESET_TOKEN_TTL_SECONDS,
+        },
+    },
+};
+
+// Handles the "this wasn't me" link of a new device email: the reported session is revoked
+// and the user must reset their password before logging in again. As the caller proved access
+// to the mailbox, they get a password reset token straight away.
+// The link is single-use: it is banned, and every token of the user is revoked along with it.
+#[tracing::instrument(name = "Report login", skip_all)]
+pub async fn report_login(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    Json(request): Json<ReportLoginRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_action_token(
+        request.token.expose_secret(),
+        TokenPurpose::LoginReport,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = get_token_user(&state, &claims.sub, claims.ver).await?;
+    let email = user.email.clone();
+
+    state
+        .banned_token_store
+        .add_token(request.token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if let Some(session_id) = &claims.sid {
+        revoke_session(session_id, &state.banned_token_store)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    // The ban is forgotten before the link expires, the version bump is not
+    state
+        .user_store
+        .revoke_tokens(&user.id)
+        .await
+        .map_err(map_user_store_error)?;
+
+    state
+        .user_store
+        .set_password_reset_required(&email, true)
+        .await
+        .map_err(map_user_store_error)?;
+
+    // Issued for the new token version
+    let user = state
+        .user_store
+        .get_user_by_id(&user.id)
+        .await
+        .map_err(map_user_store_error)?;
+
+    let password_reset_token = generate_action_token(
+        &user,
+        TokenPurpose::PasswordReset,
+        PASSWORD_RESET_TOKEN_TTL_SECONDS,
+        None,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::LoginReported,
+        Some(email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    Ok(Json(ReportLoginResponse {
+        password_reset_token,
+    }))
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReportLoginRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportLoginResponse {
+    #[serde(rename = "passwordResetToken")]
+    pub password_reset_token: String,
+}
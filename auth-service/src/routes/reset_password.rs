@@ -0,0 +1,76 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, Password, RequestMetadata, UserStoreError},
+    utils::{
+        audit::record_audit_event,
+        auth::{get_token_user, validate_action_token, TokenPurpose},
+        breached_passwords::is_password_breached,
+    },
+};
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    metadata: RequestMetadata,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_action_token(
+        request.token.expose_secret(),
+        TokenPurpose::PasswordReset,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = get_token_user(&state, &claims.sub, claims.ver).await?.email;
+
+    state
+        .password_policy
+        .check(&request.new_password, &email)
+        .map_err(AuthAPIError::PasswordPolicyViolated)?;
+
+    let password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if is_password_breached(&state, &password).await {
+        return Err(AuthAPIError::BreachedPassword);
+    }
+
+    state
+        .user_store
+        .update_password(&email, password)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            UserStoreError::HashingOverloaded => AuthAPIError::ServiceOverloaded,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // Reset tokens are single-use
+    state
+        .banned_token_store
+        .add_token(request.token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::PasswordReset,
+        Some(email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
@@ -1,41 +1,66 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User},
+    domain::{
+        AuditEventKind, AuthAPIError, Email, Password, RequestMetadata, User, UserStoreError,
+    },
+    utils::{audit::record_audit_event, breached_passwords::is_password_breached},
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    metadata: RequestMetadata,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let email = Email::parse(request.email);
+    let email = Email::parse_with(request.email, state.lowercase_email_local_part);
     if email.is_err() {
         return Err(AuthAPIError::InvalidCredentials);
     }
     let email = email.unwrap();
 
+    state
+        .password_policy
+        .check(&request.password, &email)
+        .map_err(AuthAPIError::PasswordPolicyViolated)?;
+
     let password = Password::parse(request.password);
     if password.is_err() {
         return Err(AuthAPIError::InvalidCredentials);
     }
     let password = password.unwrap();
 
-    let user = User::new(email.clone(), password, request.requires_2fa);
+    if is_password_breached(&state, &password).await {
+        return Err(AuthAPIError::BreachedPassword);
+    }
 
-    let mut user_store = state.user_store.write().await;
+    let user = User::new(email.clone(), password, request.requires_2fa);
 
-    if let Ok(_) = user_store.get_user(&email).await {
+    // Spares hashing the password of a taken email. Concurrent signups with the same email can
+    // both get past this check, the store's uniqueness constraint rejects all but one of them.
+    if let Ok(_) = state.user_store.get_user(&email).await {
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    if let Err(e) = state.user_store.add_user(user).await {
+        return Err(match e {
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            UserStoreError::HashingOverloaded => AuthAPIError::ServiceOverloaded,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        });
     }
 
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::Signup,
+        Some(email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
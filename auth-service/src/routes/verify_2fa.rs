@@ -1,28 +1,36 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEventKind, AuthAPIError, Email, LoginAttemptId, RequestMetadata, TwoFACode,
+        TwoFACodeStoreError, UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_auth_cookie, generate_csrf_cookie, generate_session_id},
+        devices::notify_if_new_device,
+    },
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let email = Email::parse(request.email);
+    let email = Email::parse_with(request.email, state.lowercase_email_local_part);
     if email.is_err() {
         return (jar, Err(AuthAPIError::InvalidCredentials));
     }
     let email = email.unwrap();
 
-    let login_attempt_id =
-        LoginAttemptId::parse(request.login_attempt_id);
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id);
     if login_attempt_id.is_err() {
         return (jar, Err(AuthAPIError::InvalidCredentials));
     }
@@ -34,27 +42,60 @@ pub async fn verify_2fa(
     }
     let two_fa_code = two_fa_code.unwrap();
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    // Of concurrent requests with the same code, only the one that removes the code goes on
+    match state
+        .two_fa_code_store
+        .consume_code(&email, &login_attempt_id, &two_fa_code)
+        .await
+    {
+        Ok(()) => {}
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound | TwoFACodeStoreError::IncorrectCode) => {
+            record_audit_event(
+                &state.audit_log,
+                AuditEventKind::TwoFAFailed,
+                Some(email.as_ref().expose_secret().to_owned()),
+                &metadata,
+            )
+            .await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
 
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
-        Ok(tuple) => tuple,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    // Tokens refer to the user by id
+    let user = match state.user_store.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     };
 
-    if code_tuple.0 != login_attempt_id || code_tuple.1 != two_fa_code {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
-    }
-
-    if let Err(e) = two_fa_code_store.remove_code(&email).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
+    let session_id = generate_session_id();
 
-    let auth_cookie = match generate_auth_cookie(&email) {
+    let auth_cookie = match generate_auth_cookie(&user, &session_id) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(generate_csrf_cookie());
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::TwoFAVerified,
+        Some(email.as_ref().expose_secret().to_owned()),
+        &metadata,
+    )
+    .await;
+
+    // The login goes through even if its time can't be recorded
+    if let Err(e) = state
+        .user_store
+        .set_last_login_at(&user.id, Utc::now())
+        .await
+    {
+        tracing::error!(error = ?e, "failed to record last login time");
+    }
+
+    notify_if_new_device(&state, &user, &session_id, &metadata).await;
 
     (updated_jar, Ok(StatusCode::OK.into_response()))
 }
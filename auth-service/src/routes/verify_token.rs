@@ -1,24 +1,42 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use serde::Deserialize;
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use secrecy::ExposeSecret;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, AuthAPIError, RequestMetadata},
+    utils::{
+        audit::record_audit_event,
+        auth::{get_token_user, validate_token},
+        extractors::AuthToken,
+    },
+};
 
 #[tracing::instrument(name = "Verify token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(request): Json<VerifyTokenRequest>,
+    metadata: RequestMetadata,
+    AuthToken(token): AuthToken,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let token = request.token;
+    // Tokens of deleted users, or revoked by an email change, are rejected too
+    let user = match validate_token(token.expose_secret(), state.banned_token_store.clone()).await {
+        Ok(claims) => get_token_user(&state, &claims.sub, claims.ver).await,
+        Err(_) => Err(AuthAPIError::InvalidToken),
+    };
 
-    match validate_token(&token, state.banned_token_store.clone()).await {
+    match user {
         Ok(_) => (),
-        Err(_) => return Err(AuthAPIError::InvalidToken),
+        Err(AuthAPIError::InvalidToken) => {
+            record_audit_event(
+                &state.audit_log,
+                AuditEventKind::TokenRejected,
+                None,
+                &metadata,
+            )
+            .await;
+            return Err(AuthAPIError::InvalidToken);
+        }
+        Err(e) => return Err(e),
     }
 
     Ok(StatusCode::OK.into_response())
 }
-
-#[derive(Deserialize)]
-pub struct VerifyTokenRequest {
-    pub token: String,
-}
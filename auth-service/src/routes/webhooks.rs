@@ -0,0 +1,83 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, WebhookEndpoint},
+    utils::extractors::AdminApiKey,
+};
+
+// Registers an endpoint to receive domain events.
+// The signing secret is generated here and only returned once, in this response.
+#[tracing::instrument(name = "Register webhook endpoint", skip_all)]
+pub async fn register_webhook_endpoint(
+    State(state): State<AppState>,
+    _: AdminApiKey,
+    Json(request): Json<RegisterWebhookRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let url = Url::parse(&request.url).map_err(|_| AuthAPIError::InvalidWebhookUrl)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AuthAPIError::InvalidWebhookUrl);
+    }
+
+    let secret = Secret::new(generate_webhook_secret());
+
+    let endpoint = state
+        .outbox_store
+        .add_endpoint(url.to_string(), secret.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = RegisterWebhookResponse {
+        id: endpoint.id,
+        url: endpoint.url,
+        secret: secret.expose_secret().to_owned(),
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[tracing::instrument(name = "List webhook endpoints", skip_all)]
+pub async fn list_webhook_endpoints(
+    State(state): State<AppState>,
+    _: AdminApiKey,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let endpoints = state
+        .outbox_store
+        .list_endpoints()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ListWebhookEndpointsResponse { endpoints }))
+}
+
+const WEBHOOK_SECRET_LENGTH: usize = 32;
+
+fn generate_webhook_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(WEBHOOK_SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterWebhookResponse {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListWebhookEndpointsResponse {
+    pub endpoints: Vec<WebhookEndpoint>,
+}
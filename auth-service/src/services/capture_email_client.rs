@@ -0,0 +1,160 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+// Only the most recent emails are kept, so a long running dev server doesn't grow unbounded
+const MAILBOX_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedEmail {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+// In-memory mailbox holding the emails sent through a `CaptureEmailClient`, for local development
+#[derive(Debug, Clone, Default)]
+pub struct DevMailbox {
+    emails: Arc<RwLock<VecDeque<CapturedEmail>>>,
+}
+
+impl DevMailbox {
+    // Returns the captured emails, most recent first, optionally only those sent to `recipient`
+    pub async fn emails(&self, recipient: Option<&str>) -> Vec<CapturedEmail> {
+        self.emails
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|email| recipient.map_or(true, |r| email.recipient.eq_ignore_ascii_case(r)))
+            .cloned()
+            .collect()
+    }
+
+    async fn push(&self, email: CapturedEmail) {
+        let mut emails = self.emails.write().await;
+        if emails.len() == MAILBOX_CAPACITY {
+            emails.pop_front();
+        }
+        emails.push_back(email);
+    }
+}
+
+// Stores emails in a `DevMailbox` instead of sending them. Never use it in production.
+pub struct CaptureEmailClient {
+    mailbox: DevMailbox,
+}
+
+impl CaptureEmailClient {
+    pub fn new(mailbox: DevMailbox) -> Self {
+        Self { mailbox }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for CaptureEmailClient {
+    #[tracing::instrument(name = "Capturing email", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        self.mailbox
+            .push(CapturedEmail {
+                id: Uuid::new_v4(),
+                recipient: recipient.as_ref().expose_secret().to_owned(),
+                subject: message.subject.clone(),
+                html_body: message.html_body.clone(),
+                text_body: message.text_body.clone(),
+                sent_at: Utc::now(),
+            })
+            .await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn captured_emails_are_listed_most_recent_first() {
+        let mailbox = DevMailbox::default();
+        let client = CaptureEmailClient::new(mailbox.clone());
+
+        client
+            .send_email(&email("first@email.com"), &message("First"))
+            .await
+            .unwrap();
+        client
+            .send_email(&email("second@email.com"), &message("Second"))
+            .await
+            .unwrap();
+
+        let emails = mailbox.emails(None).await;
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].subject, "Second");
+        assert_eq!(emails[1].subject, "First");
+        assert_eq!(emails[1].recipient, "first@email.com");
+        assert_eq!(emails[1].html_body, "<p>Body</p>");
+        assert_eq!(emails[1].text_body, "Body");
+    }
+
+    #[tokio::test]
+    async fn emails_can_be_filtered_by_recipient() {
+        let mailbox = DevMailbox::default();
+        let client = CaptureEmailClient::new(mailbox.clone());
+
+        client
+            .send_email(&email("first@email.com"), &message("First"))
+            .await
+            .unwrap();
+        client
+            .send_email(&email("second@email.com"), &message("Second"))
+            .await
+            .unwrap();
+
+        let emails = mailbox.emails(Some("first@email.com")).await;
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].subject, "First");
+    }
+
+    #[tokio::test]
+    async fn oldest_emails_are_dropped_when_full() {
+        let mailbox = DevMailbox::default();
+        let client = CaptureEmailClient::new(mailbox.clone());
+
+        for i in 0..MAILBOX_CAPACITY + 1 {
+            client
+                .send_email(&email("test@email.com"), &message(&i.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let emails = mailbox.emails(None).await;
+        assert_eq!(emails.len(), MAILBOX_CAPACITY);
+        assert_eq!(emails[0].subject, MAILBOX_CAPACITY.to_string());
+        assert_eq!(emails[MAILBOX_CAPACITY - 1].subject, "1");
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use crate::domain::{Device, DeviceStatus, Email, KnownDeviceStore, KnownDeviceStoreError};
+
+#[derive(Default)]
+pub struct HashmapKnownDeviceStore {
+    devices: RwLock<HashMap<Email, HashSet<Device>>>,
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for HashmapKnownDeviceStore {
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        let mut devices = self.devices.write().unwrap();
+        let devices = devices.entry(email.clone()).or_default();
+        let had_devices = !devices.is_empty();
+
+        if !devices.insert(device.clone()) {
+            return Ok(DeviceStatus::Known);
+        }
+
+        if had_devices {
+            Ok(DeviceStatus::New)
+        } else {
+            Ok(DeviceStatus::First)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn device(user_agent: &str, ip: &str) -> Device {
+        Device {
+            user_agent: Some(user_agent.to_owned()),
+            ip: Some(ip.to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remember_device() {
+        let store = HashmapKnownDeviceStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let laptop = device("laptop", "127.0.0.1");
+        let phone = device("phone", "127.0.0.1");
+
+        let status = store.remember_device(&email, &laptop).await.unwrap();
+        assert_eq!(status, DeviceStatus::First);
+
+        let status = store.remember_device(&email, &laptop).await.unwrap();
+        assert_eq!(status, DeviceStatus::Known);
+
+        let status = store.remember_device(&email, &phone).await.unwrap();
+        assert_eq!(status, DeviceStatus::New);
+
+        let status = store
+            .remember_device(&email, &device("laptop", "10.0.0.1"))
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::New);
+    }
+
+    #[tokio::test]
+    async fn test_devices_are_remembered_per_user() {
+        let store = HashmapKnownDeviceStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let other_email = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+        let laptop = device("laptop", "127.0.0.1");
+
+        store.remember_device(&email, &laptop).await.unwrap();
+
+        let status = store.remember_device(&other_email, &laptop).await.unwrap();
+        assert_eq!(status, DeviceStatus::First);
+    }
+}
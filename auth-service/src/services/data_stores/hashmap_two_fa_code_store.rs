@@ -1,40 +1,64 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::RwLock};
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    email::Email,
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        email::Email,
+    },
+    utils::auth::constant_time_eq,
 };
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: RwLock<HashMap<Email, (LoginAttemptId, TwoFACode)>>,
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        self.codes
+            .write()
+            .unwrap()
+            .insert(email, (login_attempt_id, code));
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        self.codes.remove(email);
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes.write().unwrap().remove(email);
         Ok(())
     }
 
-    async fn get_code(
+    async fn consume_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some((login_attempt_id, code)) => Ok((login_attempt_id.clone(), code.clone())),
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut codes = self.codes.write().unwrap();
+        let (stored_login_attempt_id, stored_code) = codes
+            .get(email)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let matches = constant_time_eq(
+            stored_login_attempt_id.as_ref().expose_secret().as_bytes(),
+            login_attempt_id.as_ref().expose_secret().as_bytes(),
+        ) & constant_time_eq(
+            stored_code.as_ref().expose_secret().as_bytes(),
+            code.as_ref().expose_secret().as_bytes(),
+        );
+        if !matches {
+            return Err(TwoFACodeStoreError::IncorrectCode);
         }
+
+        codes.remove(email);
+        Ok(())
     }
 }
 
@@ -46,7 +70,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_code() {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
@@ -60,7 +84,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_remove_code() {
-        let mut store = HashmapTwoFACodeStore::default();
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
@@ -76,20 +100,48 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_code() {
-        let mut store = HashmapTwoFACodeStore::default();
+    async fn test_consume_code_keeps_code_if_incorrect() {
+        let store = HashmapTwoFACodeStore::default();
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
+        let incorrect_code = TwoFACode::parse(Secret::new("654321".to_string())).unwrap();
 
         store
             .add_code(email.clone(), login_attempt_id.clone(), code.clone())
             .await
             .unwrap();
 
-        let result = store.get_code(&email).await;
+        let result = store
+            .consume_code(&email, &login_attempt_id, &incorrect_code)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
+
+        let result = store
+            .consume_code(&email, &LoginAttemptId::default(), &code)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
 
-        assert!(result.is_ok(), "Expected Ok, got {:?}", result);
-        assert_eq!(result.unwrap(), (login_attempt_id, code));
+        let result = store.consume_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_consume_code_only_once() {
+        let store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
+
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        let result = store.consume_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.consume_code(&email, &login_attempt_id, &code).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 }
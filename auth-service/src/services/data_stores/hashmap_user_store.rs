@@ -1,31 +1,58 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::RwLock};
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
 
+use crate::domain::{
+    DisplayName, Email, ImportedUser, Password, PhoneNumber, TwoFAChannel, TwoFACode, User, UserId,
+    UserStore, UserStoreError,
+};
+
+// Methods needing both maps lock `users` first. No lock is held across an `.await`.
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
+    // Pending phone number verification codes and when they expire
+    phone_verification_codes: RwLock<HashMap<Email, (TwoFACode, DateTime<Utc>)>>,
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if let Some(_) = self.users.get(&user.email) {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().unwrap();
+        if users.contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
 
-        self.users.insert(user.email.clone(), user);
+        users.insert(user.email.clone(), user);
         Ok(())
     }
 
+    // Passwords are kept in plain text here, so there is nothing to verify imported hashes with
+    async fn import_user(&self, _user: ImportedUser) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UnexpectedError(eyre!(
+            "password hashes can't be imported into an in-memory store"
+        )))
+    }
+
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        if let Some(existing_user) = self.users.get(email) {
+        if let Some(existing_user) = self.users.read().unwrap().get(email) {
             Ok(existing_user.clone())
         } else {
             Err(UserStoreError::UserNotFound)
         }
     }
 
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        self.users
+            .read()
+            .unwrap()
+            .values()
+            .find(|user| user.id == *id)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
@@ -38,6 +65,192 @@ impl UserStore for HashmapUserStore {
             Ok(())
         }
     }
+
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().unwrap().get_mut(email) {
+            Some(existing_user) => {
+                let now = Utc::now();
+                existing_user.password = password;
+                existing_user.password_reset_required = false;
+                existing_user.password_changed_at = Some(now);
+                existing_user.updated_at = now;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().unwrap();
+        self.phone_verification_codes.write().unwrap().remove(email);
+        match users.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn change_email(&self, id: &UserId, new_email: Email) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().unwrap();
+        if users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        let old_email = users
+            .values()
+            .find(|user| user.id == *id)
+            .map(|user| user.email.clone())
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        let mut user = users
+            .remove(&old_email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        user.token_version += 1;
+        user.updated_at = Utc::now();
+        users.insert(new_email.clone(), user);
+
+        let mut phone_verification_codes = self.phone_verification_codes.write().unwrap();
+        if let Some(code) = phone_verification_codes.remove(&old_email) {
+            phone_verification_codes.insert(new_email, code);
+        }
+        Ok(())
+    }
+
+    async fn revoke_tokens(&self, id: &UserId) -> Result<(), UserStoreError> {
+        match self
+            .users
+            .write()
+            .unwrap()
+            .values_mut()
+            .find(|user| user.id == *id)
+        {
+            Some(existing_user) => {
+                existing_user.token_version += 1;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().unwrap().get_mut(email) {
+            Some(existing_user) => {
+                existing_user.password_reset_required = required;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_code: TwoFACode,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().unwrap().get_mut(email) {
+            Some(existing_user) => {
+                existing_user.phone_number = Some(phone_number);
+                existing_user.phone_number_verified = false;
+                existing_user.two_fa_channel = TwoFAChannel::Email;
+                existing_user.updated_at = Utc::now();
+                self.phone_verification_codes
+                    .write()
+                    .unwrap()
+                    .insert(email.clone(), (verification_code, expires_at));
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        verification_code: &TwoFACode,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().unwrap();
+        let existing_user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+
+        let mut phone_verification_codes = self.phone_verification_codes.write().unwrap();
+        match phone_verification_codes.get(email) {
+            Some((code, expires_at)) if code == verification_code && *expires_at > Utc::now() => {
+                phone_verification_codes.remove(email);
+                existing_user.phone_number_verified = true;
+                existing_user.updated_at = Utc::now();
+                Ok(())
+            }
+            _ => Err(UserStoreError::InvalidVerificationCode),
+        }
+    }
+
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().unwrap().get_mut(email) {
+            Some(existing_user) => {
+                if channel == TwoFAChannel::Sms && !existing_user.phone_number_verified {
+                    return Err(UserStoreError::PhoneNumberNotVerified);
+                }
+                existing_user.two_fa_channel = channel;
+                existing_user.updated_at = Utc::now();
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_display_name(
+        &self,
+        id: &UserId,
+        display_name: Option<DisplayName>,
+    ) -> Result<(), UserStoreError> {
+        match self
+            .users
+            .write()
+            .unwrap()
+            .values_mut()
+            .find(|user| user.id == *id)
+        {
+            Some(existing_user) => {
+                existing_user.display_name = display_name;
+                existing_user.updated_at = Utc::now();
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_last_login_at(
+        &self,
+        id: &UserId,
+        last_login_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        match self
+            .users
+            .write()
+            .unwrap()
+            .values_mut()
+            .find(|user| user.id == *id)
+        {
+            Some(existing_user) => {
+                existing_user.last_login_at = Some(last_login_at);
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -48,7 +261,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
         let password = Password::parse(Secret::new("password123".to_string())).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
@@ -56,33 +269,259 @@ mod tests {
         let result = store.add_user(user.clone()).await;
         assert!(result.is_ok(), "Expected Ok, got {:?}", result);
 
-        let added_user = store.users.get(&email);
+        let added_user = store.users.read().unwrap().get(&email).cloned();
         assert!(added_user.is_some(), "User was not added to the store");
-        assert_eq!(added_user.unwrap(), &user, "The added user does not match");
+        assert_eq!(added_user.unwrap(), user, "The added user does not match");
     }
 
     #[tokio::test]
     async fn test_get_user() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
         let password = Password::parse(Secret::new("password123".to_string())).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
-        store.users.insert(email.clone(), user.clone());
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), user.clone());
 
         let result = store.get_user(&email).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), user);
     }
 
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), user.clone());
+
+        let result = store.get_user_by_id(&user.id).await;
+        assert_eq!(result.unwrap(), user);
+
+        let result = store.get_user_by_id(&UserId::default()).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let new_email = Email::parse(Secret::new("2@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), user.clone());
+
+        store
+            .change_email(&user.id, new_email.clone())
+            .await
+            .unwrap();
+
+        let changed_user = store.get_user(&new_email).await.unwrap();
+        assert_eq!(changed_user.id, user.id);
+        assert_eq!(changed_user.token_version, user.token_version + 1);
+        assert_eq!(
+            store.get_user(&email).await.unwrap_err(),
+            UserStoreError::UserNotFound
+        );
+
+        let other_user = User::new(email.clone(), password, false);
+        store.add_user(other_user.clone()).await.unwrap();
+        let result = store.change_email(&other_user.id, new_email).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserAlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_set_display_name_and_last_login_at() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), user.clone());
+
+        let display_name = DisplayName::parse("Ada").unwrap();
+        store
+            .set_display_name(&user.id, Some(display_name.clone()))
+            .await
+            .unwrap();
+        let updated_user = store.get_user(&email).await.unwrap();
+        assert_eq!(updated_user.display_name, Some(display_name));
+        assert!(updated_user.updated_at >= user.updated_at);
+
+        let now = Utc::now();
+        store.set_last_login_at(&user.id, now).await.unwrap();
+        let logged_in_user = store.get_user(&email).await.unwrap();
+        assert_eq!(logged_in_user.last_login_at, Some(now));
+        assert_eq!(logged_in_user.updated_at, updated_user.updated_at);
+
+        let result = store.set_display_name(&UserId::default(), None).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
     #[tokio::test]
     async fn test_validate_user() {
-        let mut store = HashmapUserStore::default();
+        let store = HashmapUserStore::default();
         let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
         let password = Password::parse(Secret::new("password123".to_string())).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
-        store.users.insert(email.clone(), user.clone());
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), user.clone());
 
         let result = store.validate_user(&email, &password).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let new_password = Password::parse(Secret::new("password456".to_string())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        store.users.write().unwrap().insert(email.clone(), user);
+
+        let result = store.update_password(&email, new_password.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(
+            store.validate_user(&email, &password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert!(store.validate_user(&email, &new_password).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        store.users.write().unwrap().insert(email.clone(), user);
+
+        assert!(store.delete_user(&email).await.is_ok());
+        assert_eq!(
+            store.delete_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_required_is_cleared_by_password_update() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let new_password = Password::parse(Secret::new("password456".to_string())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        store.users.write().unwrap().insert(email.clone(), user);
+
+        store
+            .set_password_reset_required(&email, true)
+            .await
+            .unwrap();
+        assert!(
+            store
+                .get_user(&email)
+                .await
+                .unwrap()
+                .password_reset_required
+        );
+
+        store.update_password(&email, new_password).await.unwrap();
+        assert!(
+            !store
+                .get_user(&email)
+                .await
+                .unwrap()
+                .password_reset_required
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phone_number_verification() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_string())).unwrap();
+        let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
+        let wrong_code = TwoFACode::parse(Secret::new("654321".to_string())).unwrap();
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), User::new(email.clone(), password, true));
+
+        let expires_at = Utc::now() + chrono::Duration::minutes(10);
+        store
+            .set_phone_number(&email, phone_number.clone(), code.clone(), expires_at)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.set_two_fa_channel(&email, TwoFAChannel::Sms).await,
+            Err(UserStoreError::PhoneNumberNotVerified)
+        );
+        assert_eq!(
+            store.verify_phone_number(&email, &wrong_code).await,
+            Err(UserStoreError::InvalidVerificationCode)
+        );
+
+        store.verify_phone_number(&email, &code).await.unwrap();
+        store
+            .set_two_fa_channel(&email, TwoFAChannel::Sms)
+            .await
+            .unwrap();
+
+        let user = store.get_user(&email).await.unwrap();
+        assert_eq!(user.phone_number, Some(phone_number));
+        assert!(user.phone_number_verified);
+        assert_eq!(user.two_fa_channel, TwoFAChannel::Sms);
+
+        // Codes are single-use
+        assert_eq!(
+            store.verify_phone_number(&email, &code).await,
+            Err(UserStoreError::InvalidVerificationCode)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_phone_verification_code_is_rejected() {
+        let store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("1@email.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_string())).unwrap();
+        let code = TwoFACode::parse(Secret::new("123456".to_string())).unwrap();
+        store
+            .users
+            .write()
+            .unwrap()
+            .insert(email.clone(), User::new(email.clone(), password, true));
+
+        let expires_at = Utc::now() - chrono::Duration::seconds(1);
+        store
+            .set_phone_number(&email, phone_number, code.clone(), expires_at)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.verify_phone_number(&email, &code).await,
+            Err(UserStoreError::InvalidVerificationCode)
+        );
+    }
 }
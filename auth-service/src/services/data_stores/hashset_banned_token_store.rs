@@ -1,23 +1,31 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::RwLock};
 
 use secrecy::{ExposeSecret, Secret};
 
 use crate::domain::{BannedTokenStore, BannedTokenStoreError};
 
+// The lock is never held across an `.await`, so a blocking one is fine
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
-    banned_tokens: HashSet<String>,
+    banned_tokens: RwLock<HashSet<String>>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        self.banned_tokens.insert(token.expose_secret().to_string());
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        self.banned_tokens
+            .write()
+            .unwrap()
+            .insert(token.expose_secret().to_string());
         Ok(())
     }
 
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.banned_tokens.contains(token.expose_secret()))
+        Ok(self
+            .banned_tokens
+            .read()
+            .unwrap()
+            .contains(token.expose_secret()))
     }
 }
 
@@ -27,20 +35,20 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_banned_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
         let token = "token".to_string();
 
         let result = store.add_token(Secret::new(token.clone())).await;
 
         assert!(result.is_ok());
-        assert!(store.banned_tokens.contains(&token));
+        assert!(store.banned_tokens.read().unwrap().contains(&token));
     }
 
     #[tokio::test]
     async fn test_contains_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::default();
         let token = "token".to_string();
-        store.banned_tokens.insert(token.clone());
+        store.banned_tokens.write().unwrap().insert(token.clone());
 
         let result = store.contains_token(&Secret::new(token)).await;
 
@@ -1,6 +1,28 @@
+pub mod hashmap_known_device_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod postgres_audit_log;
+pub mod postgres_banned_token_store;
+pub mod postgres_email_queue;
+pub mod postgres_known_device_store;
+pub mod postgres_outbox_store;
+pub mod postgres_two_fa_code_store;
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
 pub mod redis_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_audit_log;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_banned_token_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_email_queue;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_known_device_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_outbox_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_user_store;
+pub mod vec_audit_log;
@@ -0,0 +1,76 @@
+use sqlx::{query, PgPool};
+
+use crate::domain::{AuditEvent, AuditLog, AuditLogError, AuditLogQuery};
+
+pub struct PostgresAuditLog {
+    pool: PgPool,
+}
+
+impl PostgresAuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for PostgresAuditLog {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        query!(
+            r#"
+            INSERT INTO audit_events (id, kind, email, ip, user_agent, request_id, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            event.id,
+            event.kind.as_str(),
+            event.email,
+            event.ip,
+            event.user_agent,
+            event.request_id,
+            event.occurred_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(&self, filter: &AuditLogQuery) -> Result<Vec<AuditEvent>, AuditLogError> {
+        query!(
+            r#"
+            SELECT id, kind, email, ip, user_agent, request_id, occurred_at
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR kind = $1)
+              AND ($2::TEXT IS NULL OR email = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR occurred_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR occurred_at < $4)
+            ORDER BY occurred_at DESC, id
+            LIMIT $5 OFFSET $6
+            "#,
+            filter.kind.map(|kind| kind.as_str()),
+            filter.email,
+            filter.from,
+            filter.to,
+            filter.limit,
+            filter.offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(AuditEvent {
+                id: row.id,
+                kind: row.kind.parse().map_err(AuditLogError::UnexpectedError)?,
+                email: row.email,
+                ip: row.ip,
+                user_agent: row.user_agent,
+                request_id: row.request_id,
+                occurred_at: row.occurred_at,
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,70 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, PgPool};
+
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+// Each ban gets an `expires_at` of TOKEN_TTL_SECONDS from when it was added, after which the
+// token would be rejected as expired anyway and the ban no longer counts. Expired bans stay in
+// the table until `purge_expired` deletes them, which `ExpiredEntriesPurger` calls every minute by
+// default.
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Deletes the expired bans and returns how many were deleted
+    #[tracing::instrument(name = "Purging expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let result = query!("DELETE FROM banned_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        query!(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES ($1, NOW() + make_interval(secs => $2::FLOAT8))
+            ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            "#,
+            token.expose_secret(),
+            TOKEN_TTL_SECONDS as f64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+        let row = query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM banned_tokens WHERE token = $1 AND expires_at > NOW()
+            ) AS "is_banned!"
+            "#,
+            token.expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.is_banned)
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, PgPool};
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, EmailMessage, EmailQueue, EmailQueueError, QueuedEmail, QueuedEmailStatus,
+};
+
+pub struct PostgresEmailQueue {
+    pool: PgPool,
+}
+
+impl PostgresEmailQueue {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailQueue for PostgresEmailQueue {
+    #[tracing::instrument(name = "Enqueuing email in PostgreSQL", skip_all)]
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<Uuid, EmailQueueError> {
+        let id = Uuid::new_v4();
+
+        query!(
+            r#"
+            INSERT INTO email_queue (id, recipient, subject, html_body, text_body)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            id,
+            recipient.as_ref().expose_secret(),
+            message.subject,
+            message.html_body,
+            message.text_body
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(id)
+    }
+
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL", skip_all)]
+    async fn claim_due_emails(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<QueuedEmail>, EmailQueueError> {
+        query!(
+            r#"
+            UPDATE email_queue
+            SET next_attempt_at = NOW() + make_interval(secs => $2::FLOAT8)
+            WHERE id IN (
+                SELECT id FROM email_queue
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, recipient, subject, html_body, text_body, attempts
+            "#,
+            limit,
+            lease.as_secs_f64()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(QueuedEmail {
+                id: row.id,
+                recipient: Email::parse_with(Secret::new(row.recipient), false)
+                    .map_err(|e| EmailQueueError::UnexpectedError(eyre!(e)))?,
+                message: EmailMessage {
+                    subject: row.subject,
+                    html_body: row.html_body,
+                    text_body: row.text_body,
+                },
+                attempts: row.attempts,
+            })
+        })
+        .collect()
+    }
+
+    // The bodies are cleared once an email leaves the queue, as they may contain codes or tokens
+    #[tracing::instrument(name = "Marking email as sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&self, email_id: Uuid) -> Result<(), EmailQueueError> {
+        query!(
+            r#"
+            UPDATE email_queue
+            SET status = $2, attempts = attempts + 1, sent_at = NOW(), last_error = NULL,
+                html_body = '', text_body = ''
+            WHERE id = $1
+            "#,
+            email_id,
+            QueuedEmailStatus::Sent.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email as failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &self,
+        email_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailQueueError> {
+        let status = match next_attempt_at {
+            Some(_) => QueuedEmailStatus::Pending,
+            None => QueuedEmailStatus::Dead,
+        };
+
+        query!(
+            r#"
+            UPDATE email_queue
+            SET status = $2, attempts = attempts + 1, last_error = $3,
+                next_attempt_at = COALESCE($4, next_attempt_at),
+                html_body = CASE WHEN $4 IS NULL THEN '' ELSE html_body END,
+                text_body = CASE WHEN $4 IS NULL THEN '' ELSE text_body END
+            WHERE id = $1
+            "#,
+            email_id,
+            status.as_str(),
+            error,
+            next_attempt_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting email status from PostgreSQL", skip_all)]
+    async fn get_status(&self, email_id: Uuid) -> Result<QueuedEmailStatus, EmailQueueError> {
+        let row = query!("SELECT status FROM email_queue WHERE id = $1", email_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?
+            .ok_or(EmailQueueError::EmailNotFound)?;
+
+        QueuedEmailStatus::parse(&row.status).map_err(EmailQueueError::UnexpectedError)
+    }
+}
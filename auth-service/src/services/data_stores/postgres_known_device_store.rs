@@ -0,0 +1,56 @@
+use secrecy::ExposeSecret;
+use sqlx::{query, PgPool};
+
+use crate::domain::{Device, DeviceStatus, Email, KnownDeviceStore, KnownDeviceStoreError};
+
+pub struct PostgresKnownDeviceStore {
+    pool: PgPool,
+}
+
+impl PostgresKnownDeviceStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for PostgresKnownDeviceStore {
+    #[tracing::instrument(name = "Remembering device in PostgreSQL", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        // Both statements run against the same snapshot, so the count excludes the inserted row
+        let row = query!(
+            r#"
+            WITH existing AS (
+                SELECT COUNT(*) AS devices FROM known_devices WHERE email = $1
+            ),
+            inserted AS (
+                INSERT INTO known_devices (email, user_agent, ip)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                RETURNING 1
+            )
+            SELECT
+                (SELECT devices FROM existing) AS "devices!",
+                (SELECT COUNT(*) FROM inserted) AS "inserted!"
+            "#,
+            email.as_ref().expose_secret(),
+            device.user_agent.as_deref().unwrap_or_default(),
+            device.ip.as_deref().unwrap_or_default()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let status = match (row.inserted, row.devices) {
+            (0, _) => DeviceStatus::Known,
+            (_, 0) => DeviceStatus::First,
+            _ => DeviceStatus::New,
+        };
+
+        Ok(status)
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::domain::{
+    DomainEvent, DomainEventKind, OutboxStore, OutboxStoreError, WebhookDelivery,
+    WebhookDeliveryStatus, WebhookEndpoint,
+};
+
+// Maximum number of outbox events fanned out to webhook deliveries per call
+const FAN_OUT_BATCH_SIZE: i64 = 100;
+
+pub struct PostgresOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+// Writes a domain event to the outbox. Callers pass the connection of the transaction
+// that performs the change the event describes, so both are committed or rolled back together.
+#[tracing::instrument(name = "Writing domain event to the outbox", skip_all)]
+pub async fn insert_outbox_event(
+    conn: &mut PgConnection,
+    event: &DomainEvent,
+) -> Result<(), sqlx::Error> {
+    query!(
+        "INSERT INTO outbox_events (id, kind, payload, occurred_at) VALUES ($1, $2, $3, $4)",
+        event.id,
+        event.kind.as_str(),
+        event.data,
+        event.occurred_at
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl OutboxStore for PostgresOutboxStore {
+    #[tracing::instrument(name = "Adding webhook endpoint to PostgreSQL", skip_all)]
+    async fn add_endpoint(
+        &self,
+        url: String,
+        secret: Secret<String>,
+    ) -> Result<WebhookEndpoint, OutboxStoreError> {
+        let row = query!(
+            r#"
+            INSERT INTO webhook_endpoints (id, url, secret)
+            VALUES ($1, $2, $3)
+            RETURNING id, url, created_at
+            "#,
+            Uuid::new_v4(),
+            url,
+            secret.expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(WebhookEndpoint {
+            id: row.id,
+            url: row.url,
+            created_at: row.created_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Listing webhook endpoints from PostgreSQL", skip_all)]
+    async fn list_endpoints(&self) -> Result<Vec<WebhookEndpoint>, OutboxStoreError> {
+        let endpoints =
+            query!("SELECT id, url, created_at FROM webhook_endpoints ORDER BY created_at")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?
+                .into_iter()
+                .map(|row| WebhookEndpoint {
+                    id: row.id,
+                    url: row.url,
+                    created_at: row.created_at,
+                })
+                .collect();
+
+        Ok(endpoints)
+    }
+
+    #[tracing::instrument(name = "Fanning out outbox events in PostgreSQL", skip_all)]
+    async fn fan_out_events(&self) -> Result<u64, OutboxStoreError> {
+        // Marking the events as dispatched and creating their deliveries happens in a single
+        // statement, so an event is never fanned out twice, even with several dispatchers.
+        let result = query!(
+            r#"
+            WITH events AS (
+                UPDATE outbox_events SET dispatched_at = NOW()
+                WHERE id IN (
+                    SELECT id FROM outbox_events
+                    WHERE dispatched_at IS NULL
+                    ORDER BY occurred_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id
+            )
+            INSERT INTO webhook_deliveries (id, event_id, endpoint_id)
+            SELECT gen_random_uuid(), events.id, webhook_endpoints.id
+            FROM events CROSS JOIN webhook_endpoints
+            "#,
+            FAN_OUT_BATCH_SIZE
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Claiming due webhook deliveries in PostgreSQL", skip_all)]
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<WebhookDelivery>, OutboxStoreError> {
+        query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET next_attempt_at = NOW() + make_interval(secs => $2::FLOAT8)
+            FROM outbox_events, webhook_endpoints
+            WHERE webhook_deliveries.id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            AND outbox_events.id = webhook_deliveries.event_id
+            AND webhook_endpoints.id = webhook_deliveries.endpoint_id
+            RETURNING
+                webhook_deliveries.id AS "id!",
+                webhook_deliveries.attempts AS "attempts!",
+                webhook_endpoints.url AS "url!",
+                webhook_endpoints.secret AS "secret!",
+                outbox_events.id AS "event_id!",
+                outbox_events.kind AS "kind!",
+                outbox_events.payload AS "payload!",
+                outbox_events.occurred_at AS "occurred_at!"
+            "#,
+            limit,
+            lease.as_secs_f64()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(WebhookDelivery {
+                id: row.id,
+                endpoint_url: row.url,
+                endpoint_secret: Secret::new(row.secret),
+                attempts: row.attempts,
+                event: DomainEvent {
+                    id: row.event_id,
+                    kind: DomainEventKind::parse(&row.kind)
+                        .map_err(|e| OutboxStoreError::UnexpectedError(eyre!(e)))?,
+                    occurred_at: row.occurred_at,
+                    data: row.payload,
+                },
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Marking webhook delivery as delivered in PostgreSQL", skip_all)]
+    async fn mark_delivered(&self, delivery_id: Uuid) -> Result<(), OutboxStoreError> {
+        query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2, attempts = attempts + 1, delivered_at = NOW(), last_error = NULL
+            WHERE id = $1
+            "#,
+            delivery_id,
+            WebhookDeliveryStatus::Delivered.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking webhook delivery as failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &self,
+        delivery_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), OutboxStoreError> {
+        let status = match next_attempt_at {
+            Some(_) => WebhookDeliveryStatus::Pending,
+            None => WebhookDeliveryStatus::Dead,
+        };
+
+        query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2, attempts = attempts + 1, last_error = $3,
+                next_attempt_at = COALESCE($4, next_attempt_at)
+            WHERE id = $1
+            "#,
+            delivery_id,
+            status.as_str(),
+            error,
+            next_attempt_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
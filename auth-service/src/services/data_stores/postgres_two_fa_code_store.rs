@@ -0,0 +1,131 @@
+use secrecy::ExposeSecret;
+use sqlx::{query, PgPool};
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::{
+        auth::{constant_time_eq, hash_verification_code},
+        constants::TWO_FA_CODE_TTL_SECONDS,
+    },
+};
+
+// Codes are stored hashed, like phone verification codes.
+// Expired codes are never accepted, but stay in the table until `purge_expired` is called.
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Deletes the expired codes and returns how many were deleted
+    #[tracing::instrument(name = "Purging expired 2FA codes from PostgreSQL", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+        let result = query!("DELETE FROM two_fa_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    // Replaces the code of a previous login attempt, as the Redis store does
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        query!(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4::FLOAT8))
+            ON CONFLICT (email) DO UPDATE
+            SET login_attempt_id = EXCLUDED.login_attempt_id,
+                code = EXCLUDED.code,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref().expose_secret(),
+            login_attempt_id.as_ref().expose_secret(),
+            hash_verification_code(&code),
+            TWO_FA_CODE_TTL_SECONDS as f64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        query!(
+            "DELETE FROM two_fa_codes WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming 2FA code from PostgreSQL", skip_all)]
+    async fn consume_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let row = query!(
+            r#"
+            SELECT login_attempt_id, code FROM two_fa_codes
+            WHERE email = $1 AND expires_at > NOW()
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let code_hash = hash_verification_code(code);
+        let matches = constant_time_eq(
+            row.login_attempt_id.as_bytes(),
+            login_attempt_id.as_ref().expose_secret().as_bytes(),
+        ) & constant_time_eq(row.code.as_bytes(), code_hash.as_bytes());
+        if !matches {
+            return Err(TwoFACodeStoreError::IncorrectCode);
+        }
+
+        // The row lock taken by the delete makes a concurrent one wait, then find nothing to
+        // delete. A code replaced in the meantime belongs to another login attempt and is kept.
+        let result = query!(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = $1 AND login_attempt_id = $2 AND code = $3 AND expires_at > NOW()
+            "#,
+            email.as_ref().expose_secret(),
+            row.login_attempt_id,
+            row.code
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -1,68 +1,209 @@
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-
-use color_eyre::eyre::{eyre, Context, Result};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
-use sqlx::{query, PgPool};
-use tokio::task::spawn_blocking;
+use sqlx::{query, query_as, PgPool};
+use uuid::Uuid;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{TwoFACode, UserStore, UserStoreError},
+        DisplayName, DomainEvent, DomainEventKind, Email, ImportedUser, Password, PhoneNumber,
+        TwoFAChannel, User, UserId,
+    },
+    services::hashing_executor::{HashingExecutor, PasswordHashingError},
+    utils::{
+        auth::hash_verification_code,
+        password_hashing::{needs_rehash, Argon2Config},
+    },
 };
 
+use super::postgres_outbox_store::insert_outbox_event;
+
 pub struct PostgresUserStore {
     pool: PgPool,
+    hashing_executor: HashingExecutor,
+    argon2_config: Argon2Config,
 }
 
 impl PostgresUserStore {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, hashing_executor: HashingExecutor) -> Self {
+        Self {
+            pool,
+            hashing_executor,
+            argon2_config: Argon2Config::default(),
+        }
+    }
+
+    pub fn with_argon2_config(mut self, argon2_config: Argon2Config) -> Self {
+        self.argon2_config = argon2_config;
+        self
+    }
+
+    // Brings emails stored while the local part kept its case in line with lowercased lookups.
+    // None of them collide, `users_email_lower_idx` keeps emails unique regardless of case.
+    #[tracing::instrument(name = "Lowercasing stored emails in PostgreSQL", skip_all)]
+    pub async fn lowercase_stored_emails(&self) -> Result<u64> {
+        let result = query!("UPDATE users SET email = LOWER(email) WHERE email <> LOWER(email)")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Only replaces the hash that was verified, so a concurrent password change wins
+    #[tracing::instrument(name = "Upgrading password hash in PostgreSQL", skip_all)]
+    async fn upgrade_password_hash(
+        &self,
+        email: &Email,
+        old_password_hash: &Secret<String>,
+        password: &Password,
+    ) -> Result<()> {
+        let password_hash = self
+            .hashing_executor
+            .compute_password_hash(password.as_ref().to_owned(), self.argon2_config)
+            .await?;
+
+        query!(
+            "UPDATE users SET password_hash = $3 WHERE email = $1 AND password_hash = $2",
+            email.as_ref().expose_secret(),
+            old_password_hash.expose_secret(),
+            password_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 }
 
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password.as_ref().to_owned())
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hashing_executor
+            .compute_password_hash(user.password.as_ref().to_owned(), self.argon2_config)
+            .await
+            .map_err(hashing_error)?;
+
+        let mut transaction = self
+            .pool
+            .begin()
             .await
-            .map_err(UserStoreError::UnexpectedError)?;
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
         query!(
-            "INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)",
+            r#"
+            INSERT INTO users (id, email, password_hash, requires_2fa, display_name, created_at,
+                updated_at, password_changed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            user.id.as_ref(),
             &user.email.as_ref().expose_secret(),
             &password_hash.expose_secret(),
+            user.requires_2fa,
+            user.display_name.as_ref().map(|name| name.as_ref()),
+            user.created_at,
+            user.updated_at,
+            user.password_changed_at
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e {
+            // Another user with the same email, maybe in a different case, got there first
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        let event = user_event(DomainEventKind::UserSignedUp, &user.id, &user.email);
+        insert_outbox_event(&mut *transaction, &event)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Importing user to PostgreSQL", skip_all)]
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = query!(
+            r#"
+            INSERT INTO users (id, email, password_hash, requires_2fa) VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            user.id.as_ref(),
+            user.email.as_ref().expose_secret(),
+            user.password_hash.expose_secret(),
             user.requires_2fa
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        Ok(())
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        // Subscribers learn about imported users like about new ones
+        let event = user_event(DomainEventKind::UserSignedUp, &user.id, &user.email);
+        insert_outbox_event(&mut *transaction, &event)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        query!(
-            "SELECT email, password_hash, requires_2fa FROM users WHERE email = $1",
+        query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, password_reset_required,
+                phone_number, phone_number_verified, two_fa_channel, token_version, display_name,
+                created_at, updated_at, last_login_at, password_changed_at
+            FROM users
+            WHERE email = $1
+            "#,
             email.as_ref().expose_secret()
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(Secret::new(row.email))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(Secret::new(row.password_hash))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
         .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgreSQL", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, requires_2fa, password_reset_required,
+                phone_number, phone_number_verified, two_fa_channel, token_version, display_name,
+                created_at, updated_at, last_login_at, password_changed_at
+            FROM users
+            WHERE id = $1
+            "#,
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
@@ -73,57 +214,385 @@ impl UserStore for PostgresUserStore {
     ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
 
-        verify_password_hash(
-            user.password.as_ref().to_owned(),
-            password.as_ref().to_owned(),
+        self.hashing_executor
+            .verify_password_hash(
+                user.password.as_ref().to_owned(),
+                password.as_ref().to_owned(),
+            )
+            .await
+            .map_err(|e| match e {
+                PasswordHashingError::Mismatch => UserStoreError::InvalidCredentials,
+                e => hashing_error(e),
+            })?;
+
+        // The login goes through even if the upgrade fails, it is retried next time
+        if needs_rehash(user.password.as_ref(), &self.argon2_config) {
+            if let Err(e) = self
+                .upgrade_password_hash(email, user.password.as_ref(), password)
+                .await
+            {
+                tracing::warn!(error = ?e, "failed to upgrade password hash");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hashing_executor
+            .compute_password_hash(password.as_ref().to_owned(), self.argon2_config)
+            .await
+            .map_err(hashing_error)?;
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let id = query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2, password_reset_required = FALSE, password_changed_at = NOW(),
+                updated_at = NOW()
+            WHERE email = $1
+            RETURNING id
+            "#,
+            email.as_ref().expose_secret(),
+            password_hash.expose_secret()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .id;
+
+        let event = user_event(DomainEventKind::PasswordChanged, &id.into(), email);
+        insert_outbox_event(&mut *transaction, &event)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&self, email: &Email) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let id = query!(
+            "DELETE FROM users WHERE email = $1 RETURNING id",
+            email.as_ref().expose_secret()
         )
+        .fetch_optional(&mut *transaction)
         .await
-        .map_err(|_| UserStoreError::InvalidCredentials)
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .id;
+
+        let event = user_event(DomainEventKind::UserDeleted, &id.into(), email);
+        insert_outbox_event(&mut *transaction, &event)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Changing user email in PostgreSQL", skip_all)]
+    async fn change_email(&self, id: &UserId, new_email: Email) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Known devices follow the email through their foreign key
+        let result = query!(
+            r#"
+            UPDATE users SET email = $2, token_version = token_version + 1, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+            new_email.as_ref().expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let event = user_event(DomainEventKind::EmailChanged, id, &new_email);
+        insert_outbox_event(&mut *transaction, &event)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking user tokens in PostgreSQL", skip_all)]
+    async fn revoke_tokens(&self, id: &UserId) -> Result<(), UserStoreError> {
+        let result = query!(
+            "UPDATE users SET token_version = token_version + 1 WHERE id = $1",
+            id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting password reset flag in PostgreSQL", skip_all)]
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            "UPDATE users SET password_reset_required = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            required
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting phone number in PostgreSQL", skip_all)]
+    async fn set_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_code: TwoFACode,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            r#"
+            UPDATE users
+            SET phone_number = $2, phone_number_verified = FALSE, two_fa_channel = $3,
+                phone_verification_code = $4, phone_verification_expires_at = $5,
+                updated_at = NOW()
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret(),
+            TwoFAChannel::Email.as_str(),
+            hash_verification_code(&verification_code),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Verifying phone number in PostgreSQL", skip_all)]
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        verification_code: &TwoFACode,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            r#"
+            UPDATE users
+            SET phone_number_verified = TRUE, phone_verification_code = NULL,
+                phone_verification_expires_at = NULL, updated_at = NOW()
+            WHERE email = $1 AND phone_verification_code = $2
+                AND phone_verification_expires_at > NOW()
+            "#,
+            email.as_ref().expose_secret(),
+            hash_verification_code(verification_code)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            // Tells a wrong code apart from a missing user
+            self.get_user(email).await?;
+            return Err(UserStoreError::InvalidVerificationCode);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting 2FA channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            r#"
+            UPDATE users SET two_fa_channel = $2, updated_at = NOW()
+            WHERE email = $1 AND ($2 <> $3 OR phone_number_verified)
+            "#,
+            email.as_ref().expose_secret(),
+            channel.as_str(),
+            TwoFAChannel::Sms.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            self.get_user(email).await?;
+            return Err(UserStoreError::PhoneNumberNotVerified);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting display name in PostgreSQL", skip_all)]
+    async fn set_display_name(
+        &self,
+        id: &UserId,
+        display_name: Option<DisplayName>,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            "UPDATE users SET display_name = $2, updated_at = NOW() WHERE id = $1",
+            id.as_ref(),
+            display_name.as_ref().map(|name| name.as_ref())
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting last login time in PostgreSQL", skip_all)]
+    async fn set_last_login_at(
+        &self,
+        id: &UserId,
+        last_login_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        let result = query!(
+            "UPDATE users SET last_login_at = $2 WHERE id = $1",
+            id.as_ref(),
+            last_login_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
     }
 }
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(&expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(
-                    password_candidate.expose_secret().as_bytes(),
-                    &expected_password_hash,
-                )
-                .wrap_err("failed to verify password hash")
-        })
-    })
-    .await;
+fn hashing_error(e: PasswordHashingError) -> UserStoreError {
+    match e {
+        PasswordHashingError::Saturated => UserStoreError::HashingOverloaded,
+        PasswordHashingError::Failed(e) => UserStoreError::UnexpectedError(e),
+        e => UserStoreError::UnexpectedError(e.into()),
+    }
+}
 
-    result?
+fn user_event(kind: DomainEventKind, id: &UserId, email: &Email) -> DomainEvent {
+    DomainEvent::new(
+        kind,
+        serde_json::json!({
+            "user_id": id,
+            "email": email.as_ref().expose_secret(),
+        }),
+    )
 }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
+struct UserRow {
+    id: Uuid,
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    password_reset_required: bool,
+    phone_number: Option<String>,
+    phone_number_verified: bool,
+    two_fa_channel: String,
+    token_version: i32,
+    display_name: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+    password_changed_at: Option<DateTime<Utc>>,
+}
 
-            Ok(Secret::new(password_hash))
-        })
-    })
-    .await;
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
 
-    result?
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: row.id.into(),
+            email: Email::parse_with(Secret::new(row.email), false)
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(Secret::new(row.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            password_reset_required: row.password_reset_required,
+            phone_number: row
+                .phone_number
+                .map(|phone_number| PhoneNumber::parse(Secret::new(phone_number)))
+                .transpose()
+                .map_err(UserStoreError::UnexpectedError)?,
+            phone_number_verified: row.phone_number_verified,
+            two_fa_channel: TwoFAChannel::parse(&row.two_fa_channel)
+                .map_err(UserStoreError::UnexpectedError)?,
+            token_version: row.token_version,
+            display_name: row
+                .display_name
+                .map(|display_name| DisplayName::parse(&display_name))
+                .transpose()
+                .map_err(UserStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_login_at: row.last_login_at,
+            password_changed_at: row.password_changed_at,
+        })
+    }
 }
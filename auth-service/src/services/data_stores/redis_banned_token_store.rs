@@ -1,21 +1,19 @@
-use std::sync::Arc;
-
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, Secret};
-use tokio::sync::RwLock;
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
     utils::auth::TOKEN_TTL_SECONDS,
 };
 
+// See `RedisTwoFACodeStore` for how the connection is shared
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: ConnectionManager) -> Self {
         Self { conn }
     }
 }
@@ -23,7 +21,7 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "AddToken", skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
         let token_key = get_key(token.expose_secret().as_str());
 
         let value = true;
@@ -35,9 +33,9 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .set_ex(&token_key, value, ttl)
+            .await
             .wrap_err("failed to set banned token in Redis") // New!
             .map_err(BannedTokenStoreError::UnexpectedError)?; // Updated!
 
@@ -50,9 +48,9 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         let is_banned: bool = self
             .conn
-            .write()
-            .await
+            .clone()
             .exists(&token_key)
+            .await
             .wrap_err("failed to check if token exists in Redis") // New!
             .map_err(BannedTokenStoreError::UnexpectedError)?; // Updated!
 
@@ -1,22 +1,24 @@
-use std::sync::Arc;
-
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{aio::ConnectionManager, AsyncCommands, Script};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::{auth::constant_time_eq, constants::TWO_FA_CODE_TTL_SECONDS},
 };
 
+// The connection manager is cheap to clone and multiplexes the commands of all its clones over one
+// connection, which it re-establishes when lost
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(conn: ConnectionManager) -> Self {
         Self { conn }
     }
 }
@@ -25,7 +27,7 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "AddCode", skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
@@ -43,9 +45,9 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
         let _: () = self
             .conn
-            .write()
+            .clone()
+            .set_ex(&key, serialized_data, TWO_FA_CODE_TTL_SECONDS)
             .await
-            .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -53,50 +55,92 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     }
 
     #[tracing::instrument(name = "RemoveCode", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         let key = get_key(email);
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .del(&key)
+            .await
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
-    #[tracing::instrument(name = "GetCode", skip_all)]
-    async fn get_code(
+    #[tracing::instrument(name = "ConsumeCode", skip_all)]
+    async fn consume_code(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
         let key = get_key(email);
 
-        match self.conn.write().await.get::<_, String>(&key) {
-            Ok(value) => {
-                let data: TwoFATuple = serde_json::from_str(&value)
-                    .wrap_err("failed to deserialize 2FA tuple")
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
-
-                let login_attempt_id = LoginAttemptId::parse(Secret::new(data.0))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let value: String = self
+            .conn
+            .clone()
+            .get::<_, Option<String>>(&key)
+            .await
+            .wrap_err("failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let (stored_login_attempt_id, stored_code) = parse_two_fa_tuple(&value)?;
+        let matches = constant_time_eq(
+            stored_login_attempt_id.as_ref().expose_secret().as_bytes(),
+            login_attempt_id.as_ref().expose_secret().as_bytes(),
+        ) & constant_time_eq(
+            stored_code.as_ref().expose_secret().as_bytes(),
+            code.as_ref().expose_secret().as_bytes(),
+        );
+        if !matches {
+            return Err(TwoFACodeStoreError::IncorrectCode);
+        }
 
-                let email_code = TwoFACode::parse(Secret::new(data.1))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        // Deletes the key only if it still holds the checked code, so that of concurrent callers
+        // only the first one succeeds, and a code replaced in the meantime is left alone
+        let deleted: i64 = Script::new(CONSUME_CODE_SCRIPT)
+            .key(&key)
+            .arg(&value)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .wrap_err("failed to consume 2FA code in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                Ok((login_attempt_id, email_code))
-            }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        if deleted == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
         }
+
+        Ok(())
     }
 }
 
+const CONSUME_CODE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+fn parse_two_fa_tuple(value: &str) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+    let data: TwoFATuple = serde_json::from_str(value)
+        .wrap_err("failed to deserialize 2FA tuple")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    let login_attempt_id =
+        LoginAttemptId::parse(Secret::new(data.0)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    let email_code =
+        TwoFACode::parse(Secret::new(data.1)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    Ok((login_attempt_id, email_code))
+}
+
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
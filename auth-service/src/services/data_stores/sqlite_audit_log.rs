@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, FromRow, SqlitePool};
+use uuid::Uuid;
+
+use crate::domain::{AuditEvent, AuditLog, AuditLogError, AuditLogQuery};
+
+pub struct SqliteAuditLog {
+    pool: SqlitePool,
+}
+
+impl SqliteAuditLog {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for SqliteAuditLog {
+    #[tracing::instrument(name = "Recording audit event in SQLite", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        query(
+            r#"
+            INSERT INTO audit_events (id, kind, email, ip, user_agent, request_id, occurred_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(event.id.to_string())
+        .bind(event.kind.as_str())
+        .bind(event.email)
+        .bind(event.ip)
+        .bind(event.user_agent)
+        .bind(event.request_id)
+        .bind(event.occurred_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from SQLite", skip_all)]
+    async fn query(&self, filter: &AuditLogQuery) -> Result<Vec<AuditEvent>, AuditLogError> {
+        query_as::<_, AuditEventRow>(
+            r#"
+            SELECT id, kind, email, ip, user_agent, request_id, occurred_at
+            FROM audit_events
+            WHERE (?1 IS NULL OR kind = ?1)
+              AND (?2 IS NULL OR email = ?2)
+              AND (?3 IS NULL OR occurred_at >= ?3)
+              AND (?4 IS NULL OR occurred_at < ?4)
+            ORDER BY occurred_at DESC, id
+            LIMIT ?5 OFFSET ?6
+            "#,
+        )
+        .bind(filter.kind.map(|kind| kind.as_str()))
+        .bind(filter.email.as_deref())
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.limit)
+        .bind(filter.offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(AuditEvent {
+                id: Uuid::parse_str(&row.id)
+                    .map_err(|e| AuditLogError::UnexpectedError(e.into()))?,
+                kind: row.kind.parse().map_err(AuditLogError::UnexpectedError)?,
+                email: row.email,
+                ip: row.ip,
+                user_agent: row.user_agent,
+                request_id: row.request_id,
+                occurred_at: row.occurred_at,
+            })
+        })
+        .collect()
+    }
+}
+
+#[derive(FromRow)]
+struct AuditEventRow {
+    id: String,
+    kind: String,
+    email: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+    occurred_at: DateTime<Utc>,
+}
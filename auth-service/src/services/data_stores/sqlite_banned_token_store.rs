@@ -0,0 +1,70 @@
+use chrono::{Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, query_scalar, SqlitePool};
+
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+// Bans expire like in the PostgreSQL store: after TOKEN_TTL_SECONDS they no longer count, and
+// they stay in the table until `purge_expired` deletes them.
+pub struct SqliteBannedTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteBannedTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Deletes the expired bans and returns how many were deleted
+    #[tracing::instrument(name = "Purging expired banned tokens from SQLite", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let result = query("DELETE FROM banned_tokens WHERE expires_at <= ?1")
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for SqliteBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to SQLite", skip_all)]
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        query(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at) VALUES (?1, ?2)
+            ON CONFLICT (token) DO UPDATE SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(Utc::now() + Duration::seconds(TOKEN_TTL_SECONDS))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in SQLite", skip_all)]
+    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+        let is_banned: bool = query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM banned_tokens WHERE token = ?1 AND expires_at > ?2
+            )
+            "#,
+        )
+        .bind(token.expose_secret())
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(is_banned)
+    }
+}
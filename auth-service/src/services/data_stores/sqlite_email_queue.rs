@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, query_as, query_scalar, FromRow, SqlitePool};
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, EmailMessage, EmailQueue, EmailQueueError, QueuedEmail, QueuedEmailStatus,
+};
+
+pub struct SqliteEmailQueue {
+    pool: SqlitePool,
+}
+
+impl SqliteEmailQueue {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailQueue for SqliteEmailQueue {
+    #[tracing::instrument(name = "Enqueuing email in SQLite", skip_all)]
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<Uuid, EmailQueueError> {
+        let id = Uuid::new_v4();
+
+        query(
+            r#"
+            INSERT INTO email_queue (id, recipient, subject, html_body, text_body,
+                next_attempt_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(recipient.as_ref().expose_secret())
+        .bind(&message.subject)
+        .bind(&message.html_body)
+        .bind(&message.text_body)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(id)
+    }
+
+    // A single statement, so two workers never claim the same email
+    #[tracing::instrument(name = "Claiming due emails in SQLite", skip_all)]
+    async fn claim_due_emails(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<QueuedEmail>, EmailQueueError> {
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(lease)
+            .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        query_as::<_, QueuedEmailRow>(
+            r#"
+            UPDATE email_queue SET next_attempt_at = ?3
+            WHERE id IN (
+                SELECT id FROM email_queue
+                WHERE status = 'pending' AND next_attempt_at <= ?2
+                ORDER BY next_attempt_at
+                LIMIT ?1
+            )
+            RETURNING id, recipient, subject, html_body, text_body, attempts
+            "#,
+        )
+        .bind(limit)
+        .bind(now)
+        .bind(now + lease)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(QueuedEmail {
+                id: Uuid::parse_str(&row.id)
+                    .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?,
+                recipient: Email::parse_with(Secret::new(row.recipient), false)
+                    .map_err(|e| EmailQueueError::UnexpectedError(eyre!(e)))?,
+                message: EmailMessage {
+                    subject: row.subject,
+                    html_body: row.html_body,
+                    text_body: row.text_body,
+                },
+                attempts: row.attempts,
+            })
+        })
+        .collect()
+    }
+
+    // The bodies are cleared once an email leaves the queue, as they may contain codes or tokens
+    #[tracing::instrument(name = "Marking email as sent in SQLite", skip_all)]
+    async fn mark_sent(&self, email_id: Uuid) -> Result<(), EmailQueueError> {
+        query(
+            r#"
+            UPDATE email_queue
+            SET status = ?2, attempts = attempts + 1, sent_at = ?3, last_error = NULL,
+                html_body = '', text_body = ''
+            WHERE id = ?1
+            "#,
+        )
+        .bind(email_id.to_string())
+        .bind(QueuedEmailStatus::Sent.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email as failed in SQLite", skip_all)]
+    async fn mark_failed(
+        &self,
+        email_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailQueueError> {
+        let status = match next_attempt_at {
+            Some(_) => QueuedEmailStatus::Pending,
+            None => QueuedEmailStatus::Dead,
+        };
+
+        query(
+            r#"
+            UPDATE email_queue
+            SET status = ?2, attempts = attempts + 1, last_error = ?3,
+                next_attempt_at = COALESCE(?4, next_attempt_at),
+                html_body = CASE WHEN ?4 IS NULL THEN '' ELSE html_body END,
+                text_body = CASE WHEN ?4 IS NULL THEN '' ELSE text_body END
+            WHERE id = ?1
+            "#,
+        )
+        .bind(email_id.to_string())
+        .bind(status.as_str())
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting email status from SQLite", skip_all)]
+    async fn get_status(&self, email_id: Uuid) -> Result<QueuedEmailStatus, EmailQueueError> {
+        let status: String = query_scalar("SELECT status FROM email_queue WHERE id = ?1")
+            .bind(email_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| EmailQueueError::UnexpectedError(e.into()))?
+            .ok_or(EmailQueueError::EmailNotFound)?;
+
+        QueuedEmailStatus::parse(&status).map_err(EmailQueueError::UnexpectedError)
+    }
+}
+
+#[derive(FromRow)]
+struct QueuedEmailRow {
+    id: String,
+    recipient: String,
+    subject: String,
+    html_body: String,
+    text_body: String,
+    attempts: i32,
+}
@@ -0,0 +1,68 @@
+use secrecy::ExposeSecret;
+use sqlx::{query, query_scalar, SqlitePool};
+
+use crate::domain::{Device, DeviceStatus, Email, KnownDeviceStore, KnownDeviceStoreError};
+
+// Keeps known devices in the SQLite file of the `SqliteUserStore`, as their rows refer to its
+// users and follow them when their email changes or they are deleted
+pub struct SqliteKnownDeviceStore {
+    pool: SqlitePool,
+}
+
+impl SqliteKnownDeviceStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for SqliteKnownDeviceStore {
+    #[tracing::instrument(name = "Remembering device in SQLite", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        // The insert takes the write lock of the database, so no other device of the user can be
+        // added before the count, which then includes the inserted row
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let inserted = query(
+            r#"
+            INSERT INTO known_devices (email, user_agent, ip)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(device.user_agent.as_deref().unwrap_or_default())
+        .bind(device.ip.as_deref().unwrap_or_default())
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?
+        .rows_affected();
+
+        let devices: i64 = query_scalar("SELECT COUNT(*) FROM known_devices WHERE email = ?1")
+            .bind(email.as_ref().expose_secret())
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let status = match (inserted, devices) {
+            (0, _) => DeviceStatus::Known,
+            (_, 1) => DeviceStatus::First,
+            _ => DeviceStatus::New,
+        };
+
+        Ok(status)
+    }
+}
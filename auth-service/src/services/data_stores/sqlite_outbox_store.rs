@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{query, query_as, query_scalar, FromRow, SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::domain::{
+    DomainEvent, DomainEventKind, OutboxStore, OutboxStoreError, WebhookDelivery,
+    WebhookDeliveryStatus, WebhookEndpoint,
+};
+
+// Maximum number of outbox events fanned out to webhook deliveries per call
+const FAN_OUT_BATCH_SIZE: i64 = 100;
+
+// Keeps the outbox in the SQLite file of the `SqliteUserStore`, which writes events to it in the
+// transaction of the change they describe. SQLite runs a single writer at a time, so the
+// transactions below that start with a write can't interleave with each other.
+pub struct SqliteOutboxStore {
+    pool: SqlitePool,
+}
+
+impl SqliteOutboxStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+// Writes a domain event to the outbox. Callers pass the connection of the transaction
+// that performs the change the event describes, so both are committed or rolled back together.
+#[tracing::instrument(name = "Writing domain event to the outbox", skip_all)]
+pub async fn insert_outbox_event(
+    conn: &mut SqliteConnection,
+    event: &DomainEvent,
+) -> Result<(), sqlx::Error> {
+    query("INSERT INTO outbox_events (id, kind, payload, occurred_at) VALUES (?1, ?2, ?3, ?4)")
+        .bind(event.id.to_string())
+        .bind(event.kind.as_str())
+        .bind(event.data.to_string())
+        .bind(event.occurred_at)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl OutboxStore for SqliteOutboxStore {
+    #[tracing::instrument(name = "Adding webhook endpoint to SQLite", skip_all)]
+    async fn add_endpoint(
+        &self,
+        url: String,
+        secret: Secret<String>,
+    ) -> Result<WebhookEndpoint, OutboxStoreError> {
+        let endpoint = WebhookEndpoint {
+            id: Uuid::new_v4(),
+            url,
+            created_at: Utc::now(),
+        };
+
+        query(
+            "INSERT INTO webhook_endpoints (id, url, secret, created_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(endpoint.id.to_string())
+        .bind(&endpoint.url)
+        .bind(secret.expose_secret())
+        .bind(endpoint.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(endpoint)
+    }
+
+    #[tracing::instrument(name = "Listing webhook endpoints from SQLite", skip_all)]
+    async fn list_endpoints(&self) -> Result<Vec<WebhookEndpoint>, OutboxStoreError> {
+        query_as::<_, WebhookEndpointRow>(
+            "SELECT id, url, created_at FROM webhook_endpoints ORDER BY created_at",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(WebhookEndpoint {
+                id: parse_id(&row.id)?,
+                url: row.url,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Fanning out outbox events in SQLite", skip_all)]
+    async fn fan_out_events(&self) -> Result<u64, OutboxStoreError> {
+        // Marking the events as dispatched takes the write lock, so no other dispatcher picks
+        // them up before their deliveries are created
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+        let now = Utc::now();
+
+        let event_ids: Vec<String> = query_scalar(
+            r#"
+            UPDATE outbox_events SET dispatched_at = ?2
+            WHERE id IN (
+                SELECT id FROM outbox_events
+                WHERE dispatched_at IS NULL
+                ORDER BY occurred_at
+                LIMIT ?1
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(FAN_OUT_BATCH_SIZE)
+        .bind(now)
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        let endpoint_ids: Vec<String> = query_scalar("SELECT id FROM webhook_endpoints")
+            .fetch_all(&mut *transaction)
+            .await
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        let mut deliveries = 0;
+        for event_id in &event_ids {
+            for endpoint_id in &endpoint_ids {
+                query(
+                    r#"
+                    INSERT INTO webhook_deliveries (id, event_id, endpoint_id, next_attempt_at)
+                    VALUES (?1, ?2, ?3, ?4)
+                    "#,
+                )
+                .bind(Uuid::new_v4().to_string())
+                .bind(event_id)
+                .bind(endpoint_id)
+                .bind(now)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+                deliveries += 1;
+            }
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(deliveries)
+    }
+
+    #[tracing::instrument(name = "Claiming due webhook deliveries in SQLite", skip_all)]
+    async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<WebhookDelivery>, OutboxStoreError> {
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(lease)
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        // SQLite can't return the columns of other tables from an update, so the claimed
+        // deliveries are read back within the same transaction
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        let delivery_ids: Vec<String> = query_scalar(
+            r#"
+            UPDATE webhook_deliveries SET next_attempt_at = ?3
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= ?2
+                ORDER BY next_attempt_at
+                LIMIT ?1
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(limit)
+        .bind(now)
+        .bind(now + lease)
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        let rows = query_as::<_, WebhookDeliveryRow>(
+            r#"
+            SELECT
+                webhook_deliveries.id,
+                webhook_deliveries.attempts,
+                webhook_endpoints.url,
+                webhook_endpoints.secret,
+                outbox_events.id AS event_id,
+                outbox_events.kind,
+                outbox_events.payload,
+                outbox_events.occurred_at
+            FROM webhook_deliveries
+            JOIN outbox_events ON outbox_events.id = webhook_deliveries.event_id
+            JOIN webhook_endpoints ON webhook_endpoints.id = webhook_deliveries.endpoint_id
+            WHERE webhook_deliveries.id IN (SELECT value FROM json_each(?1))
+            "#,
+        )
+        .bind(
+            serde_json::to_string(&delivery_ids)
+                .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookDelivery {
+                    id: parse_id(&row.id)?,
+                    endpoint_url: row.url,
+                    endpoint_secret: Secret::new(row.secret),
+                    attempts: row.attempts,
+                    event: DomainEvent {
+                        id: parse_id(&row.event_id)?,
+                        kind: DomainEventKind::parse(&row.kind)
+                            .map_err(|e| OutboxStoreError::UnexpectedError(eyre!(e)))?,
+                        occurred_at: row.occurred_at,
+                        data: serde_json::from_str(&row.payload)
+                            .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Marking webhook delivery as delivered in SQLite", skip_all)]
+    async fn mark_delivered(&self, delivery_id: Uuid) -> Result<(), OutboxStoreError> {
+        query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?2, attempts = attempts + 1, delivered_at = ?3, last_error = NULL
+            WHERE id = ?1
+            "#,
+        )
+        .bind(delivery_id.to_string())
+        .bind(WebhookDeliveryStatus::Delivered.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking webhook delivery as failed in SQLite", skip_all)]
+    async fn mark_failed(
+        &self,
+        delivery_id: Uuid,
+        error: String,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<(), OutboxStoreError> {
+        let status = match next_attempt_at {
+            Some(_) => WebhookDeliveryStatus::Pending,
+            None => WebhookDeliveryStatus::Dead,
+        };
+
+        query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?2, attempts = attempts + 1, last_error = ?3,
+                next_attempt_at = COALESCE(?4, next_attempt_at)
+            WHERE id = ?1
+            "#,
+        )
+        .bind(delivery_id.to_string())
+        .bind(status.as_str())
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, OutboxStoreError> {
+    Uuid::parse_str(id).map_err(|e| OutboxStoreError::UnexpectedError(e.into()))
+}
+
+#[derive(FromRow)]
+struct WebhookEndpointRow {
+    id: String,
+    url: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct WebhookDeliveryRow {
+    id: String,
+    attempts: i32,
+    url: String,
+    secret: String,
+    event_id: String,
+    kind: String,
+    payload: String,
+    occurred_at: DateTime<Utc>,
+}
@@ -0,0 +1,140 @@
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use sqlx::{query, query_as, FromRow, SqlitePool};
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::{
+        auth::{constant_time_eq, hash_verification_code},
+        constants::TWO_FA_CODE_TTL_SECONDS,
+    },
+};
+
+// Codes are stored hashed, like in the PostgreSQL store.
+// Expired codes are never accepted, but stay in the table until `purge_expired` is called.
+pub struct SqliteTwoFACodeStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTwoFACodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Deletes the expired codes and returns how many were deleted
+    #[tracing::instrument(name = "Purging expired 2FA codes from SQLite", skip_all)]
+    pub async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+        let result = query("DELETE FROM two_fa_codes WHERE expires_at <= ?1")
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for SqliteTwoFACodeStore {
+    // Replaces the code of a previous login attempt, as the Redis store does
+    #[tracing::instrument(name = "Adding 2FA code to SQLite", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        query(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (email) DO UPDATE
+            SET login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(login_attempt_id.as_ref().expose_secret())
+        .bind(hash_verification_code(&code))
+        .bind(Utc::now() + Duration::seconds(TWO_FA_CODE_TTL_SECONDS as i64))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from SQLite", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        query("DELETE FROM two_fa_codes WHERE email = ?1")
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming 2FA code from SQLite", skip_all)]
+    async fn consume_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let now = Utc::now();
+        let row = query_as::<_, TwoFACodeRow>(
+            r#"
+            SELECT login_attempt_id, code FROM two_fa_codes
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let code_hash = hash_verification_code(code);
+        let matches = constant_time_eq(
+            row.login_attempt_id.as_bytes(),
+            login_attempt_id.as_ref().expose_secret().as_bytes(),
+        ) & constant_time_eq(row.code.as_bytes(), code_hash.as_bytes());
+        if !matches {
+            return Err(TwoFACodeStoreError::IncorrectCode);
+        }
+
+        // Deletes are serialized by the write lock, so of concurrent ones only the first finds
+        // the row. A code replaced in the meantime belongs to another login attempt and is kept.
+        let result = query(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = ?1 AND login_attempt_id = ?2 AND code = ?3 AND expires_at > ?4
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(&row.login_attempt_id)
+        .bind(&row.code)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(FromRow)]
+struct TwoFACodeRow {
+    login_attempt_id: String,
+    code: String,
+}
@@ -0,0 +1,99 @@
+use std::sync::RwLock;
+
+use crate::domain::{AuditEvent, AuditLog, AuditLogError, AuditLogQuery};
+
+#[derive(Default)]
+pub struct VecAuditLog {
+    events: RwLock<Vec<AuditEvent>>,
+}
+
+#[async_trait::async_trait]
+impl AuditLog for VecAuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        self.events.write().unwrap().push(event);
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditLogQuery) -> Result<Vec<AuditEvent>, AuditLogError> {
+        let mut events: Vec<AuditEvent> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|event| query.matches(event))
+            .cloned()
+            .collect();
+
+        events.sort_by(|a, b| b.occurred_at.cmp(&a.occurred_at));
+
+        Ok(events
+            .into_iter()
+            .skip(query.offset.max(0) as usize)
+            .take(query.limit.max(0) as usize)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{AuditEventKind, RequestMetadata};
+
+    use super::*;
+
+    fn event(kind: AuditEventKind, email: &str) -> AuditEvent {
+        AuditEvent::new(kind, Some(email.to_owned()), &RequestMetadata::default())
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let audit_log = VecAuditLog::default();
+        let event = event(AuditEventKind::Signup, "test@example.com");
+
+        let result = audit_log.record(event.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(*audit_log.events.read().unwrap(), vec![event]);
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_by_kind_and_email() {
+        let audit_log = VecAuditLog::default();
+        let signup = event(AuditEventKind::Signup, "1@example.com");
+        let login = event(AuditEventKind::LoginSucceeded, "1@example.com");
+        let other_login = event(AuditEventKind::LoginSucceeded, "2@example.com");
+        for e in [&signup, &login, &other_login] {
+            audit_log.record(e.clone()).await.unwrap();
+        }
+
+        let query = AuditLogQuery {
+            kind: Some(AuditEventKind::LoginSucceeded),
+            email: Some("1@example.com".to_owned()),
+            limit: 10,
+            ..AuditLogQuery::default()
+        };
+
+        let result = audit_log.query(&query).await.unwrap();
+        assert_eq!(result, vec![login]);
+    }
+
+    #[tokio::test]
+    async fn test_query_paginates_newest_first() {
+        let audit_log = VecAuditLog::default();
+        let mut events = Vec::new();
+        for i in 0..5 {
+            let mut e = event(AuditEventKind::LoginFailed, "test@example.com");
+            e.occurred_at += chrono::Duration::seconds(i);
+            audit_log.record(e.clone()).await.unwrap();
+            events.push(e);
+        }
+
+        let query = AuditLogQuery {
+            limit: 2,
+            offset: 2,
+            ..AuditLogQuery::default()
+        };
+
+        let result = audit_log.query(&query).await.unwrap();
+        assert_eq!(result, vec![events[2].clone(), events[1].clone()]);
+    }
+}
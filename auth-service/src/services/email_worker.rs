@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::{Report, Result};
+
+use crate::{
+    app_state::{EmailClientType, EmailQueueType},
+    domain::QueuedEmail,
+    utils::backoff::exponential_backoff,
+};
+
+#[derive(Debug, Clone)]
+pub struct EmailWorkerConfig {
+    // How long to wait between two rounds
+    pub poll_interval: Duration,
+    // Maximum number of emails sent per round
+    pub batch_size: i64,
+    // How long a claimed email stays hidden from other workers
+    pub lease: Duration,
+    // Emails failing this many times are dead-lettered
+    pub max_attempts: i32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for EmailWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            batch_size: 50,
+            lease: Duration::from_secs(60),
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl EmailWorkerConfig {
+    // Delay before retrying an email that failed `attempts` times
+    pub fn backoff(&self, attempts: i32) -> Duration {
+        exponential_backoff(self.base_backoff, self.max_backoff, attempts)
+    }
+}
+
+// Sends the emails of the queue through the email client
+pub struct EmailWorker {
+    email_queue: EmailQueueType,
+    email_client: EmailClientType,
+    config: EmailWorkerConfig,
+}
+
+impl EmailWorker {
+    pub fn new(
+        email_queue: EmailQueueType,
+        email_client: EmailClientType,
+        config: EmailWorkerConfig,
+    ) -> Self {
+        Self {
+            email_queue,
+            email_client,
+            config,
+        }
+    }
+
+    // Sends forever, meant to be spawned as a background task
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                tracing::error!(error = ?e, "email round failed");
+            }
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    // Runs a single round and returns the number of emails attempted
+    #[tracing::instrument(name = "Sending queued emails", skip_all)]
+    pub async fn run_once(&self) -> Result<usize> {
+        let emails = self
+            .email_queue
+            .claim_due_emails(self.config.batch_size, self.config.lease)
+            .await?;
+
+        for email in &emails {
+            let outcome = self
+                .email_client
+                .send_email(&email.recipient, &email.message)
+                .await;
+
+            match outcome {
+                Ok(()) => self.email_queue.mark_sent(email.id).await?,
+                Err(e) => self.record_failure(email, e).await?,
+            }
+        }
+
+        Ok(emails.len())
+    }
+
+    async fn record_failure(&self, email: &QueuedEmail, error: Report) -> Result<()> {
+        let attempts = email.attempts + 1;
+        let next_attempt_at = if attempts >= self.config.max_attempts {
+            tracing::warn!(email_id = %email.id, attempts, "email dead-lettered");
+            None
+        } else {
+            let delay = chrono::Duration::from_std(self.config.backoff(attempts))?;
+            Some(Utc::now() + delay)
+        };
+
+        self.email_queue
+            .mark_failed(email.id, format!("{:#}", error), next_attempt_at)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Result;
+
+use crate::services::data_stores::{
+    postgres_banned_token_store::PostgresBannedTokenStore,
+    postgres_two_fa_code_store::PostgresTwoFACodeStore,
+};
+#[cfg(feature = "sqlite")]
+use crate::services::data_stores::{
+    sqlite_banned_token_store::SqliteBannedTokenStore,
+    sqlite_two_fa_code_store::SqliteTwoFACodeStore,
+};
+
+#[derive(Debug, Clone)]
+pub struct ExpiredEntriesPurgerConfig {
+    // How long to wait between two purges
+    pub interval: Duration,
+}
+
+impl Default for ExpiredEntriesPurgerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+// A store keeping its expired entries until they are purged
+#[async_trait::async_trait]
+pub trait ExpiringEntries: Send + Sync {
+    // Deletes the expired entries and returns how many were deleted
+    async fn purge_expired_entries(&self) -> Result<u64>;
+}
+
+// Deletes the expired 2FA codes and banned tokens kept in Postgres or SQLite, which unlike Redis
+// don't expire them on their own
+pub struct ExpiredEntriesPurger {
+    two_fa_code_store: Box<dyn ExpiringEntries>,
+    banned_token_store: Box<dyn ExpiringEntries>,
+    config: ExpiredEntriesPurgerConfig,
+}
+
+impl ExpiredEntriesPurger {
+    pub fn new(
+        two_fa_code_store: impl ExpiringEntries + 'static,
+        banned_token_store: impl ExpiringEntries + 'static,
+        config: ExpiredEntriesPurgerConfig,
+    ) -> Self {
+        Self {
+            two_fa_code_store: Box::new(two_fa_code_store),
+            banned_token_store: Box::new(banned_token_store),
+            config,
+        }
+    }
+
+    // Purges forever, meant to be spawned as a background task
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                tracing::error!(error = ?e, "purge of expired entries failed");
+            }
+            tokio::time::sleep(self.config.interval).await;
+        }
+    }
+
+    // Runs a single purge and returns the number of entries deleted
+    #[tracing::instrument(name = "Purging expired entries", skip_all)]
+    pub async fn run_once(&self) -> Result<u64> {
+        let two_fa_codes = self.two_fa_code_store.purge_expired_entries().await?;
+        let banned_tokens = self.banned_token_store.purge_expired_entries().await?;
+
+        Ok(two_fa_codes + banned_tokens)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringEntries for PostgresTwoFACodeStore {
+    async fn purge_expired_entries(&self) -> Result<u64> {
+        Ok(self.purge_expired().await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringEntries for PostgresBannedTokenStore {
+    async fn purge_expired_entries(&self) -> Result<u64> {
+        Ok(self.purge_expired().await?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait::async_trait]
+impl ExpiringEntries for SqliteTwoFACodeStore {
+    async fn purge_expired_entries(&self) -> Result<u64> {
+        Ok(self.purge_expired().await?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait::async_trait]
+impl ExpiringEntries for SqliteBannedTokenStore {
+    async fn purge_expired_entries(&self) -> Result<u64> {
+        Ok(self.purge_expired().await?)
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use color_eyre::eyre::Report;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use crate::utils::password_hashing::{self, Argon2Config};
+
+#[derive(Debug, Clone, Copy)]
+pub struct HashingExecutorConfig {
+    // Number of threads hashing passwords, each of them needs the Argon2 memory cost
+    pub workers: usize,
+    // Jobs waiting for a worker beyond this are rejected straight away
+    pub queue_capacity: usize,
+}
+
+impl Default for HashingExecutorConfig {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism()
+                .map(|workers| workers.get())
+                .unwrap_or(1),
+            queue_capacity: 32,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HashingExecutorError {
+    #[error("Password hashing queue is full")]
+    Saturated,
+    #[error("Password hashing job failed")]
+    JobFailed,
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordHashingError {
+    // Too many passwords are being hashed, the request should be retried later
+    #[error("Password hashing is saturated")]
+    Saturated,
+    #[error("Password does not match")]
+    Mismatch,
+    // The hash could not be computed or verified, e.g. the job panicked
+    #[error("Password hashing failed")]
+    Failed(#[source] Report),
+}
+
+impl From<HashingExecutorError> for PasswordHashingError {
+    fn from(e: HashingExecutorError) -> Self {
+        match e {
+            HashingExecutorError::Saturated => Self::Saturated,
+            e => Self::Failed(e.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashingExecutorMetrics {
+    pub workers: usize,
+    pub queue_capacity: usize,
+    // Jobs waiting for a worker
+    pub queued: usize,
+    // Jobs being run by a worker
+    pub in_flight: usize,
+    pub completed: u64,
+    // Jobs refused because the queue was full
+    pub rejected: u64,
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+// Runs password hashing on a fixed set of threads, apart from tokio's blocking pool, so a burst
+// of logins can't use up all the memory or delay other blocking work.
+// Workers stop once every clone of the executor is dropped.
+#[derive(Clone)]
+pub struct HashingExecutor {
+    sender: SyncSender<Job>,
+    counters: Arc<Counters>,
+    config: HashingExecutorConfig,
+}
+
+impl HashingExecutor {
+    pub fn new(config: HashingExecutorConfig) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(config.queue_capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(Counters::default());
+
+        for i in 0..config.workers.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("password-hashing-{}", i))
+                .spawn(move || run_worker(&receiver))
+                .expect("Failed to spawn password hashing worker");
+        }
+
+        Self {
+            sender,
+            counters,
+            config,
+        }
+    }
+
+    // Fails fast with `Saturated` when the queue is full, instead of waiting for room
+    pub async fn run<F, T>(&self, f: F) -> Result<T, HashingExecutorError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let counters = self.counters.clone();
+        // Counters are updated before the result is sent, so callers see them up to date
+        let job: Job = Box::new(move || {
+            counters.queued.fetch_sub(1, Ordering::SeqCst);
+            counters.in_flight.fetch_add(1, Ordering::SeqCst);
+
+            let result = catch_unwind(AssertUnwindSafe(f));
+
+            counters.in_flight.fetch_sub(1, Ordering::SeqCst);
+            counters.completed.fetch_add(1, Ordering::SeqCst);
+
+            match result {
+                Ok(result) => {
+                    let _ = result_sender.send(result);
+                }
+                Err(_) => tracing::error!("password hashing job panicked"),
+            }
+        });
+
+        // Counted before sending, so a worker never sees the job before it is counted
+        self.counters.queued.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.try_send(job) {
+            self.counters.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(match e {
+                TrySendError::Full(_) => {
+                    self.counters.rejected.fetch_add(1, Ordering::SeqCst);
+                    HashingExecutorError::Saturated
+                }
+                TrySendError::Disconnected(_) => HashingExecutorError::JobFailed,
+            });
+        }
+
+        // The result sender is dropped unused if the job panicked
+        result_receiver
+            .await
+            .map_err(|_| HashingExecutorError::JobFailed)
+    }
+
+    // Fails with `Mismatch` when the password doesn't match
+    #[tracing::instrument(name = "Verify password hash", skip_all)]
+    pub async fn verify_password_hash(
+        &self,
+        expected_password_hash: Secret<String>,
+        password_candidate: Secret<String>,
+    ) -> Result<(), PasswordHashingError> {
+        let current_span: tracing::Span = tracing::Span::current();
+        let matches = self
+            .run(move || {
+                current_span.in_scope(|| {
+                    password_hashing::verify_password_hash(
+                        &expected_password_hash,
+                        &password_candidate,
+                    )
+                })
+            })
+            .await?
+            .map_err(PasswordHashingError::Failed)?;
+
+        match matches {
+            true => Ok(()),
+            false => Err(PasswordHashingError::Mismatch),
+        }
+    }
+
+    #[tracing::instrument(name = "Computing password hash", skip_all)]
+    pub async fn compute_password_hash(
+        &self,
+        password: Secret<String>,
+        config: Argon2Config,
+    ) -> Result<Secret<String>, PasswordHashingError> {
+        let current_span: tracing::Span = tracing::Span::current();
+        self.run(move || {
+            current_span.in_scope(|| password_hashing::compute_password_hash(&password, config))
+        })
+        .await?
+        .map_err(PasswordHashingError::Failed)
+    }
+
+    pub fn metrics(&self) -> HashingExecutorMetrics {
+        HashingExecutorMetrics {
+            workers: self.config.workers.max(1),
+            queue_capacity: self.config.queue_capacity,
+            queued: self.counters.queued.load(Ordering::SeqCst),
+            in_flight: self.counters.in_flight.load(Ordering::SeqCst),
+            completed: self.counters.completed.load(Ordering::SeqCst),
+            rejected: self.counters.rejected.load(Ordering::SeqCst),
+        }
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is only held while waiting for a job, not while running it
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        match job {
+            Ok(job) => job(),
+            // Every sender is dropped
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{sync::RwLock, task::JoinHandle};
+
+    use super::*;
+
+    fn executor(workers: usize, queue_capacity: usize) -> HashingExecutor {
+        HashingExecutor::new(HashingExecutorConfig {
+            workers,
+            queue_capacity,
+        })
+    }
+
+    async fn wait_for_in_flight(executor: &HashingExecutor, in_flight: usize) {
+        for _ in 0..100 {
+            if executor.metrics().in_flight == in_flight {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{} jobs never got in flight", in_flight);
+    }
+
+    // Submits a job that runs until the write lock on `gate` is released
+    fn blocked_job(
+        executor: &HashingExecutor,
+        gate: &Arc<RwLock<()>>,
+    ) -> JoinHandle<Result<(), HashingExecutorError>> {
+        let executor = executor.clone();
+        let gate = gate.clone();
+        tokio::spawn(async move { executor.run(move || drop(gate.blocking_read())).await })
+    }
+
+    #[tokio::test]
+    async fn runs_jobs_on_workers() {
+        let executor = executor(2, 4);
+
+        let thread_name = executor
+            .run(|| thread::current().name().map(str::to_owned))
+            .await
+            .unwrap();
+
+        assert!(thread_name.unwrap().starts_with("password-hashing-"));
+        assert_eq!(executor.metrics().completed, 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_jobs_when_queue_is_full() {
+        let executor = executor(1, 1);
+        let gate = Arc::new(RwLock::new(()));
+        let guard = gate.write().await;
+
+        let running = blocked_job(&executor, &gate);
+        wait_for_in_flight(&executor, 1).await;
+        let queued = blocked_job(&executor, &gate);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let result = executor.run(|| ()).await;
+        assert!(matches!(result, Err(HashingExecutorError::Saturated)));
+
+        assert_eq!(
+            executor.metrics(),
+            HashingExecutorMetrics {
+                workers: 1,
+                queue_capacity: 1,
+                queued: 1,
+                in_flight: 1,
+                completed: 0,
+                rejected: 1,
+            }
+        );
+
+        drop(guard);
+        assert!(running.await.unwrap().is_ok());
+        assert!(queued.await.unwrap().is_ok());
+        assert!(executor.run(|| ()).await.is_ok());
+        assert_eq!(executor.metrics().completed, 3);
+    }
+
+    #[tokio::test]
+    async fn survives_panicking_jobs() {
+        let executor = executor(1, 1);
+
+        let result = executor.run(|| panic!("hashing failed")).await;
+        assert!(matches!(result, Err(HashingExecutorError::JobFailed)));
+
+        assert_eq!(executor.run(|| 42).await.unwrap(), 42);
+    }
+}
@@ -0,0 +1,146 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+
+use crate::domain::BreachedPasswordChecker;
+
+// Looks passwords up with the Have I Been Pwned range API, or any server implementing its protocol
+pub struct HibpPasswordChecker {
+    http_client: Client,
+    base_url: String,
+}
+
+impl HibpPasswordChecker {
+    pub fn new(base_url: String, http_client: Client) -> Self {
+        Self {
+            http_client,
+            base_url,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HibpPasswordChecker {
+    #[tracing::instrument(name = "Fetching breached password range", skip_all)]
+    async fn get_range(&self, prefix: &str) -> Result<String> {
+        let base = Url::parse(&self.base_url)?;
+        let url = base.join(&format!("/range/{}", prefix))?;
+
+        // Padding hides the size of the response, which could otherwise hint at the prefix.
+        // For more information, see the API docs: https://haveibeenpwned.com/API/v3#PwnedPasswords
+        let range = self
+            .http_client
+            .get(url)
+            .header(ADD_PADDING_HEADER, "true")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(range)
+    }
+}
+
+const ADD_PADDING_HEADER: &str = "Add-Padding";
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{domain::Password, utils::constants::test};
+
+    use super::*;
+
+    fn password_checker(base_url: String) -> HibpPasswordChecker {
+        let http_client = Client::builder()
+            .timeout(test::password_checker::TIMEOUT)
+            .build()
+            .unwrap();
+        HibpPasswordChecker::new(base_url, http_client)
+    }
+
+    fn password(s: &str) -> Password {
+        Password::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn breach_count_only_sends_the_hash_prefix() {
+        let mock_server = MockServer::start().await;
+        let password_checker = password_checker(mock_server.uri());
+
+        Mock::given(path("/range/5BAA6"))
+            .and(method("GET"))
+            .and(header(ADD_PADDING_HEADER, "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "0018A45C4D1DEF81644B54AB7F969B88D65:0\r\n\
+                 1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let count = password_checker
+            .breach_count(&password("password"))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 10434004);
+    }
+
+    #[tokio::test]
+    async fn breach_count_is_zero_if_the_suffix_is_not_in_the_range() {
+        let mock_server = MockServer::start().await;
+        let password_checker = password_checker(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("0018A45C4D1DEF81644B54AB7F969B88D65:3\r\n"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let count = password_checker
+            .breach_count(&password("password"))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn breach_count_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let password_checker = password_checker(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = password_checker.breach_count(&password("password")).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn breach_count_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let password_checker = password_checker(mock_server.uri());
+
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = password_checker.breach_count(&password("password")).await;
+
+        assert!(outcome.is_err());
+    }
+}
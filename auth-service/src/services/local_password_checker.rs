@@ -0,0 +1,118 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::{BreachedPasswordChecker, HASH_PREFIX_LENGTH};
+
+// Looks passwords up in a local copy of the Pwned Passwords dataset, for offline deployments.
+// The directory holds one `<PREFIX>.txt` range file per hash prefix, as written by the
+// Pwned Passwords downloader. A missing range file means no breached hash has that prefix.
+pub struct LocalPasswordChecker {
+    directory: PathBuf,
+}
+
+impl LocalPasswordChecker {
+    pub fn new(directory: PathBuf) -> Result<Self> {
+        if !directory.is_dir() {
+            return Err(eyre!("{} is not a directory", directory.display()));
+        }
+
+        Ok(Self { directory })
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for LocalPasswordChecker {
+    #[tracing::instrument(name = "Reading breached password range", skip_all)]
+    async fn get_range(&self, prefix: &str) -> Result<String> {
+        // The prefix ends up in a path, so anything but a hash prefix is refused
+        if prefix.len() != HASH_PREFIX_LENGTH || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(eyre!("{} is not a valid hash prefix", prefix));
+        }
+
+        let path = self
+            .directory
+            .join(format!("{}.txt", prefix.to_ascii_uppercase()));
+
+        match tokio::fs::read_to_string(path).await {
+            Ok(range) => Ok(range),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use crate::domain::Password;
+
+    use super::*;
+
+    fn password(s: &str) -> Password {
+        Password::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    fn range_directory() -> PathBuf {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir(&directory).unwrap();
+        directory
+    }
+
+    #[tokio::test]
+    async fn breach_count_is_read_from_the_range_file() {
+        let directory = range_directory();
+        std::fs::write(
+            directory.join("5BAA6.txt"),
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+             1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n",
+        )
+        .unwrap();
+        let password_checker = LocalPasswordChecker::new(directory.clone()).unwrap();
+
+        let count = password_checker
+            .breach_count(&password("password"))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 10434004);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn breach_count_is_zero_without_range_file() {
+        let directory = range_directory();
+        let password_checker = LocalPasswordChecker::new(directory.clone()).unwrap();
+
+        let count = password_checker
+            .breach_count(&password("password"))
+            .await
+            .unwrap();
+
+        assert_eq!(count, 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_prefixes_are_rejected() {
+        let directory = range_directory();
+        let password_checker = LocalPasswordChecker::new(directory.clone()).unwrap();
+
+        for prefix in ["", "5BAA", "5BAA61", "../5B", "5BAAG"] {
+            assert!(
+                password_checker.get_range(prefix).await.is_err(),
+                "Failed for prefix: {:?}",
+                prefix
+            );
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_directory_is_rejected() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        assert!(LocalPasswordChecker::new(directory).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use crate::domain::{PhoneNumber, SmsClient};
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+
+// Only logs that a text message would have been sent. Messages carry verification codes, so
+// neither they nor the full phone number end up in the logs.
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()> {
+        tracing::debug!(
+            "Sending SMS of {} characters to {}",
+            content.chars().count(),
+            redact_phone_number(recipient)
+        );
+
+        Ok(())
+    }
+}
+
+// Keeps the last two digits, enough to tell numbers apart while debugging
+fn redact_phone_number(phone_number: &PhoneNumber) -> String {
+    let digits = phone_number.as_ref().expose_secret();
+    let visible = digits.len().saturating_sub(2);
+    format!("{}{}", "*".repeat(visible), &digits[visible..])
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[test]
+    fn phone_numbers_are_redacted_to_their_last_two_digits() {
+        let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_owned())).unwrap();
+
+        assert_eq!(redact_phone_number(&phone_number), "**********78");
+    }
+}
@@ -1,3 +1,14 @@
+pub mod capture_email_client;
 pub mod data_stores;
+pub mod email_worker;
+pub mod expired_entries_purger;
+pub mod hashing_executor;
+pub mod hibp_password_checker;
+pub mod local_password_checker;
 pub mod mock_email_client;
-pub mod postmark_email_client;
\ No newline at end of file
+pub mod mock_sms_client;
+pub mod noop_password_checker;
+pub mod postmark_email_client;
+pub mod smtp_email_client;
+pub mod twilio_sms_client;
+pub mod webhook_dispatcher;
@@ -2,7 +2,7 @@ use color_eyre::eyre::Result; // For improved error handling and reporting
 use reqwest::{Client, Url}; // For making HTTP requests
 use secrecy::{ExposeSecret, Secret}; // For securely handling sensitive data
 
-use crate::domain::{Email, EmailClient}; // Import domain-specific modules
+use crate::domain::{Email, EmailClient, EmailMessage}; // Import domain-specific modules
 
 // Define the PostmarkEmailClient struct
 pub struct PostmarkEmailClient {
@@ -32,7 +32,7 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
@@ -41,9 +41,9 @@ impl EmailClient for PostmarkEmailClient {
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
@@ -94,14 +94,14 @@ mod tests {
 
     use super::PostmarkEmailClient;
 
-    // Helper function to generate a test subject
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
-
-    // Helper function to generate test content
-    fn content() -> String {
-        Paragraph(1..10).fake()
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        let content: String = Paragraph(1..10).fake();
+        EmailMessage {
+            subject: Sentence(1..2).fake(),
+            html_body: format!("<p>{}</p>", content),
+            text_body: content,
+        }
     }
 
     // Helper function to generate a test email
@@ -155,9 +155,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_ok());
     }
@@ -176,9 +174,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -198,9 +194,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -0,0 +1,309 @@
+use color_eyre::eyre::Result;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{Email, EmailClient, EmailMessage},
+    utils::smtp::{SmtpConfig, SmtpTls},
+};
+
+// Sends emails through an SMTP relay, as a multipart message with a plaintext and an HTML part
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Email,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: SmtpConfig, sender: Email) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+
+        let mut builder = builder.port(config.port).timeout(Some(config.timeout));
+
+        if let Some((username, password)) = config.credentials {
+            builder = builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let from: Mailbox = self.sender.as_ref().expose_secret().parse()?;
+        let to: Mailbox = recipient.as_ref().expose_secret().parse()?;
+
+        let email = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(&message.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.clone(),
+                message.html_body.clone(),
+            ))?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use secrecy::Secret;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        sync::Mutex,
+    };
+
+    use crate::utils::constants::test;
+
+    use super::*;
+
+    // "\0smtp-user\0smtp-password", as sent by AUTH PLAIN
+    const AUTH_PLAIN_CREDENTIALS: &str = "AHNtdHAtdXNlcgBzbXRwLXBhc3N3b3Jk";
+
+    #[derive(Debug, Default, Clone)]
+    struct ReceivedEmail {
+        auth: Option<String>,
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    // A minimal in-process SMTP server recording the emails it receives
+    struct FakeSmtpServer {
+        port: u16,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeSmtpServer {
+        async fn start(advertise_starttls: bool) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let commands = Arc::new(Mutex::new(Vec::new()));
+
+            let (received_clone, commands_clone) = (received.clone(), commands.clone());
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_connection(
+                        stream,
+                        advertise_starttls,
+                        received_clone.clone(),
+                        commands_clone.clone(),
+                    ));
+                }
+            });
+
+            Self {
+                port,
+                received,
+                commands,
+            }
+        }
+
+        fn config(&self, tls: SmtpTls) -> SmtpConfig {
+            SmtpConfig {
+                host: "127.0.0.1".to_owned(),
+                port: self.port,
+                tls,
+                credentials: Some((
+                    "smtp-user".to_owned(),
+                    Secret::new("smtp-password".to_owned()),
+                )),
+                timeout: test::email_client::TIMEOUT,
+            }
+        }
+    }
+
+    async fn handle_connection(
+        stream: TcpStream,
+        advertise_starttls: bool,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+        commands: Arc<Mutex<Vec<String>>>,
+    ) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut email = ReceivedEmail::default();
+
+        writer.write_all(b"220 localhost ESMTP fake\r\n").await?;
+
+        while let Some(line) = lines.next_line().await? {
+            commands.lock().await.push(line.clone());
+            let command = line.to_uppercase();
+
+            let reply = if command.starts_with("EHLO") {
+                let starttls = if advertise_starttls {
+                    "250-STARTTLS\r\n"
+                } else {
+                    ""
+                };
+                format!("250-localhost\r\n{}250 AUTH PLAIN LOGIN\r\n", starttls)
+            } else if command.starts_with("STARTTLS") {
+                "454 TLS not available\r\n".to_owned()
+            } else if command.starts_with("AUTH PLAIN") {
+                email.auth = line.split_whitespace().nth(2).map(str::to_owned);
+                "235 Authentication successful\r\n".to_owned()
+            } else if command.starts_with("MAIL FROM:") {
+                email.from = first_argument(&line["MAIL FROM:".len()..]);
+                "250 OK\r\n".to_owned()
+            } else if command.starts_with("RCPT TO:") {
+                email.to.push(first_argument(&line["RCPT TO:".len()..]));
+                "250 OK\r\n".to_owned()
+            } else if command == "DATA" {
+                writer
+                    .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                    .await?;
+                while let Some(data_line) = lines.next_line().await? {
+                    if data_line == "." {
+                        break;
+                    }
+                    email.data.push_str(&data_line);
+                    email.data.push('\n');
+                }
+                received.lock().await.push(std::mem::take(&mut email));
+                "250 OK\r\n".to_owned()
+            } else if command == "QUIT" {
+                writer.write_all(b"221 Bye\r\n").await?;
+                return Ok(());
+            } else {
+                "250 OK\r\n".to_owned()
+            };
+
+            writer.write_all(reply.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+
+    // Strips the optional ESMTP parameters following the address of MAIL FROM and RCPT TO
+    fn first_argument(arguments: &str) -> String {
+        arguments
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Test subject".to_owned(),
+            html_body: "<p>HTML body</p>".to_owned(),
+            text_body: "Text body".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_multipart_message() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client =
+            SmtpEmailClient::new(server.config(SmtpTls::None), email("sender@email.com")).unwrap();
+
+        let outcome = email_client
+            .send_email(&email("recipient@email.com"), &message())
+            .await;
+
+        assert!(outcome.is_ok());
+
+        let received = server.received.lock().await;
+        assert_eq!(received.len(), 1);
+
+        let email = &received[0];
+        assert_eq!(email.auth.as_deref(), Some(AUTH_PLAIN_CREDENTIALS));
+        assert_eq!(email.from, "<sender@email.com>");
+        assert_eq!(email.to, vec!["<recipient@email.com>".to_owned()]);
+        assert!(email.data.contains("Subject: Test subject"));
+        assert!(email.data.contains("multipart/alternative"));
+        assert!(email.data.contains("Text body"));
+        assert!(email.data.contains("<p>HTML body</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_starttls_is_not_supported() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client =
+            SmtpEmailClient::new(server.config(SmtpTls::StartTls), email("sender@email.com"))
+                .unwrap();
+
+        let outcome = email_client
+            .send_email(&email("recipient@email.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+        assert!(server.received.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_upgrades_connection_with_starttls() {
+        let server = FakeSmtpServer::start(true).await;
+        let email_client =
+            SmtpEmailClient::new(server.config(SmtpTls::StartTls), email("sender@email.com"))
+                .unwrap();
+
+        // The fake server can't complete the TLS handshake, but the client must try to
+        // upgrade before authenticating or sending anything
+        let outcome = email_client
+            .send_email(&email("recipient@email.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+
+        let commands = server.commands.lock().await;
+        assert!(commands.iter().any(|command| command == "STARTTLS"));
+        assert!(!commands.iter().any(|command| command.starts_with("AUTH")));
+        assert!(server.received.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        // Accepts connections but never greets the client
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let config = SmtpConfig {
+            host: "127.0.0.1".to_owned(),
+            port,
+            tls: SmtpTls::None,
+            credentials: None,
+            timeout: test::email_client::TIMEOUT,
+        };
+        let email_client = SmtpEmailClient::new(config, email("sender@email.com")).unwrap();
+
+        let outcome = email_client
+            .send_email(&email("recipient@email.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use color_eyre::eyre::{Report, Result};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::Instrument;
+
+use crate::{
+    app_state::OutboxStoreType,
+    domain::WebhookDelivery,
+    utils::{
+        backoff::exponential_backoff,
+        constants::{
+            WEBHOOK_ID_HEADER_NAME, WEBHOOK_SIGNATURE_HEADER_NAME, WEBHOOK_TIMESTAMP_HEADER_NAME,
+        },
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcherConfig {
+    // How long to wait between two dispatch rounds
+    pub poll_interval: Duration,
+    // Maximum number of deliveries attempted per round
+    pub batch_size: i64,
+    // Maximum number of deliveries sent at the same time
+    pub concurrency: usize,
+    // How long a claimed delivery stays hidden from other dispatchers. It must outlast a round,
+    // which takes at most `batch_size / concurrency` requests in a row.
+    pub lease: Duration,
+    // Deliveries failing this many times are dead-lettered
+    pub max_attempts: i32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for WebhookDispatcherConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            batch_size: 50,
+            concurrency: 10,
+            lease: Duration::from_secs(60),
+            max_attempts: 8,
+            base_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl WebhookDispatcherConfig {
+    // Delay before retrying a delivery that failed `attempts` times
+    pub fn backoff(&self, attempts: i32) -> Duration {
+        exponential_backoff(self.base_backoff, self.max_backoff, attempts)
+    }
+}
+
+// Delivers the domain events of the outbox to the registered webhook endpoints
+pub struct WebhookDispatcher {
+    outbox_store: OutboxStoreType,
+    http_client: Client,
+    config: WebhookDispatcherConfig,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        outbox_store: OutboxStoreType,
+        http_client: Client,
+        config: WebhookDispatcherConfig,
+    ) -> Self {
+        Self {
+            outbox_store,
+            http_client,
+            config,
+        }
+    }
+
+    // Dispatches forever, meant to be spawned as a background task
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                tracing::error!(error = ?e, "webhook dispatch round failed");
+            }
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    // Runs a single dispatch round and returns the number of deliveries attempted
+    #[tracing::instrument(name = "Dispatching webhooks", skip_all)]
+    pub async fn run_once(&self) -> Result<usize> {
+        self.outbox_store.fan_out_events().await?;
+
+        let deliveries = self
+            .outbox_store
+            .claim_due_deliveries(self.config.batch_size, self.config.lease)
+            .await?;
+        let attempted = deliveries.len();
+
+        // Sent concurrently, so a slow endpoint doesn't keep the rest of the batch past its lease
+        let permits = Arc::new(Semaphore::new(self.config.concurrency));
+        let mut sends = JoinSet::new();
+        for delivery in deliveries {
+            let http_client = self.http_client.clone();
+            let permits = permits.clone();
+            sends.spawn(
+                async move {
+                    let _permit = permits
+                        .acquire_owned()
+                        .await
+                        .expect("The semaphore is never closed");
+                    let outcome = send_webhook(&http_client, &delivery).await;
+                    (delivery, outcome)
+                }
+                .in_current_span(),
+            );
+        }
+
+        while let Some(sent) = sends.join_next().await {
+            let (delivery, outcome) = sent?;
+            match outcome {
+                Ok(()) => self.outbox_store.mark_delivered(delivery.id).await?,
+                Err(e) => self.record_failure(&delivery, e).await?,
+            }
+        }
+
+        Ok(attempted)
+    }
+
+    async fn record_failure(&self, delivery: &WebhookDelivery, error: Report) -> Result<()> {
+        let attempts = delivery.attempts + 1;
+        let next_attempt_at = if attempts >= self.config.max_attempts {
+            tracing::warn!(
+                delivery_id = %delivery.id,
+                attempts,
+                "webhook delivery dead-lettered"
+            );
+            None
+        } else {
+            let delay = chrono::Duration::from_std(self.config.backoff(attempts))?;
+            Some(Utc::now() + delay)
+        };
+
+        self.outbox_store
+            .mark_failed(delivery.id, format!("{:#}", error), next_attempt_at)
+            .await?;
+
+        Ok(())
+    }
+}
+
+// POSTs the event to the endpoint. Any non-2xx response counts as a failure.
+#[tracing::instrument(name = "Sending webhook", skip_all, fields(delivery_id = %delivery.id))]
+pub async fn send_webhook(http_client: &Client, delivery: &WebhookDelivery) -> Result<()> {
+    let body = serde_json::to_vec(&delivery.event)?;
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_webhook(&delivery.endpoint_secret, timestamp, &body);
+
+    http_client
+        .post(&delivery.endpoint_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_ID_HEADER_NAME, delivery.event.id.to_string())
+        .header(WEBHOOK_TIMESTAMP_HEADER_NAME, timestamp.to_string())
+        .header(WEBHOOK_SIGNATURE_HEADER_NAME, signature)
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+// Signs `<timestamp>.<body>` with HMAC-SHA256 using the endpoint secret.
+// Receivers recompute it to authenticate the request; the timestamp lets them reject replays.
+pub fn sign_webhook(secret: &Secret<String>, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::domain::{DomainEvent, DomainEventKind};
+
+    use super::*;
+
+    fn delivery(endpoint_url: String) -> WebhookDelivery {
+        WebhookDelivery {
+            id: Uuid::new_v4(),
+            endpoint_url,
+            endpoint_secret: Secret::new("secret".to_owned()),
+            attempts: 0,
+            event: DomainEvent::new(
+                DomainEventKind::UserSignedUp,
+                serde_json::json!({ "email": "test@email.com" }),
+            ),
+        }
+    }
+
+    #[test]
+    fn signature_depends_on_secret_timestamp_and_body() {
+        let secret = Secret::new("secret".to_owned());
+        let signature = sign_webhook(&secret, 1, b"body");
+
+        assert!(signature.starts_with("v1="));
+        assert_eq!(signature, sign_webhook(&secret, 1, b"body"));
+        assert_ne!(signature, sign_webhook(&secret, 2, b"body"));
+        assert_ne!(signature, sign_webhook(&secret, 1, b"other body"));
+        assert_ne!(
+            signature,
+            sign_webhook(&Secret::new("other".to_owned()), 1, b"body")
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let config = WebhookDispatcherConfig {
+            base_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(60),
+            ..Default::default()
+        };
+
+        assert_eq!(config.backoff(1), Duration::from_secs(10));
+        assert_eq!(config.backoff(2), Duration::from_secs(20));
+        assert_eq!(config.backoff(3), Duration::from_secs(40));
+        assert_eq!(config.backoff(4), Duration::from_secs(60));
+        assert_eq!(config.backoff(100), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn send_webhook_posts_signed_event() {
+        let mock_server = MockServer::start().await;
+        let delivery = delivery(format!("{}/webhook", mock_server.uri()));
+
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .and(header("Content-Type", "application/json"))
+            .and(header(
+                WEBHOOK_ID_HEADER_NAME,
+                delivery.event.id.to_string().as_str(),
+            ))
+            .and(header_exists(WEBHOOK_TIMESTAMP_HEADER_NAME))
+            .and(header_exists(WEBHOOK_SIGNATURE_HEADER_NAME))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = send_webhook(&Client::new(), &delivery).await;
+        assert!(outcome.is_ok());
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let timestamp: i64 = request.headers[WEBHOOK_TIMESTAMP_HEADER_NAME]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            request.headers[WEBHOOK_SIGNATURE_HEADER_NAME],
+            sign_webhook(&delivery.endpoint_secret, timestamp, &request.body).as_str()
+        );
+
+        let event: DomainEvent = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(event, delivery.event);
+    }
+
+    #[tokio::test]
+    async fn send_webhook_fails_if_endpoint_returns_error() {
+        let mock_server = MockServer::start().await;
+        let delivery = delivery(mock_server.uri());
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = send_webhook(&Client::new(), &delivery).await;
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{
+    app_state::AuditLogType,
+    domain::{AuditEvent, AuditEventKind, RequestMetadata},
+};
+
+// Records an audit event. Failing to record it is logged but never fails the request.
+#[tracing::instrument(name = "Record audit event", skip_all, fields(kind = %kind))]
+pub async fn record_audit_event(
+    audit_log: &AuditLogType,
+    kind: AuditEventKind,
+    email: Option<String>,
+    metadata: &RequestMetadata,
+) {
+    let event = AuditEvent::new(kind, email, metadata);
+
+    if let Err(e) = audit_log.record(event).await {
+        tracing::error!(error = ?e, "failed to record audit event");
+    }
+}
@@ -1,31 +1,142 @@
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use rand::{distributions::Alphanumeric, Rng};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use time::Duration;
+use uuid::Uuid;
 
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::{AppState, BannedTokenStoreType},
+    domain::{AuthAPIError, Email, TwoFACode, User, UserId, UserStoreError},
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::constants::{
+    ADMIN_API_KEY, AUTH_COOKIE_CONFIG, CSRF_COOKIE_NAME, HOST_PREFIXED_JWT_COOKIE_NAME,
+    INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_COOKIE_NAME, JWT_SECRET,
+};
 
 #[tracing::instrument(name = "Generate auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token))
+pub fn generate_auth_cookie(user: &User, session_id: &str) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user, session_id)?;
+    Ok(create_auth_cookie(token, &AUTH_COOKIE_CONFIG))
 }
 
 #[tracing::instrument(name = "Create auth cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/") // apple cookie to all URLs on the server
-        .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+fn create_auth_cookie(token: String, config: &AuthCookieConfig) -> Cookie<'static> {
+    let mut cookie = Cookie::build((config.name(), token))
+        .path(config.path.clone()) // apply cookie to all URLs under this path
+        .http_only(config.http_only) // prevent JavaScript from accessing the cookie
+        .same_site(config.same_site) // by default, send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+        .secure(config.secure) // only send the cookie over HTTPS
+        .max_age(Duration::seconds(config.max_age_seconds)) // expire the cookie together with the token
         .build();
 
+    if let Some(domain) = &config.domain {
+        cookie.set_domain(domain.clone());
+    }
+
     cookie
 }
 
+// Builds a cookie with the same attributes as the auth cookie, so that removing it
+// matches (and clears) the cookie held by the browser
+#[tracing::instrument(name = "Create auth removal cookie", skip_all)]
+pub fn create_auth_removal_cookie() -> Cookie<'static> {
+    create_auth_cookie(String::new(), &AUTH_COOKIE_CONFIG)
+}
+
+#[tracing::instrument(name = "Generate CSRF cookie", skip_all)]
+pub fn generate_csrf_cookie() -> Cookie<'static> {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(CSRF_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+
+    create_csrf_cookie(token, &AUTH_COOKIE_CONFIG)
+}
+
+// The CSRF cookie shares the scope of the auth cookie but must stay readable by client scripts
+fn create_csrf_cookie(token: String, config: &AuthCookieConfig) -> Cookie<'static> {
+    let mut cookie = Cookie::build((CSRF_COOKIE_NAME, token))
+        .path(config.path.clone())
+        .http_only(false) // the client script must read this cookie to echo it back in a header
+        .same_site(config.same_site)
+        .secure(config.secure)
+        .max_age(Duration::seconds(config.max_age_seconds))
+        .build();
+
+    if let Some(domain) = &config.domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    cookie
+}
+
+#[tracing::instrument(name = "Create CSRF removal cookie", skip_all)]
+pub fn create_csrf_removal_cookie() -> Cookie<'static> {
+    create_csrf_cookie(String::new(), &AUTH_COOKIE_CONFIG)
+}
+
+// Attributes of the auth cookie. Defaults keep the cookie usable over plain HTTP in development;
+// production deployments should at least enable `secure`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthCookieConfig {
+    pub path: String,
+    pub domain: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    pub max_age_seconds: i64,
+    // Use the `__Host-` name prefix, which makes browsers reject the cookie
+    // unless it is `Secure`, has `Path=/` and no `Domain`
+    pub host_prefix: bool,
+}
+
+impl Default for AuthCookieConfig {
+    fn default() -> Self {
+        Self {
+            path: "/".to_owned(),
+            domain: None,
+            secure: false,
+            http_only: true,
+            same_site: SameSite::Lax,
+            max_age_seconds: TOKEN_TTL_SECONDS,
+            host_prefix: false,
+        }
+    }
+}
+
+impl AuthCookieConfig {
+    // Adjusts attributes that browsers would otherwise reject the cookie for
+    pub fn normalized(mut self) -> Self {
+        if self.host_prefix {
+            self.secure = true;
+            self.path = "/".to_owned();
+            self.domain = None;
+        }
+        if self.same_site == SameSite::None {
+            self.secure = true;
+        }
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.host_prefix {
+            HOST_PREFIXED_JWT_COOKIE_NAME
+        } else {
+            JWT_COOKIE_NAME
+        }
+    }
+}
+
+const CSRF_TOKEN_LENGTH: usize = 32;
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
@@ -35,12 +146,36 @@ pub enum GenerateTokenError {
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
+// Links in new device emails may be followed long after the login. Using one revokes every
+// token of the user, the link included, so it outlasts its ban in the banned token store.
+pub const LOGIN_REPORT_TOKEN_TTL_SECONDS: i64 = 24 * 60 * 60; // 1 day
+
+// Password reset tokens are banned once used. Banned tokens are only remembered for
+// TOKEN_TTL_SECONDS, so reset tokens must not outlive that to stay single-use.
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = TOKEN_TTL_SECONDS;
+
+// Confirmation tokens can't be used twice either: confirming revokes every token of the user,
+// the confirmation token included
+pub const EMAIL_CHANGE_TOKEN_TTL_SECONDS: i64 = TOKEN_TTL_SECONDS;
+
+// Scope and roles granted to every token issued by the login flow
+pub const DEFAULT_TOKEN_SCOPE: &str = "profile";
+pub const DEFAULT_TOKEN_ROLES: [&str; 1] = ["user"];
+
+// Identifies the session started by a login. It is embedded in the auth token as `jti`,
+// so that a single session can be revoked without knowing its token.
+pub fn generate_session_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
 #[tracing::instrument(name = "Generate auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+fn generate_auth_token(user: &User, session_id: &str) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -50,9 +185,20 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_string();
+    let iat: usize = now.timestamp().try_into().wrap_err(format!(
+        "failed to cast iat time to usize. iat time: {}",
+        now.timestamp()
+    ))?;
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub: user.id.to_string(),
+        exp,
+        iat,
+        scope: DEFAULT_TOKEN_SCOPE.to_owned(),
+        roles: DEFAULT_TOKEN_ROLES.iter().map(|r| r.to_string()).collect(),
+        jti: session_id.to_owned(),
+        ver: user.token_version,
+    };
 
     create_token(&claims)
 }
@@ -63,8 +209,6 @@ pub async fn validate_token(
     banned_token_store: BannedTokenStoreType,
 ) -> Result<Claims> {
     match banned_token_store
-        .read()
-        .await
         .contains_token(&Secret::new(token.to_string()))
         .await
     {
@@ -76,13 +220,174 @@ pub async fn validate_token(
         Err(e) => return Err(e.into()),
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode token")?;
+
+    if banned_token_store
+        .contains_token(&revoked_session_key(&claims.jti))
+        .await?
+    {
+        return Err(eyre!("session is revoked"));
+    }
+
+    Ok(claims)
+}
+
+// Revokes every auth token issued for the session, for as long as such tokens can be valid
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn revoke_session(
+    session_id: &str,
+    banned_token_store: &BannedTokenStoreType,
+) -> Result<()> {
+    banned_token_store
+        .add_token(revoked_session_key(session_id))
+        .await?;
+    Ok(())
+}
+
+fn revoked_session_key(session_id: &str) -> Secret<String> {
+    Secret::new(format!("session:{}", session_id))
+}
+
+// Generates a token that authorizes a single kind of action on an account, e.g. a password reset.
+// Action tokens can't be used as auth tokens and vice versa, as their claims differ.
+#[tracing::instrument(name = "Generate action token", skip_all)]
+pub fn generate_action_token(
+    user: &User,
+    purpose: TokenPurpose,
+    ttl_seconds: i64,
+    session_id: Option<String>,
+) -> Result<String> {
+    let mut claims = action_claims(user, purpose, ttl_seconds)?;
+    claims.sid = session_id;
+    create_action_token(&claims)
+}
+
+// Generates the tokens of the links sent when a user asks to change their email to `new_email`:
+// the confirmation link sent to the new address and the cancel link sent to the current one.
+#[tracing::instrument(name = "Generate email change token", skip_all)]
+pub fn generate_email_change_token(
+    user: &User,
+    purpose: TokenPurpose,
+    new_email: &Email,
+) -> Result<String> {
+    let mut claims = action_claims(user, purpose, EMAIL_CHANGE_TOKEN_TTL_SECONDS)?;
+    claims.new_email = Some(new_email.as_ref().expose_secret().to_owned());
+    create_action_token(&claims)
+}
+
+fn action_claims(user: &User, purpose: TokenPurpose, ttl_seconds: i64) -> Result<ActionClaims> {
+    let now = Utc::now();
+    let delta =
+        chrono::Duration::try_seconds(ttl_seconds).wrap_err("failed to create time delta")?;
+    let exp = now
+        .checked_add_signed(delta)
+        .ok_or(eyre!("failed to add time delta to current time"))?
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast exp time to usize")?;
+    let iat = now
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+
+    Ok(ActionClaims {
+        sub: user.id.to_string(),
+        exp,
+        iat,
+        purpose,
+        sid: None,
+        new_email: None,
+        ver: user.token_version,
+    })
+}
+
+fn create_action_token(claims: &ActionClaims) -> Result<String> {
+    encode(
+        &jsonwebtoken::Header::default(),
+        claims,
+        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+    )
+    .wrap_err("failed to create action token")
+}
+
+#[tracing::instrument(name = "Validate action token", skip_all)]
+pub async fn validate_action_token(
+    token: &str,
+    purpose: TokenPurpose,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<ActionClaims> {
+    if banned_token_store
+        .contains_token(&Secret::new(token.to_string()))
+        .await?
+    {
+        return Err(eyre!("token is banned"));
+    }
+
+    let claims = decode::<ActionClaims>(
         token,
         &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .wrap_err("failed to decode action token")?;
+
+    if claims.purpose != purpose {
+        return Err(eyre!("action token has the wrong purpose"));
+    }
+
+    Ok(claims)
+}
+
+// Looks up the user a token was issued for, by the user id in its `sub` claim.
+// Tokens of deleted users are rejected, as are tokens issued before the user's tokens were
+// revoked, which the `ver` claim tells.
+#[tracing::instrument(name = "Get token user", skip_all)]
+pub async fn get_token_user(state: &AppState, sub: &str, ver: i32) -> Result<User, AuthAPIError> {
+    let user_id = UserId::parse(sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = state
+        .user_store
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    if user.token_version != ver {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    Ok(user)
+}
+
+#[tracing::instrument(name = "Validate client credentials", skip_all)]
+pub fn validate_client_credentials(client_id: &str, client_secret: &str) -> bool {
+    // Evaluate both comparisons so the response time does not reveal which one failed
+    let id_matches = constant_time_eq(client_id.as_bytes(), INTROSPECTION_CLIENT_ID.as_bytes());
+    let secret_matches = constant_time_eq(
+        client_secret.as_bytes(),
+        INTROSPECTION_CLIENT_SECRET.expose_secret().as_bytes(),
+    );
+    id_matches & secret_matches
+}
+
+#[tracing::instrument(name = "Validate admin API key", skip_all)]
+pub fn validate_admin_api_key(api_key: &str) -> bool {
+    constant_time_eq(api_key.as_bytes(), ADMIN_API_KEY.expose_secret().as_bytes())
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 #[tracing::instrument(name = "Create token", skip_all)]
@@ -95,10 +400,55 @@ fn create_token(claims: &Claims) -> Result<String> {
     .wrap_err("failed to create token")
 }
 
+// Verification codes have too few digits for a plain hash to hide them, so they are stored as
+// an HMAC keyed with the JWT secret, which the database does not hold
+pub fn hash_verification_code(code: &TwoFACode) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(JWT_SECRET.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(code.as_ref().expose_secret().as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    // The user id
+    pub sub: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub scope: String,
+    pub roles: Vec<String>,
+    // The session id
+    pub jti: String,
+    // The token version of the user when the token was issued
+    #[serde(default)]
+    pub ver: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPurpose {
+    LoginReport,
+    PasswordReset,
+    EmailChange,
+    EmailChangeCancel,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActionClaims {
+    // The user id
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    pub purpose: TokenPurpose,
+    // The session the action refers to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // The requested address of an email change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_email: Option<String>,
+    // The token version of the user when the token was issued
+    #[serde(default)]
+    pub ver: i32,
 }
 
 #[cfg(test)]
@@ -106,19 +456,26 @@ mod tests {
     use std::sync::Arc;
 
     use secrecy::Secret;
-    use tokio::sync::RwLock;
 
     use crate::{
-        domain::BannedTokenStore,
+        domain::{BannedTokenStore, Password},
         services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore,
     };
 
     use super::*;
 
+    fn user() -> User {
+        User::new(
+            Email::parse(Secret::new("test@email.com".to_owned())).unwrap(),
+            Password::parse(Secret::new("password123".to_owned())).unwrap(),
+            false,
+        )
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let user = user();
+        let cookie = generate_auth_cookie(&user, &generate_session_id()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -129,28 +486,87 @@ mod tests {
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
-        let cookie = create_auth_cookie(token.clone());
+        let cookie = create_auth_cookie(token.clone(), &AuthCookieConfig::default());
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), Some(Duration::seconds(TOKEN_TTL_SECONDS)));
+        assert_eq!(cookie.domain(), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_auth_cookie_with_custom_config() {
+        let config = AuthCookieConfig {
+            path: "/auth".to_owned(),
+            domain: Some("example.com".to_owned()),
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Strict,
+            max_age_seconds: 60,
+            host_prefix: false,
+        };
+        let cookie = create_auth_cookie("test_token".to_owned(), &config);
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/auth"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.max_age(), Some(Duration::seconds(60)));
+    }
+
+    #[tokio::test]
+    async fn test_host_prefix_forces_secure_root_path_and_no_domain() {
+        let config = AuthCookieConfig {
+            path: "/auth".to_owned(),
+            domain: Some("example.com".to_owned()),
+            secure: false,
+            host_prefix: true,
+            ..AuthCookieConfig::default()
+        }
+        .normalized();
+        let cookie = create_auth_cookie("test_token".to_owned(), &config);
+        assert_eq!(cookie.name(), HOST_PREFIXED_JWT_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(cookie.secure(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_same_site_none_forces_secure() {
+        let config = AuthCookieConfig {
+            same_site: SameSite::None,
+            ..AuthCookieConfig::default()
+        }
+        .normalized();
+        assert!(config.secure);
+    }
+
+    #[test]
+    fn test_generate_csrf_cookie() {
+        let cookie = generate_csrf_cookie();
+        assert_eq!(cookie.name(), CSRF_COOKIE_NAME);
+        assert_eq!(cookie.value().len(), CSRF_TOKEN_LENGTH);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(false));
+        assert_ne!(cookie.value(), generate_csrf_cookie().value());
     }
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let user = user();
+        let result = generate_auth_token(&user, &generate_session_id()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user = user();
+        let token = generate_auth_token(&user, &generate_session_id()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
         let result = validate_token(&token, banned_token_store).await.unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.sub, user.id.to_string());
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -158,24 +574,122 @@ mod tests {
             .timestamp();
 
         assert!(result.exp > exp as usize);
+        assert!(result.iat <= Utc::now().timestamp() as usize);
+        assert_eq!(result.scope, DEFAULT_TOKEN_SCOPE);
+        assert_eq!(result.roles, vec!["user".to_owned()]);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret1"));
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::default());
         let result = validate_token(&token, banned_token_store).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let mut hs = HashsetBannedTokenStore::default();
+        let user = user();
+        let token = generate_auth_token(&user, &generate_session_id()).unwrap();
+        let hs = HashsetBannedTokenStore::default();
         hs.add_token(Secret::new(token.clone())).await.unwrap();
-        let banned_token_store = Arc::new(RwLock::new(hs));
+        let banned_token_store = Arc::new(hs);
         let result = validate_token(&token, banned_token_store).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let user = user();
+        let session_id = generate_session_id();
+        let token = generate_auth_token(&user, &session_id).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        let claims = validate_token(&token, banned_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(claims.jti, session_id);
+
+        revoke_session(&session_id, &banned_token_store)
+            .await
+            .unwrap();
+        let result = validate_token(&token, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_action_token() {
+        let user = user();
+        let token = generate_action_token(
+            &user,
+            TokenPurpose::LoginReport,
+            LOGIN_REPORT_TOKEN_TTL_SECONDS,
+            Some("session".to_owned()),
+        )
+        .unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        let claims = validate_action_token(
+            &token,
+            TokenPurpose::LoginReport,
+            banned_token_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.sub, user.id.to_string());
+        assert_eq!(claims.sid, Some("session".to_owned()));
+
+        let result =
+            validate_action_token(&token, TokenPurpose::PasswordReset, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_action_and_auth_tokens_are_not_interchangeable() {
+        let user = user();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        let action_token =
+            generate_action_token(&user, TokenPurpose::PasswordReset, 60, None).unwrap();
+        let result = validate_token(&action_token, banned_token_store.clone()).await;
+        assert!(result.is_err());
+
+        let auth_token = generate_auth_token(&user, &generate_session_id()).unwrap();
+        let result =
+            validate_action_token(&auth_token, TokenPurpose::PasswordReset, banned_token_store)
+                .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_email_change_token() {
+        let user = user();
+        let new_email = Email::parse(Secret::new("new@email.com".to_owned())).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        let token =
+            generate_email_change_token(&user, TokenPurpose::EmailChange, &new_email).unwrap();
+        let claims = validate_action_token(
+            &token,
+            TokenPurpose::EmailChange,
+            banned_token_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.sub, user.id.to_string());
+        assert_eq!(claims.new_email, Some("new@email.com".to_owned()));
+        assert_eq!(claims.ver, user.token_version);
+
+        let result =
+            validate_action_token(&token, TokenPurpose::EmailChangeCancel, banned_token_store)
+                .await;
+        assert!(result.is_err());
+    }
 }
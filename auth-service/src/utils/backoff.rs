@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+// Delay before retrying an operation that failed `attempts` times, doubling after each failure
+pub fn exponential_backoff(base: Duration, max: Duration, attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 31) as u32;
+    base.saturating_mul(2u32.saturating_pow(exponent)).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let base = Duration::from_secs(10);
+        let max = Duration::from_secs(60);
+
+        assert_eq!(exponential_backoff(base, max, 1), Duration::from_secs(10));
+        assert_eq!(exponential_backoff(base, max, 2), Duration::from_secs(20));
+        assert_eq!(exponential_backoff(base, max, 3), Duration::from_secs(40));
+        assert_eq!(exponential_backoff(base, max, 4), Duration::from_secs(60));
+        assert_eq!(exponential_backoff(base, max, 100), Duration::from_secs(60));
+    }
+}
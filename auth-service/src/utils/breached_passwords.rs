@@ -0,0 +1,115 @@
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventKind, Password, RequestMetadata, User},
+};
+
+use super::{
+    audit::record_audit_event,
+    auth::{generate_action_token, TokenPurpose, PASSWORD_RESET_TOKEN_TTL_SECONDS},
+    constants::AUTH_SERVICE_URL,
+    email_templates::{BreachedPasswordEmail, EmailTemplate, PasswordResetRequiredEmail},
+};
+
+// Checks the password against the breached password checker. The check fails open: when the
+// checker is unavailable the error is logged and the password is accepted, so an outage of the
+// dataset doesn't lock users out.
+#[tracing::instrument(name = "Check breached password", skip_all)]
+pub async fn is_password_breached(state: &AppState, password: &Password) -> bool {
+    match state
+        .breached_password_checker
+        .read()
+        .await
+        .breach_count(password)
+        .await
+    {
+        Ok(count) => count > 0,
+        Err(e) => {
+            tracing::warn!(error = ?e, "failed to check password against breaches");
+            false
+        }
+    }
+}
+
+// Forces the user to reset a breached password before logging in again, and emails them a
+// password reset link. Only the mailbox owner can reset it, as the password itself is known.
+// The flag is only set once the link is enqueued, so the user is never left without one.
+#[tracing::instrument(name = "Flag breached password", skip_all)]
+pub async fn flag_breached_password(
+    state: &AppState,
+    user: &User,
+    metadata: &RequestMetadata,
+) -> Result<()> {
+    let email = &user.email;
+
+    let message = BreachedPasswordEmail {
+        reset_link: &password_reset_link(user)?,
+    }
+    .render()?;
+
+    state.email_queue.enqueue(email, &message).await?;
+
+    // Starts the interval, so logging in again straight away doesn't send another link
+    state
+        .password_reset_rate_limiter
+        .try_acquire(&[&rate_limit_key(user)]);
+
+    state
+        .user_store
+        .set_password_reset_required(email, true)
+        .await?;
+
+    record_audit_event(
+        &state.audit_log,
+        AuditEventKind::BreachedPasswordDetected,
+        Some(email.as_ref().expose_secret().to_owned()),
+        metadata,
+    )
+    .await;
+
+    Ok(())
+}
+
+// Emails a fresh password reset link to a user whose login was refused until they reset their
+// password, as the link they got when it was flagged may have expired or gone missing. At most
+// one is sent per interval, the other refused logins send nothing.
+#[tracing::instrument(name = "Resend password reset link", skip_all)]
+pub async fn resend_password_reset_link(state: &AppState, user: &User) -> Result<()> {
+    if !state
+        .password_reset_rate_limiter
+        .try_acquire(&[&rate_limit_key(user)])
+    {
+        return Ok(());
+    }
+
+    let message = PasswordResetRequiredEmail {
+        reset_link: &password_reset_link(user)?,
+        expires_in_minutes: PASSWORD_RESET_TOKEN_TTL_SECONDS as u64 / 60,
+    }
+    .render()?;
+
+    state.email_queue.enqueue(&user.email, &message).await?;
+
+    Ok(())
+}
+
+fn password_reset_link(user: &User) -> Result<String> {
+    let reset_token = generate_action_token(
+        user,
+        TokenPurpose::PasswordReset,
+        PASSWORD_RESET_TOKEN_TTL_SECONDS,
+        None,
+    )?;
+
+    Ok(format!(
+        "{}/?reset={}",
+        AUTH_SERVICE_URL.as_str(),
+        reset_token
+    ))
+}
+
+fn rate_limit_key(user: &User) -> String {
+    format!("user:{}", user.id.as_ref())
+}
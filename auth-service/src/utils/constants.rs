@@ -1,7 +1,16 @@
+use axum_extra::extract::cookie::SameSite;
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use secrecy::Secret;
-use std::env as std_env;
+use std::{env as std_env, path::PathBuf};
+
+use crate::{domain::PasswordPolicy, services::hashing_executor::HashingExecutorConfig};
+
+use super::{
+    auth::AuthCookieConfig,
+    password_hashing::Argon2Config,
+    smtp::{SmtpConfig, SmtpTls},
+};
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
@@ -9,6 +18,75 @@ lazy_static! {
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref INTROSPECTION_CLIENT_ID: String = set_introspection_client_id();
+    pub static ref INTROSPECTION_CLIENT_SECRET: Secret<String> = set_introspection_client_secret();
+    pub static ref AUTH_COOKIE_CONFIG: AuthCookieConfig = set_auth_cookie_config();
+    pub static ref ADMIN_API_KEY: Secret<String> = set_admin_api_key();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref EMAIL_PROVIDER: EmailProvider = set_email_provider();
+    pub static ref SMTP_CONFIG: SmtpConfig = set_smtp_config();
+    pub static ref SMS_PROVIDER: SmsProvider = set_sms_provider();
+    pub static ref TOKEN_STORE_BACKEND: TokenStoreBackend = set_token_store_backend();
+    pub static ref USER_STORE_BACKEND: UserStoreBackend = set_user_store_backend();
+    pub static ref SQLITE_DATABASE_URL: String = set_sqlite_database_url();
+    pub static ref TWILIO_ACCOUNT_SID: String = set_twilio_account_sid();
+    pub static ref TWILIO_AUTH_TOKEN: Secret<String> = set_twilio_auth_token();
+    pub static ref TWILIO_FROM_NUMBER: String = set_twilio_from_number();
+    pub static ref BREACHED_PASSWORD_CHECKER: BreachedPasswordCheckerProvider =
+        set_breached_password_checker();
+    pub static ref PWNED_PASSWORDS_DIR: PathBuf = set_pwned_passwords_dir();
+    pub static ref CHECK_BREACHED_PASSWORDS_AT_LOGIN: bool =
+        parse_env_var(env::CHECK_BREACHED_PASSWORDS_AT_LOGIN_ENV_VAR, false);
+    pub static ref EMAIL_LOWERCASE_LOCAL_PART: bool =
+        parse_env_var(env::EMAIL_LOWERCASE_LOCAL_PART_ENV_VAR, true);
+    pub static ref PASSWORD_POLICY: PasswordPolicy = set_password_policy();
+    pub static ref ARGON2_CONFIG: Argon2Config = set_argon2_config();
+    pub static ref HASHING_EXECUTOR_CONFIG: HashingExecutorConfig = set_hashing_executor_config();
+}
+
+// The service used to send emails, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailProvider {
+    Postmark,
+    Smtp,
+    // Development only: emails are kept in memory and listed at `/dev/mailbox`
+    Capture,
+}
+
+// The service used to send text messages, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmsProvider {
+    Twilio,
+    // Development only: text messages are written to the logs
+    Log,
+}
+
+// Where 2FA codes and banned tokens are kept, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStoreBackend {
+    Redis,
+    // Expired entries are deleted by a background task
+    Postgres,
+    // Kept in the SQLite database of the user store, so it needs `USER_STORE_BACKEND=sqlite`.
+    // Expired entries are deleted by a background task.
+    Sqlite,
+}
+
+// Where users are kept, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserStoreBackend {
+    Postgres,
+    // Needs the `sqlite` feature. The database is read from `SQLITE_DATABASE_URL`.
+    Sqlite,
+}
+
+// Where breached password ranges are looked up, chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreachedPasswordCheckerProvider {
+    Hibp,
+    // A local copy of the dataset, read from `PWNED_PASSWORDS_DIR`
+    Local,
+    Disabled,
 }
 
 fn set_token() -> Secret<String> {
@@ -41,16 +119,347 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_introspection_client_id() -> String {
+    dotenv().ok();
+    std_env::var(env::INTROSPECTION_CLIENT_ID_ENV_VAR)
+        .unwrap_or(DEFAULT_INTROSPECTION_CLIENT_ID.to_owned())
+}
+
+fn set_introspection_client_secret() -> Secret<String> {
+    dotenv().ok();
+    let secret = std_env::var(env::INTROSPECTION_CLIENT_SECRET_ENV_VAR)
+        .expect("INTROSPECTION_CLIENT_SECRET must be set.");
+    if secret.is_empty() {
+        panic!("INTROSPECTION_CLIENT_SECRET must not be empty.");
+    }
+    Secret::new(secret)
+}
+
+fn set_admin_api_key() -> Secret<String> {
+    dotenv().ok();
+    let api_key = std_env::var(env::ADMIN_API_KEY_ENV_VAR).expect("ADMIN_API_KEY must be set.");
+    if api_key.is_empty() {
+        panic!("ADMIN_API_KEY must not be empty.");
+    }
+    Secret::new(api_key)
+}
+
+// The public base URL of this service, used to build links sent by email
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR)
+        .unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+fn set_email_provider() -> EmailProvider {
+    dotenv().ok();
+    match std_env::var(env::EMAIL_PROVIDER_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "postmark" => EmailProvider::Postmark,
+            "smtp" => EmailProvider::Smtp,
+            "capture" => EmailProvider::Capture,
+            _ => panic!("EMAIL_PROVIDER must be one of: postmark, smtp, capture."),
+        },
+        Err(_) => EmailProvider::Postmark,
+    }
+}
+
+fn set_smtp_config() -> SmtpConfig {
+    dotenv().ok();
+    let host = std_env::var(env::SMTP_HOST_ENV_VAR).expect("SMTP_HOST must be set.");
+    if host.is_empty() {
+        panic!("SMTP_HOST must not be empty.");
+    }
+
+    let tls = match std_env::var(env::SMTP_TLS_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "starttls" => SmtpTls::StartTls,
+            "none" => SmtpTls::None,
+            _ => panic!("SMTP_TLS must be one of: starttls, none."),
+        },
+        Err(_) => SmtpTls::StartTls,
+    };
+
+    let credentials = match std_env::var(env::SMTP_USERNAME_ENV_VAR) {
+        Ok(username) if !username.is_empty() => {
+            let password = std_env::var(env::SMTP_PASSWORD_ENV_VAR)
+                .expect("SMTP_PASSWORD must be set when SMTP_USERNAME is.");
+            Some((username, Secret::new(password)))
+        }
+        _ => None,
+    };
+
+    SmtpConfig {
+        host,
+        port: parse_env_var(env::SMTP_PORT_ENV_VAR, DEFAULT_SMTP_PORT),
+        tls,
+        credentials,
+        timeout: prod::email_client::TIMEOUT,
+    }
+}
+
+fn set_sms_provider() -> SmsProvider {
+    dotenv().ok();
+    match std_env::var(env::SMS_PROVIDER_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "twilio" => SmsProvider::Twilio,
+            "log" => SmsProvider::Log,
+            _ => panic!("SMS_PROVIDER must be one of: twilio, log."),
+        },
+        Err(_) => SmsProvider::Log,
+    }
+}
+
+fn set_token_store_backend() -> TokenStoreBackend {
+    dotenv().ok();
+    match std_env::var(env::TOKEN_STORE_BACKEND_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "redis" => TokenStoreBackend::Redis,
+            "postgres" => TokenStoreBackend::Postgres,
+            "sqlite" => TokenStoreBackend::Sqlite,
+            _ => panic!("TOKEN_STORE_BACKEND must be one of: redis, postgres, sqlite."),
+        },
+        Err(_) => TokenStoreBackend::Redis,
+    }
+}
+
+fn set_user_store_backend() -> UserStoreBackend {
+    dotenv().ok();
+    match std_env::var(env::USER_STORE_BACKEND_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "postgres" => UserStoreBackend::Postgres,
+            "sqlite" => UserStoreBackend::Sqlite,
+            _ => panic!("USER_STORE_BACKEND must be one of: postgres, sqlite."),
+        },
+        Err(_) => UserStoreBackend::Postgres,
+    }
+}
+
+fn set_sqlite_database_url() -> String {
+    dotenv().ok();
+    std_env::var(env::SQLITE_DATABASE_URL_ENV_VAR)
+        .unwrap_or_else(|_| "sqlite://auth-service.db".to_owned())
+}
+
+fn set_twilio_account_sid() -> String {
+    dotenv().ok();
+    let account_sid =
+        std_env::var(env::TWILIO_ACCOUNT_SID_ENV_VAR).expect("TWILIO_ACCOUNT_SID must be set.");
+    if account_sid.is_empty() {
+        panic!("TWILIO_ACCOUNT_SID must not be empty.");
+    }
+    account_sid
+}
+
+fn set_twilio_auth_token() -> Secret<String> {
+    dotenv().ok();
+    Secret::new(
+        std_env::var(env::TWILIO_AUTH_TOKEN_ENV_VAR).expect("TWILIO_AUTH_TOKEN must be set."),
+    )
+}
+
+fn set_twilio_from_number() -> String {
+    dotenv().ok();
+    std_env::var(env::TWILIO_FROM_NUMBER_ENV_VAR).expect("TWILIO_FROM_NUMBER must be set.")
+}
+
+fn set_breached_password_checker() -> BreachedPasswordCheckerProvider {
+    dotenv().ok();
+    match std_env::var(env::BREACHED_PASSWORD_CHECKER_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "hibp" => BreachedPasswordCheckerProvider::Hibp,
+            "local" => BreachedPasswordCheckerProvider::Local,
+            "disabled" => BreachedPasswordCheckerProvider::Disabled,
+            _ => panic!("BREACHED_PASSWORD_CHECKER must be one of: hibp, local, disabled."),
+        },
+        Err(_) => BreachedPasswordCheckerProvider::Hibp,
+    }
+}
+
+fn set_pwned_passwords_dir() -> PathBuf {
+    dotenv().ok();
+    let directory =
+        std_env::var(env::PWNED_PASSWORDS_DIR_ENV_VAR).expect("PWNED_PASSWORDS_DIR must be set.");
+    if directory.is_empty() {
+        panic!("PWNED_PASSWORDS_DIR must not be empty.");
+    }
+    PathBuf::from(directory)
+}
+
+// The deny list file holds one password per line
+fn set_password_policy() -> PasswordPolicy {
+    dotenv().ok();
+    let default = PasswordPolicy::default();
+
+    let policy = PasswordPolicy {
+        min_length: parse_env_var(env::PASSWORD_MIN_LENGTH_ENV_VAR, default.min_length),
+        max_length: parse_env_var(env::PASSWORD_MAX_LENGTH_ENV_VAR, default.max_length),
+        min_strength: parse_env_var(env::PASSWORD_MIN_STRENGTH_ENV_VAR, default.min_strength),
+        deny_list: default.deny_list,
+    };
+    // Empty passwords are never parsed, whatever the policy
+    if policy.min_length == 0 {
+        panic!("PASSWORD_MIN_LENGTH must be at least 1.");
+    }
+    if policy.min_length > policy.max_length {
+        panic!("PASSWORD_MIN_LENGTH must not be greater than PASSWORD_MAX_LENGTH.");
+    }
+    if policy.min_strength > 4 {
+        panic!("PASSWORD_MIN_STRENGTH must be between 0 and 4.");
+    }
+
+    match std_env::var(env::PASSWORD_DENY_LIST_FILE_ENV_VAR) {
+        Ok(path) if !path.is_empty() => {
+            let contents = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+                panic!("Failed to read PASSWORD_DENY_LIST_FILE {}: {}", path, e)
+            });
+            policy.with_deny_list(contents.lines())
+        }
+        _ => policy,
+    }
+}
+
+fn set_argon2_config() -> Argon2Config {
+    dotenv().ok();
+    let default = Argon2Config::default();
+
+    let config = Argon2Config {
+        memory_cost: parse_env_var(env::ARGON2_MEMORY_COST_ENV_VAR, default.memory_cost),
+        time_cost: parse_env_var(env::ARGON2_TIME_COST_ENV_VAR, default.time_cost),
+        parallelism: parse_env_var(env::ARGON2_PARALLELISM_ENV_VAR, default.parallelism),
+    };
+    if let Err(e) = config.params() {
+        panic!("ARGON2_* variables have invalid values: {}", e);
+    }
+    config
+}
+
+fn set_hashing_executor_config() -> HashingExecutorConfig {
+    dotenv().ok();
+    let default = HashingExecutorConfig::default();
+
+    let config = HashingExecutorConfig {
+        workers: parse_env_var(env::PASSWORD_HASHING_WORKERS_ENV_VAR, default.workers),
+        queue_capacity: parse_env_var(
+            env::PASSWORD_HASHING_QUEUE_CAPACITY_ENV_VAR,
+            default.queue_capacity,
+        ),
+    };
+    if config.workers == 0 {
+        panic!("PASSWORD_HASHING_WORKERS must be at least 1.");
+    }
+    config
+}
+
+fn set_auth_cookie_config() -> AuthCookieConfig {
+    dotenv().ok();
+    let default = AuthCookieConfig::default();
+
+    let same_site = match std_env::var(env::AUTH_COOKIE_SAME_SITE_ENV_VAR) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            _ => panic!("AUTH_COOKIE_SAME_SITE must be one of: strict, lax, none."),
+        },
+        Err(_) => default.same_site,
+    };
+
+    AuthCookieConfig {
+        path: std_env::var(env::AUTH_COOKIE_PATH_ENV_VAR).unwrap_or(default.path),
+        domain: std_env::var(env::AUTH_COOKIE_DOMAIN_ENV_VAR)
+            .ok()
+            .filter(|domain| !domain.is_empty()),
+        secure: parse_env_var(env::AUTH_COOKIE_SECURE_ENV_VAR, default.secure),
+        http_only: parse_env_var(env::AUTH_COOKIE_HTTP_ONLY_ENV_VAR, default.http_only),
+        same_site,
+        max_age_seconds: parse_env_var(
+            env::AUTH_COOKIE_MAX_AGE_SECONDS_ENV_VAR,
+            default.max_age_seconds,
+        ),
+        host_prefix: parse_env_var(env::AUTH_COOKIE_HOST_PREFIX_ENV_VAR, default.host_prefix),
+    }
+    .normalized()
+}
+
+// Empty values count as unset, as compose passes them for variables left out of the `.env` file
+fn parse_env_var<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std_env::var(name) {
+        Ok(value) if value.is_empty() => default,
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} has an invalid value: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
 pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const POSTGRES_PASSWORD_ENV_VAR: &str = "POSTGRES_PASSWORD";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const TOKEN_STORE_BACKEND_ENV_VAR: &str = "TOKEN_STORE_BACKEND";
+    pub const USER_STORE_BACKEND_ENV_VAR: &str = "USER_STORE_BACKEND";
+    pub const SQLITE_DATABASE_URL_ENV_VAR: &str = "SQLITE_DATABASE_URL";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const INTROSPECTION_CLIENT_ID_ENV_VAR: &str = "INTROSPECTION_CLIENT_ID";
+    pub const INTROSPECTION_CLIENT_SECRET_ENV_VAR: &str = "INTROSPECTION_CLIENT_SECRET";
+    pub const ADMIN_API_KEY_ENV_VAR: &str = "ADMIN_API_KEY";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const EMAIL_PROVIDER_ENV_VAR: &str = "EMAIL_PROVIDER";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMS_PROVIDER_ENV_VAR: &str = "SMS_PROVIDER";
+    pub const TWILIO_ACCOUNT_SID_ENV_VAR: &str = "TWILIO_ACCOUNT_SID";
+    pub const TWILIO_AUTH_TOKEN_ENV_VAR: &str = "TWILIO_AUTH_TOKEN";
+    pub const TWILIO_FROM_NUMBER_ENV_VAR: &str = "TWILIO_FROM_NUMBER";
+    pub const BREACHED_PASSWORD_CHECKER_ENV_VAR: &str = "BREACHED_PASSWORD_CHECKER";
+    pub const PWNED_PASSWORDS_DIR_ENV_VAR: &str = "PWNED_PASSWORDS_DIR";
+    pub const CHECK_BREACHED_PASSWORDS_AT_LOGIN_ENV_VAR: &str = "CHECK_BREACHED_PASSWORDS_AT_LOGIN";
+    pub const PASSWORD_MIN_LENGTH_ENV_VAR: &str = "PASSWORD_MIN_LENGTH";
+    pub const PASSWORD_MAX_LENGTH_ENV_VAR: &str = "PASSWORD_MAX_LENGTH";
+    pub const PASSWORD_MIN_STRENGTH_ENV_VAR: &str = "PASSWORD_MIN_STRENGTH";
+    pub const PASSWORD_DENY_LIST_FILE_ENV_VAR: &str = "PASSWORD_DENY_LIST_FILE";
+    pub const ARGON2_MEMORY_COST_ENV_VAR: &str = "ARGON2_MEMORY_COST";
+    pub const ARGON2_TIME_COST_ENV_VAR: &str = "ARGON2_TIME_COST";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const PASSWORD_HASHING_WORKERS_ENV_VAR: &str = "PASSWORD_HASHING_WORKERS";
+    pub const PASSWORD_HASHING_QUEUE_CAPACITY_ENV_VAR: &str = "PASSWORD_HASHING_QUEUE_CAPACITY";
+    pub const EMAIL_LOWERCASE_LOCAL_PART_ENV_VAR: &str = "EMAIL_LOWERCASE_LOCAL_PART";
+    pub const AUTH_COOKIE_PATH_ENV_VAR: &str = "AUTH_COOKIE_PATH";
+    pub const AUTH_COOKIE_DOMAIN_ENV_VAR: &str = "AUTH_COOKIE_DOMAIN";
+    pub const AUTH_COOKIE_SECURE_ENV_VAR: &str = "AUTH_COOKIE_SECURE";
+    pub const AUTH_COOKIE_HTTP_ONLY_ENV_VAR: &str = "AUTH_COOKIE_HTTP_ONLY";
+    pub const AUTH_COOKIE_SAME_SITE_ENV_VAR: &str = "AUTH_COOKIE_SAME_SITE";
+    pub const AUTH_COOKIE_MAX_AGE_SECONDS_ENV_VAR: &str = "AUTH_COOKIE_MAX_AGE_SECONDS";
+    pub const AUTH_COOKIE_HOST_PREFIX_ENV_VAR: &str = "AUTH_COOKIE_HOST_PREFIX";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const HOST_PREFIXED_JWT_COOKIE_NAME: &str = "__Host-jwt";
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+pub const REQUEST_ID_HEADER_NAME: &str = "x-request-id";
+pub const ADMIN_API_KEY_HEADER_NAME: &str = "x-admin-api-key";
+pub const WEBHOOK_ID_HEADER_NAME: &str = "x-webhook-id";
+pub const WEBHOOK_TIMESTAMP_HEADER_NAME: &str = "x-webhook-timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER_NAME: &str = "x-webhook-signature";
+// How long a 2FA code stays valid
+pub const TWO_FA_CODE_TTL_SECONDS: u64 = 600;
+// How long a user waits before another verification code is sent to them or to the same number
+pub const SMS_RESEND_INTERVAL_SECONDS: u64 = 60;
+// How long a user whose login was refused until they reset their password waits for a new link
+pub const PASSWORD_RESET_RESEND_INTERVAL_SECONDS: u64 = 60;
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_INTROSPECTION_CLIENT_ID: &str = "app-service";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+// The submission port, on which relays expect STARTTLS
+pub const DEFAULT_SMTP_PORT: u16 = 587;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
@@ -61,6 +470,34 @@ pub mod prod {
         pub const SENDER: &str = "bogdan@codeiron.io";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const BASE_URL: &str = "https://api.twilio.com";
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
+    pub mod password_checker {
+        use std::time::Duration;
+
+        pub const BASE_URL: &str = "https://api.pwnedpasswords.com";
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(5);
+    }
+    pub mod email_worker {
+        use std::time::Duration;
+
+        pub const POLL_INTERVAL: Duration = std::time::Duration::from_secs(1);
+    }
+    pub mod webhook_dispatcher {
+        use std::time::Duration;
+
+        pub const POLL_INTERVAL: Duration = std::time::Duration::from_secs(5);
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
+    pub mod expired_entries_purger {
+        use std::time::Duration;
+
+        pub const INTERVAL: Duration = std::time::Duration::from_secs(60);
+    }
 }
 
 pub mod test {
@@ -71,4 +508,15 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const SENDER: &str = "+15005550006";
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+    }
+    pub mod password_checker {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+    }
 }
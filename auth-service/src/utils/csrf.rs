@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+
+use crate::domain::AuthAPIError;
+
+use super::{
+    auth::constant_time_eq,
+    constants::{AUTH_COOKIE_CONFIG, CSRF_COOKIE_NAME, CSRF_HEADER_NAME},
+};
+
+// Origins allowed to send cookie-authenticated, state-changing requests.
+// This is the same allowlist the CORS layer is configured with.
+#[derive(Clone)]
+pub struct CsrfConfig {
+    allowed_origins: Arc<Vec<HeaderValue>>,
+}
+
+impl CsrfConfig {
+    pub fn new(allowed_origins: Vec<HeaderValue>) -> Self {
+        Self {
+            allowed_origins: Arc::new(allowed_origins),
+        }
+    }
+}
+
+// Protects cookie-authenticated, state-changing requests against cross-site request forgery.
+// Such requests must come from an allowed (or the same) origin and carry the double-submit
+// token from the CSRF cookie in the `X-CSRF-Token` header.
+// Requests authenticated with a bearer token can't be forged by a browser and are exempt.
+#[tracing::instrument(name = "CSRF protection", skip_all)]
+pub async fn csrf_protection(
+    State(config): State<CsrfConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_safe_method(request.method())
+        || has_bearer_token(request.headers())
+        || !has_auth_cookie(request.headers())
+    {
+        return next.run(request).await;
+    }
+
+    if !is_origin_allowed(request.headers(), &config.allowed_origins) {
+        tracing::warn!("rejected request from a disallowed origin");
+        return AuthAPIError::CsrfCheckFailed.into_response();
+    }
+
+    if !is_csrf_token_valid(request.headers()) {
+        tracing::warn!("rejected request with a missing or invalid CSRF token");
+        return AuthAPIError::CsrfCheckFailed.into_response();
+    }
+
+    next.run(request).await
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn has_bearer_token(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("Bearer "))
+        .unwrap_or(false)
+}
+
+fn has_auth_cookie(headers: &HeaderMap) -> bool {
+    CookieJar::from_headers(headers)
+        .get(AUTH_COOKIE_CONFIG.name())
+        .map(|cookie| !cookie.value().is_empty())
+        .unwrap_or(false)
+}
+
+// Browsers always send `Origin` on cross-origin POSTs; `Referer` is the fallback for older ones.
+// When neither is present the request did not come from a browser page and the token check decides.
+fn is_origin_allowed(headers: &HeaderMap, allowed_origins: &[HeaderValue]) -> bool {
+    let origin = match headers.get(header::ORIGIN) {
+        Some(origin) => origin.to_str().ok().map(|o| o.to_owned()),
+        None => match headers.get(header::REFERER) {
+            Some(referer) => referer.to_str().ok().and_then(origin_of),
+            None => return true,
+        },
+    };
+
+    let origin = match origin {
+        Some(origin) => origin,
+        None => return false,
+    };
+
+    if allowed_origins
+        .iter()
+        .any(|allowed| allowed == origin.as_str())
+    {
+        return true;
+    }
+
+    is_same_origin(&origin, headers.get(header::HOST))
+}
+
+fn origin_of(url: &str) -> Option<String> {
+    let uri = url.parse::<Uri>().ok()?;
+    Some(format!("{}://{}", uri.scheme_str()?, uri.authority()?))
+}
+
+fn is_same_origin(origin: &str, host: Option<&HeaderValue>) -> bool {
+    let host = match host.and_then(|host| host.to_str().ok()) {
+        Some(host) => host,
+        None => return false,
+    };
+
+    origin
+        .parse::<Uri>()
+        .ok()
+        .and_then(|uri| uri.authority().map(|authority| authority.as_str() == host))
+        .unwrap_or(false)
+}
+
+fn is_csrf_token_valid(headers: &HeaderMap) -> bool {
+    let jar = CookieJar::from_headers(headers);
+    let cookie_token = match jar.get(CSRF_COOKIE_NAME) {
+        Some(cookie) if !cookie.value().is_empty() => cookie.value(),
+        _ => return false,
+    };
+
+    let header_token = match headers
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(token) => token,
+        None => return false,
+    };
+
+    constant_time_eq(cookie_token.as_bytes(), header_token.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn allowed_origins() -> Vec<HeaderValue> {
+        vec![HeaderValue::from_static("http://localhost:8000")]
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        let headers = headers(&[("origin", "http://localhost:8000")]);
+        assert!(is_origin_allowed(&headers, &allowed_origins()));
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let headers = headers(&[
+            ("origin", "http://localhost:3000"),
+            ("host", "localhost:3000"),
+        ]);
+        assert!(is_origin_allowed(&headers, &allowed_origins()));
+    }
+
+    #[test]
+    fn test_disallowed_origin() {
+        let headers = headers(&[("origin", "http://evil.com"), ("host", "localhost:3000")]);
+        assert!(!is_origin_allowed(&headers, &allowed_origins()));
+    }
+
+    #[test]
+    fn test_referer_fallback() {
+        let allowed = headers(&[("referer", "http://localhost:8000/some/page")]);
+        assert!(is_origin_allowed(&allowed, &allowed_origins()));
+
+        let disallowed = headers(&[("referer", "http://evil.com/some/page")]);
+        assert!(!is_origin_allowed(&disallowed, &allowed_origins()));
+    }
+
+    #[test]
+    fn test_csrf_token_must_match_cookie() {
+        let valid = headers(&[("cookie", "csrf_token=abc"), (CSRF_HEADER_NAME, "abc")]);
+        assert!(is_csrf_token_valid(&valid));
+
+        let mismatched = headers(&[("cookie", "csrf_token=abc"), (CSRF_HEADER_NAME, "abd")]);
+        assert!(!is_csrf_token_valid(&mismatched));
+
+        let missing_header = headers(&[("cookie", "csrf_token=abc")]);
+        assert!(!is_csrf_token_valid(&missing_header));
+
+        let missing_cookie = headers(&[(CSRF_HEADER_NAME, "abc")]);
+        assert!(!is_csrf_token_valid(&missing_cookie));
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::AppState,
+    domain::{Device, DeviceStatus, RequestMetadata, User},
+};
+
+use super::{
+    auth::{generate_action_token, TokenPurpose, LOGIN_REPORT_TOKEN_TTL_SECONDS},
+    constants::AUTH_SERVICE_URL,
+    email_templates::{EmailTemplate, NewDeviceEmail},
+};
+
+// Remembers the device a session was started from and, when the user has logged in from
+// other devices before, emails them about it with a link to report the login if it wasn't them.
+// Failures are logged but never fail the login.
+#[tracing::instrument(name = "Notify new device", skip_all)]
+pub async fn notify_if_new_device(
+    state: &AppState,
+    user: &User,
+    session_id: &str,
+    metadata: &RequestMetadata,
+) {
+    let email = &user.email;
+    let device = Device::from(metadata);
+
+    let status = state
+        .known_device_store
+        .remember_device(email, &device)
+        .await;
+
+    match status {
+        Ok(DeviceStatus::New) => {
+            if let Err(e) = send_new_device_email(state, user, session_id, &device).await {
+                tracing::error!(error = ?e, "failed to enqueue new device email");
+            }
+        }
+        Ok(DeviceStatus::Known | DeviceStatus::First) => {}
+        Err(e) => tracing::error!(error = ?e, "failed to remember device"),
+    }
+}
+
+async fn send_new_device_email(
+    state: &AppState,
+    user: &User,
+    session_id: &str,
+    device: &Device,
+) -> Result<()> {
+    let report_token = generate_action_token(
+        user,
+        TokenPurpose::LoginReport,
+        LOGIN_REPORT_TOKEN_TTL_SECONDS,
+        Some(session_id.to_owned()),
+    )?;
+
+    let message = NewDeviceEmail {
+        time: &Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        ip: device.ip_or_unknown(),
+        device: device.user_agent_or_unknown(),
+        report_link: &format!("{}/?report={}", AUTH_SERVICE_URL.as_str(), report_token),
+    }
+    .render()?;
+
+    state.email_queue.enqueue(&user.email, &message).await?;
+
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use askama::Template;
+use color_eyre::eyre::Result;
+
+use crate::domain::EmailMessage;
+
+// Every message type has an HTML and a plaintext template under `templates/emails`
+pub trait EmailTemplate {
+    fn subject(&self) -> String;
+    fn render_html(&self) -> askama::Result<String>;
+    fn render_text(&self) -> askama::Result<String>;
+
+    fn render(&self) -> Result<EmailMessage> {
+        Ok(EmailMessage {
+            subject: self.subject(),
+            html_body: self.render_html()?,
+            text_body: self.render_text()?,
+        })
+    }
+}
+
+pub struct TwoFACodeEmail<'a> {
+    pub code: &'a str,
+    pub expires_in_minutes: u64,
+    pub ip: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    email: &'a TwoFACodeEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    email: &'a TwoFACodeEmail<'a>,
+}
+
+impl EmailTemplate for TwoFACodeEmail<'_> {
+    fn subject(&self) -> String {
+        "2FA code".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        TwoFACodeHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        TwoFACodeText { email: self }.render()
+    }
+}
+
+pub struct NewDeviceEmail<'a> {
+    pub time: &'a str,
+    pub ip: &'a str,
+    pub device: &'a str,
+    pub report_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_device.html")]
+struct NewDeviceHtml<'a> {
+    email: &'a NewDeviceEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_device.txt")]
+struct NewDeviceText<'a> {
+    email: &'a NewDeviceEmail<'a>,
+}
+
+impl EmailTemplate for NewDeviceEmail<'_> {
+    fn subject(&self) -> String {
+        "New sign-in to your account".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        NewDeviceHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        NewDeviceText { email: self }.render()
+    }
+}
+
+pub struct BreachedPasswordEmail<'a> {
+    pub reset_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/breached_password.html")]
+struct BreachedPasswordHtml<'a> {
+    email: &'a BreachedPasswordEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/breached_password.txt")]
+struct BreachedPasswordText<'a> {
+    email: &'a BreachedPasswordEmail<'a>,
+}
+
+impl EmailTemplate for BreachedPasswordEmail<'_> {
+    fn subject(&self) -> String {
+        "Your password must be changed".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        BreachedPasswordHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        BreachedPasswordText { email: self }.render()
+    }
+}
+
+// Sent again on each login refused until the password is reset, as the first link may be gone
+pub struct PasswordResetRequiredEmail<'a> {
+    pub reset_link: &'a str,
+    pub expires_in_minutes: u64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset_required.html")]
+struct PasswordResetRequiredHtml<'a> {
+    email: &'a PasswordResetRequiredEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset_required.txt")]
+struct PasswordResetRequiredText<'a> {
+    email: &'a PasswordResetRequiredEmail<'a>,
+}
+
+impl EmailTemplate for PasswordResetRequiredEmail<'_> {
+    fn subject(&self) -> String {
+        "Your password must be changed".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        PasswordResetRequiredHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        PasswordResetRequiredText { email: self }.render()
+    }
+}
+
+// Sent to the new address of an email change
+pub struct EmailChangeConfirmEmail<'a> {
+    pub confirm_link: &'a str,
+    pub expires_in_minutes: u64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_change_confirm.html")]
+struct EmailChangeConfirmHtml<'a> {
+    email: &'a EmailChangeConfirmEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_change_confirm.txt")]
+struct EmailChangeConfirmText<'a> {
+    email: &'a EmailChangeConfirmEmail<'a>,
+}
+
+impl EmailTemplate for EmailChangeConfirmEmail<'_> {
+    fn subject(&self) -> String {
+        "Confirm your new email address".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        EmailChangeConfirmHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        EmailChangeConfirmText { email: self }.render()
+    }
+}
+
+// Sent to the current address of an email change
+pub struct EmailChangeNoticeEmail<'a> {
+    pub new_email: &'a str,
+    pub cancel_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_change_notice.html")]
+struct EmailChangeNoticeHtml<'a> {
+    email: &'a EmailChangeNoticeEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_change_notice.txt")]
+struct EmailChangeNoticeText<'a> {
+    email: &'a EmailChangeNoticeEmail<'a>,
+}
+
+impl EmailTemplate for EmailChangeNoticeEmail<'_> {
+    fn subject(&self) -> String {
+        "Your email address is being changed".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        EmailChangeNoticeHtml { email: self }.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        EmailChangeNoticeText { email: self }.render()
+    }
+}
+
+// Sent to the new address of an email change when it already belongs to an account, in place of
+// the confirmation link
+pub struct EmailInUseEmail;
+
+#[derive(Template)]
+#[template(path = "emails/email_in_use.html")]
+struct EmailInUseHtml;
+
+#[derive(Template)]
+#[template(path = "emails/email_in_use.txt")]
+struct EmailInUseText;
+
+impl EmailTemplate for EmailInUseEmail {
+    fn subject(&self) -> String {
+        "Your email address is already in use".to_owned()
+    }
+
+    fn render_html(&self) -> askama::Result<String> {
+        EmailInUseHtml.render()
+    }
+
+    fn render_text(&self) -> askama::Result<String> {
+        EmailInUseText.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_fa_code_email() {
+        let message = TwoFACodeEmail {
+            code: "123456",
+            expires_in_minutes: 10,
+            ip: "127.0.0.1",
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "2FA code");
+        for body in [&message.html_body, &message.text_body] {
+            assert!(body.contains("123456"));
+            assert!(body.contains("10 minutes"));
+            assert!(body.contains("127.0.0.1"));
+        }
+        assert!(message.html_body.contains("<strong>123456</strong>"));
+        assert!(!message.text_body.contains('<'));
+    }
+
+    #[test]
+    fn test_new_device_email() {
+        let message = NewDeviceEmail {
+            time: "now",
+            ip: "unknown",
+            device: "test-agent",
+            report_link: "http://localhost/?report=token",
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "New sign-in to your account");
+        assert!(message.text_body.contains("Time: now"));
+        assert!(message.text_body.contains("IP address: unknown"));
+        assert!(message.text_body.contains("Device: test-agent"));
+        assert!(message
+            .text_body
+            .contains("reset your password: http://localhost/?report=token"));
+        assert!(message
+            .html_body
+            .contains(r#"<a href="http://localhost/?report=token">"#));
+    }
+
+    #[test]
+    fn test_breached_password_email() {
+        let message = BreachedPasswordEmail {
+            reset_link: "http://localhost/?reset=token",
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "Your password must be changed");
+        assert!(message
+            .text_body
+            .contains("sign in again: http://localhost/?reset=token"));
+        assert!(message
+            .html_body
+            .contains(r#"<a href="http://localhost/?reset=token">"#));
+    }
+
+    #[test]
+    fn test_password_reset_required_email() {
+        let message = PasswordResetRequiredEmail {
+            reset_link: "http://localhost/?reset=token",
+            expires_in_minutes: 10,
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "Your password must be changed");
+        assert!(message
+            .text_body
+            .contains("sign in again: http://localhost/?reset=token"));
+        assert!(message.text_body.contains("10 minutes"));
+        assert!(message
+            .html_body
+            .contains(r#"<a href="http://localhost/?reset=token">"#));
+    }
+
+    #[test]
+    fn test_email_change_confirm_email() {
+        let message = EmailChangeConfirmEmail {
+            confirm_link: "http://localhost/?confirm-email=token",
+            expires_in_minutes: 10,
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "Confirm your new email address");
+        assert!(message
+            .text_body
+            .contains("Confirm your new email address: http://localhost/?confirm-email=token"));
+        assert!(message.text_body.contains("10 minutes"));
+        assert!(message
+            .html_body
+            .contains(r#"<a href="http://localhost/?confirm-email=token">"#));
+    }
+
+    #[test]
+    fn test_email_change_notice_email() {
+        let message = EmailChangeNoticeEmail {
+            new_email: "new@email.com",
+            cancel_link: "http://localhost/?cancel-email-change=token",
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(message.subject, "Your email address is being changed");
+        for body in [&message.html_body, &message.text_body] {
+            assert!(body.contains("new@email.com"));
+        }
+        assert!(message
+            .text_body
+            .contains("sign out all sessions: http://localhost/?cancel-email-change=token"));
+        assert!(message
+            .html_body
+            .contains(r#"<a href="http://localhost/?cancel-email-change=token">"#));
+    }
+
+    #[test]
+    fn test_email_in_use_email() {
+        let message = EmailInUseEmail.render().unwrap();
+
+        assert_eq!(message.subject, "Your email address is already in use");
+        for body in [&message.html_body, &message.text_body] {
+            assert!(body.contains("already belongs to yours"));
+        }
+        assert!(!message.text_body.contains('<'));
+    }
+
+    #[test]
+    fn html_body_escapes_variables() {
+        let message = NewDeviceEmail {
+            time: "now",
+            ip: "127.0.0.1",
+            device: "<script>alert(1)</script>",
+            report_link: "http://localhost/?report=token",
+        }
+        .render()
+        .unwrap();
+
+        assert!(!message.html_body.contains("<script>"));
+        assert!(message
+            .text_body
+            .contains("Device: <script>alert(1)</script>"));
+    }
+}
@@ -0,0 +1,285 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{ConnectInfo, FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts},
+};
+use axum_extra::{
+    extract::CookieJar,
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, RequestMetadata, UserId},
+};
+
+use super::{
+    auth::{get_token_user, validate_admin_api_key, validate_token, Claims},
+    constants::{ADMIN_API_KEY_HEADER_NAME, AUTH_COOKIE_CONFIG, REQUEST_ID_HEADER_NAME},
+};
+
+// Resolves the auth token of a request, looking in order at:
+// 1. the `Authorization: Bearer <token>` header
+// 2. a JSON body of the form `{ "token": "<token>" }`
+// 3. the auth cookie
+// A token the caller sent explicitly wins over the cookie the browser attached on its own.
+// Because it may consume the body, this must be the last extractor of a handler.
+pub struct AuthToken(pub Secret<String>);
+
+#[derive(Deserialize)]
+struct TokenBody {
+    token: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for AuthToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (mut parts, body) = req.into_parts();
+
+        if let Ok(TypedHeader(Authorization(bearer))) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(&mut parts, state).await
+        {
+            return non_empty(bearer.token());
+        }
+
+        let jar = CookieJar::from_headers(&parts.headers);
+
+        let req = Request::from_parts(parts, body);
+        if let Ok(bytes) = Bytes::from_request(req, state).await {
+            if let Ok(TokenBody { token: Some(token) }) = serde_json::from_slice(&bytes) {
+                if !token.is_empty() {
+                    return non_empty(&token);
+                }
+            }
+        }
+
+        match jar.get(AUTH_COOKIE_CONFIG.name()) {
+            Some(cookie) => non_empty(cookie.value()),
+            None => Err(AuthAPIError::MissingToken),
+        }
+    }
+}
+
+fn non_empty(token: &str) -> Result<AuthToken, AuthAPIError> {
+    if token.is_empty() {
+        Err(AuthAPIError::MissingToken)
+    } else {
+        Ok(AuthToken(Secret::new(token.to_owned())))
+    }
+}
+
+// The user authenticated by the auth token of the `Authorization: Bearer <token>` header or
+// of the auth cookie. Unlike `AuthToken` it leaves the body alone, for handlers that also read it.
+pub struct AuthenticatedUser {
+    pub user_id: UserId,
+    // The current email of the user, looked up by id
+    pub email: Email,
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token =
+            match TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await {
+                Ok(TypedHeader(Authorization(bearer))) => bearer.token().to_owned(),
+                Err(_) => CookieJar::from_headers(&parts.headers)
+                    .get(AUTH_COOKIE_CONFIG.name())
+                    .map(|cookie| cookie.value().to_owned())
+                    .unwrap_or_default(),
+            };
+        let AuthToken(token) = non_empty(&token)?;
+
+        let claims = validate_token(token.expose_secret(), state.banned_token_store.clone())
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        let user = get_token_user(state, &claims.sub, claims.ver).await?;
+
+        Ok(AuthenticatedUser {
+            user_id: user.id,
+            email: user.email,
+            claims,
+        })
+    }
+}
+
+// Collects the client IP, user agent and request id of a request for audit events.
+// The IP is only available when the app is served with connect info.
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestMetadata
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let header_value = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_owned())
+        };
+
+        Ok(RequestMetadata {
+            ip,
+            user_agent: header_value(header::USER_AGENT.as_str()),
+            request_id: header_value(REQUEST_ID_HEADER_NAME),
+        })
+    }
+}
+
+// Guards admin routes: the request must carry the admin API key in the `X-Admin-Api-Key` header
+pub struct AdminApiKey;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminApiKey
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let api_key = parts
+            .headers
+            .get(ADMIN_API_KEY_HEADER_NAME)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        if validate_admin_api_key(api_key) {
+            Ok(AdminApiKey)
+        } else {
+            Err(AuthAPIError::InvalidAdminApiKey)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    async fn extract(request: Request) -> Result<AuthToken, AuthAPIError> {
+        AuthToken::from_request(request, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_bearer_header_takes_precedence() {
+        let request = Request::builder()
+            .header(header::AUTHORIZATION, "Bearer header_token")
+            .header(
+                header::COOKIE,
+                format!("{}=cookie_token", AUTH_COOKIE_CONFIG.name()),
+            )
+            .body(Body::from(r#"{"token":"body_token"}"#))
+            .unwrap();
+
+        let token = extract(request).await.unwrap();
+        assert_eq!(token.0.expose_secret(), "header_token");
+    }
+
+    #[tokio::test]
+    async fn test_body_takes_precedence_over_cookie() {
+        let request = Request::builder()
+            .header(
+                header::COOKIE,
+                format!("{}=cookie_token", AUTH_COOKIE_CONFIG.name()),
+            )
+            .body(Body::from(r#"{"token":"body_token"}"#))
+            .unwrap();
+
+        let token = extract(request).await.unwrap();
+        assert_eq!(token.0.expose_secret(), "body_token");
+    }
+
+    #[tokio::test]
+    async fn test_token_from_cookie_without_body_token() {
+        let inputs = ["", "{}", r#"{"token":""}"#];
+
+        for input in inputs {
+            let request = Request::builder()
+                .header(
+                    header::COOKIE,
+                    format!("{}=cookie_token", AUTH_COOKIE_CONFIG.name()),
+                )
+                .body(Body::from(input))
+                .unwrap();
+
+            let token = extract(request).await.unwrap();
+            assert_eq!(
+                token.0.expose_secret(),
+                "cookie_token",
+                "Failed for input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_from_body() {
+        let request = Request::builder()
+            .body(Body::from(r#"{"token":"body_token"}"#))
+            .unwrap();
+
+        let token = extract(request).await.unwrap();
+        assert_eq!(token.0.expose_secret(), "body_token");
+    }
+
+    #[tokio::test]
+    async fn test_request_metadata() {
+        let mut request = Request::builder()
+            .header(header::USER_AGENT, "test-agent")
+            .header(REQUEST_ID_HEADER_NAME, "request-id")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 8080))));
+        let (mut parts, _) = request.into_parts();
+
+        let metadata = RequestMetadata::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.ip, Some("127.0.0.1".to_owned()));
+        assert_eq!(metadata.user_agent, Some("test-agent".to_owned()));
+        assert_eq!(metadata.request_id, Some("request-id".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_missing_token() {
+        let inputs = ["", "{}", r#"{"token":""}"#, "not json"];
+
+        for input in inputs {
+            let request = Request::builder().body(Body::from(input)).unwrap();
+            let result = extract(request).await;
+            assert!(
+                matches!(result, Err(AuthAPIError::MissingToken)),
+                "Failed for input: {:?}",
+                input
+            );
+        }
+    }
+}
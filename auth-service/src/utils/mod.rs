@@ -1,3 +1,13 @@
+pub mod audit;
 pub mod auth;
+pub mod backoff;
+pub mod breached_passwords;
 pub mod constants;
+pub mod csrf;
+pub mod devices;
+pub mod email_templates;
+pub mod extractors;
+pub mod password_hashing;
+pub mod rate_limiter;
+pub mod smtp;
 pub mod tracing;
@@ -0,0 +1,379 @@
+use argon2::{
+    password_hash::{self, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+};
+use color_eyre::eyre::{eyre, Context, Result};
+use pbkdf2::Pbkdf2;
+use secrecy::{ExposeSecret, Secret};
+
+// PHC string algorithms that can be verified. Only Argon2id is used for new hashes, the others
+// come from imported users.
+const SUPPORTED_PHC_ALGORITHMS: [&str; 4] = ["argon2id", "argon2i", "argon2d", "pbkdf2-sha256"];
+
+// bcrypt hashes predate the PHC string format, and have their own
+const BCRYPT_PREFIXES: [&str; 4] = ["$2a$", "$2b$", "$2x$", "$2y$"];
+
+// Imported hashes are verified with the costs they carry, so a hash with huge ones would tie up
+// a hashing worker on every login. Argon2 costs above these, unless the configured ones are
+// higher still, are refused at import.
+const MAX_IMPORTED_ARGON2_MEMORY_COST: u32 = 64 * 1024;
+const MAX_IMPORTED_ARGON2_TIME_COST: u32 = 10;
+const MAX_IMPORTED_ARGON2_PARALLELISM: u32 = 4;
+const MAX_IMPORTED_BCRYPT_COST: u32 = 14;
+const MAX_IMPORTED_PBKDF2_ROUNDS: u32 = 1_000_000;
+
+// Cost parameters used to hash new passwords. Stored hashes keep the parameters they were
+// created with, and are upgraded on the next successful login when these are raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Config {
+    // In KiB
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_cost: 15000,
+            time_cost: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Config {
+    pub fn params(&self) -> Result<Params> {
+        Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+            .wrap_err("invalid Argon2 parameters")
+    }
+}
+
+// A wrong password is `Ok(false)`, an error means the stored hash can't be verified at all.
+// Blocks for as long as the hash takes, see `HashingExecutor::verify_password_hash`.
+pub fn verify_password_hash(
+    expected_password_hash: &Secret<String>,
+    password_candidate: &Secret<String>,
+) -> Result<bool> {
+    let expected_password_hash = expected_password_hash.expose_secret();
+    let password_candidate = password_candidate.expose_secret().as_bytes();
+
+    if is_bcrypt_hash(expected_password_hash) {
+        return bcrypt::verify(password_candidate, expected_password_hash)
+            .wrap_err("failed to verify bcrypt hash");
+    }
+
+    let expected_password_hash: PasswordHash<'_> = PasswordHash::new(expected_password_hash)?;
+
+    // The parameters are read from the stored hash
+    match expected_password_hash.verify_password(&[&Argon2::default(), &Pbkdf2], password_candidate)
+    {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(eyre!(e).wrap_err("failed to verify password hash")),
+    }
+}
+
+// Blocks for as long as the hash takes, see `HashingExecutor::compute_password_hash`
+pub fn compute_password_hash(
+    password: &Secret<String>,
+    config: Argon2Config,
+) -> Result<Secret<String>> {
+    let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, config.params()?)
+        .hash_password(password.expose_secret().as_bytes(), &salt)?
+        .to_string();
+
+    Ok(Secret::new(password_hash))
+}
+
+// Tells whether a stored hash was created with another algorithm or weaker parameters than the
+// current ones, and should be recomputed while the plain password is at hand
+pub fn needs_rehash(password_hash: &Secret<String>, config: &Argon2Config) -> bool {
+    let password_hash = match PasswordHash::new(password_hash.expose_secret()) {
+        Ok(password_hash) => password_hash,
+        Err(_) => return true,
+    };
+
+    if password_hash.algorithm != Algorithm::Argon2id.ident()
+        || password_hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+
+    match Params::try_from(&password_hash) {
+        Ok(params) => {
+            params.m_cost() < config.memory_cost
+                || params.t_cost() < config.time_cost
+                || params.p_cost() < config.parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+// Tells whether a password hash, e.g. from an imported user, can be verified at login
+pub fn is_supported_password_hash(password_hash: &Secret<String>) -> bool {
+    let password_hash = password_hash.expose_secret();
+
+    if is_bcrypt_hash(password_hash) {
+        return password_hash.parse::<bcrypt::HashParts>().is_ok();
+    }
+
+    match PasswordHash::new(password_hash) {
+        Ok(password_hash) => {
+            SUPPORTED_PHC_ALGORITHMS.contains(&password_hash.algorithm.as_str())
+                && password_hash.hash.is_some()
+        }
+        Err(_) => false,
+    }
+}
+
+// Tells whether a supported password hash, e.g. from an imported user, would be too costly to
+// verify at login
+pub fn exceeds_import_cost_limits(password_hash: &Secret<String>, config: &Argon2Config) -> bool {
+    let password_hash = password_hash.expose_secret();
+
+    if is_bcrypt_hash(password_hash) {
+        return match password_hash.parse::<bcrypt::HashParts>() {
+            Ok(parts) => parts.get_cost() > MAX_IMPORTED_BCRYPT_COST,
+            Err(_) => true,
+        };
+    }
+
+    let password_hash = match PasswordHash::new(password_hash) {
+        Ok(password_hash) => password_hash,
+        Err(_) => return true,
+    };
+
+    if password_hash.algorithm.as_str().starts_with("pbkdf2") {
+        // Without a round count the default one is used, which is below the limit
+        return password_hash
+            .params
+            .get_decimal("i")
+            .is_some_and(|rounds| rounds > MAX_IMPORTED_PBKDF2_ROUNDS);
+    }
+
+    match Params::try_from(&password_hash) {
+        Ok(params) => {
+            params.m_cost() > MAX_IMPORTED_ARGON2_MEMORY_COST.max(config.memory_cost)
+                || params.t_cost() > MAX_IMPORTED_ARGON2_TIME_COST.max(config.time_cost)
+                || params.p_cost() > MAX_IMPORTED_ARGON2_PARALLELISM.max(config.parallelism)
+        }
+        Err(_) => true,
+    }
+}
+
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    BCRYPT_PREFIXES
+        .iter()
+        .any(|prefix| password_hash.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters, so the tests run fast
+    const CONFIG: Argon2Config = Argon2Config {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    fn password() -> Secret<String> {
+        Secret::new("password123".to_owned())
+    }
+
+    #[test]
+    fn hash_can_be_verified() {
+        let password_hash = compute_password_hash(&password(), CONFIG).unwrap();
+
+        assert!(verify_password_hash(&password_hash, &password()).unwrap());
+        assert!(
+            !verify_password_hash(&password_hash, &Secret::new("password456".to_owned())).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_hash_fails_to_verify() {
+        for password_hash in ["corrupted", "$2b$12$tooshort"] {
+            let password_hash = Secret::new(password_hash.to_owned());
+            assert!(verify_password_hash(&password_hash, &password()).is_err());
+        }
+    }
+
+    #[test]
+    fn hash_with_current_params_does_not_need_rehash() {
+        let password_hash = compute_password_hash(&password(), CONFIG).unwrap();
+
+        assert!(!needs_rehash(&password_hash, &CONFIG));
+    }
+
+    #[test]
+    fn hash_with_weaker_params_needs_rehash() {
+        let password_hash = compute_password_hash(&password(), CONFIG).unwrap();
+
+        let stronger = Argon2Config {
+            time_cost: 2,
+            ..CONFIG
+        };
+        assert!(needs_rehash(&password_hash, &stronger));
+
+        let more_memory = Argon2Config {
+            memory_cost: 128,
+            ..CONFIG
+        };
+        assert!(needs_rehash(&password_hash, &more_memory));
+    }
+
+    #[test]
+    fn hash_with_stronger_params_does_not_need_rehash() {
+        let password_hash = compute_password_hash(&password(), CONFIG).unwrap();
+
+        // Lowering the costs doesn't downgrade the hashes created before
+        let weaker = Argon2Config {
+            memory_cost: 32,
+            ..CONFIG
+        };
+        assert!(!needs_rehash(&password_hash, &weaker));
+    }
+
+    #[test]
+    fn hash_with_other_algorithm_needs_rehash() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let password_hash =
+            Argon2::new(Algorithm::Argon2i, Version::V0x13, CONFIG.params().unwrap())
+                .hash_password(b"password123", &salt)
+                .unwrap()
+                .to_string();
+
+        assert!(needs_rehash(&Secret::new(password_hash), &CONFIG));
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let config = Argon2Config {
+            memory_cost: 1,
+            ..CONFIG
+        };
+
+        assert!(config.params().is_err());
+    }
+
+    fn bcrypt_hash() -> Secret<String> {
+        Secret::new(bcrypt::hash("password123", 4).unwrap())
+    }
+
+    fn pbkdf2_hash() -> Secret<String> {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let params = pbkdf2::Params {
+            rounds: 1000,
+            ..Default::default()
+        };
+        let password_hash = Pbkdf2
+            .hash_password_customized(b"password123", None, None, params, &salt)
+            .unwrap()
+            .to_string();
+
+        Secret::new(password_hash)
+    }
+
+    #[test]
+    fn imported_hashes_with_excessive_costs_are_refused() {
+        let config = Argon2Config::default();
+        let argon2_hash = |params: &str| {
+            Secret::new(format!(
+                "$argon2id$v=19${}$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG",
+                params
+            ))
+        };
+
+        assert!(!exceeds_import_cost_limits(
+            &argon2_hash("m=15000,t=2,p=1"),
+            &config
+        ));
+        assert!(exceeds_import_cost_limits(
+            &argon2_hash("m=4194304,t=2,p=1"),
+            &config
+        ));
+        assert!(exceeds_import_cost_limits(
+            &argon2_hash("m=15000,t=1000,p=1"),
+            &config
+        ));
+        assert!(exceeds_import_cost_limits(
+            &argon2_hash("m=15000,t=2,p=64"),
+            &config
+        ));
+
+        // The configured costs are always accepted
+        let costly_config = Argon2Config {
+            memory_cost: 4194304,
+            ..config
+        };
+        assert!(!exceeds_import_cost_limits(
+            &argon2_hash("m=4194304,t=2,p=1"),
+            &costly_config
+        ));
+
+        assert!(!exceeds_import_cost_limits(&bcrypt_hash(), &config));
+        let costly_bcrypt_hash = bcrypt_hash().expose_secret().replacen("$04$", "$20$", 1);
+        assert!(exceeds_import_cost_limits(
+            &Secret::new(costly_bcrypt_hash),
+            &config
+        ));
+
+        assert!(!exceeds_import_cost_limits(&pbkdf2_hash(), &config));
+        let costly_pbkdf2_hash = pbkdf2_hash()
+            .expose_secret()
+            .replacen("i=1000", "i=100000000", 1);
+        assert!(exceeds_import_cost_limits(
+            &Secret::new(costly_pbkdf2_hash),
+            &config
+        ));
+    }
+
+    #[test]
+    fn legacy_hashes_can_be_verified() {
+        for password_hash in [bcrypt_hash(), pbkdf2_hash()] {
+            assert!(verify_password_hash(&password_hash, &password()).unwrap());
+            assert!(
+                !verify_password_hash(&password_hash, &Secret::new("password456".to_owned()))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn legacy_hashes_need_rehash() {
+        assert!(needs_rehash(&bcrypt_hash(), &CONFIG));
+        assert!(needs_rehash(&pbkdf2_hash(), &CONFIG));
+    }
+
+    #[test]
+    fn supported_hashes_are_recognized() {
+        let argon2_hash = compute_password_hash(&password(), CONFIG).unwrap();
+
+        for password_hash in [argon2_hash, bcrypt_hash(), pbkdf2_hash()] {
+            assert!(is_supported_password_hash(&password_hash));
+        }
+    }
+
+    #[test]
+    fn unsupported_hashes_are_rejected() {
+        let password_hashes = [
+            "password123",
+            "$2b$12$tooshort",
+            "$pbkdf2-sha512$i=1000,l=64$c2FsdHNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ",
+            "5f4dcc3b5aa765d61d8327deb882cf99",
+        ];
+
+        for password_hash in password_hashes {
+            assert!(
+                !is_supported_password_hash(&Secret::new(password_hash.to_owned())),
+                "Failed for hash: {}",
+                password_hash
+            );
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Lets each key through at most once per interval, e.g. to throttle text messages per user and
+// per phone number. The state is kept in memory, so every instance of the service throttles on
+// its own.
+pub struct RateLimiter {
+    interval: Duration,
+    last_allowed: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_allowed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Only lets the request through if none of its keys was let through within the interval,
+    // in which case all of them start a new one
+    pub fn try_acquire(&self, keys: &[&str]) -> bool {
+        let now = Instant::now();
+        let mut last_allowed = self.last_allowed.lock().unwrap();
+
+        // Forgets the keys whose interval is over, so the map does not keep growing
+        last_allowed.retain(|_, allowed_at| now.duration_since(*allowed_at) < self.interval);
+
+        if keys.iter().any(|key| last_allowed.contains_key(*key)) {
+            return false;
+        }
+
+        for key in keys {
+            last_allowed.insert((*key).to_owned(), now);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_throttled_until_the_interval_is_over() {
+        let rate_limiter = RateLimiter::new(Duration::from_millis(50));
+
+        assert!(rate_limiter.try_acquire(&["user:1", "number:1"]));
+        assert!(!rate_limiter.try_acquire(&["user:1", "number:2"]));
+        assert!(!rate_limiter.try_acquire(&["user:2", "number:1"]));
+
+        // A rejected request does not start an interval for its other keys
+        assert!(rate_limiter.try_acquire(&["user:2", "number:2"]));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(rate_limiter.try_acquire(&["user:1", "number:1"]));
+    }
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use secrecy::Secret;
+
+// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    // Upgrade the connection with STARTTLS, refusing to send if the server doesn't support it
+    StartTls,
+    // Plaintext connection, only meant for relays on a trusted network
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    // Username and password used to AUTH, if the relay requires it
+    pub credentials: Option<(String, Secret<String>)>,
+    pub timeout: Duration,
+}
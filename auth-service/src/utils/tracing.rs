@@ -8,6 +8,8 @@ use tracing_error::ErrorLayer;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 
+use super::constants::REQUEST_ID_HEADER_NAME;
+
 pub fn init_tracing() -> Result<()> {
     // Create a formatting layer for tracing output with a compact format
     let fmt_layer = fmt::layer().compact();
@@ -28,10 +30,15 @@ pub fn init_tracing() -> Result<()> {
     Ok(())
 }
 
-// Creates a new tracing span with a unique request ID for each incoming request.
+// Creates a new tracing span with the unique request ID of each incoming request.
 // This helps in tracking and correlating logs for individual requests.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     tracing::span!(
         Level::INFO,
         "[REQUEST]",
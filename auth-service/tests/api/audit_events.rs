@@ -0,0 +1,133 @@
+use auth_service::{
+    domain::AuditEventKind, routes::ListAuditEventsResponse, utils::constants::ADMIN_API_KEY,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[api_test]
+async fn should_return_401_if_admin_api_key_incorrect() {
+    let response = app.get_audit_events("", "wrong_key").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid admin API key".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_record_signup_and_login_events() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong_password",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .get_audit_events(
+            &format!("email={}", random_email),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    let kinds: Vec<AuditEventKind> = body.events.iter().map(|event| event.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            AuditEventKind::LoginSucceeded,
+            AuditEventKind::LoginFailed,
+            AuditEventKind::Signup,
+        ]
+    );
+
+    let event = &body.events[0];
+    assert_eq!(event.ip, Some("127.0.0.1".to_owned()));
+    assert!(event.request_id.is_some());
+}
+
+#[api_test]
+async fn should_filter_and_paginate_events() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong_password",
+    });
+
+    for _ in 0..3 {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .get_audit_events(
+            &format!(
+                "email={}&kind=login_failed&page=2&page_size=2",
+                random_email
+            ),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    assert_eq!(body.page, 2);
+    assert_eq!(body.page_size, 2);
+    assert_eq!(body.events.len(), 1);
+    assert_eq!(body.events[0].kind, AuditEventKind::LoginFailed);
+}
@@ -0,0 +1,242 @@
+use auth_service::{
+    domain::AuditEventKind, routes::ListAuditEventsResponse, utils::constants::ADMIN_API_KEY,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const BREACHED_PASSWORD_ERROR: &str = "Password found in a data breach, choose another one";
+
+fn signup_body(email: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": password,
+        "requires2FA": false
+    })
+}
+
+fn login_body(email: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": password,
+    })
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error
+    );
+}
+
+// Extracts the password reset token from the last email sent through Postmark
+async fn get_reset_token(app: &TestApp) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)
+        .expect("Could not deserialize email request body");
+
+    assert_eq!(body["Subject"], "Your password must be changed");
+
+    let text = body["TextBody"].as_str().expect("No text body found");
+    let (_, token) = text.split_once("?reset=").expect("No reset link found");
+    token.split_whitespace().next().unwrap().to_owned()
+}
+
+#[api_test]
+async fn should_return_400_if_signing_up_with_breached_password() {
+    app.add_breached_password("password");
+
+    let response = app
+        .post_signup(&signup_body(&get_random_email(), "password"))
+        .await;
+
+    assert_error(response, 400, BREACHED_PASSWORD_ERROR).await;
+
+    let response = app
+        .post_signup(&signup_body(&get_random_email(), "password123"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[api_test]
+async fn should_require_password_reset_if_logging_in_with_breached_password() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // The password shows up in a breach after the user signed up
+    app.add_breached_password("password123");
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+    assert_error(response, 403, "Password reset required").await;
+
+    assert_eq!(app.send_queued_emails().await, 1);
+    let reset_token = get_reset_token(&app).await;
+
+    // Refused logins right after the flag don't send another link
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+    assert_error(response, 403, "Password reset required").await;
+    assert_eq!(app.send_queued_emails().await, 0);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": "password123",
+        }))
+        .await;
+    assert_error(response, 400, BREACHED_PASSWORD_ERROR).await;
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": "newpassword123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&login_body(&random_email, "newpassword123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_send_fresh_reset_link_on_login_while_password_reset_required() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Flagged long ago, the link sent back then has expired
+    sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE email = $1")
+        .bind(&random_email)
+        .execute(&app.pg_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+    assert_error(response, 403, "Password reset required").await;
+
+    assert_eq!(app.send_queued_emails().await, 1);
+    let reset_token = get_reset_token(&app).await;
+
+    // At most one link is sent per interval
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+    assert_error(response, 403, "Password reset required").await;
+    assert_eq!(app.send_queued_emails().await, 0);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": "newpassword123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&login_body(&random_email, "newpassword123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_not_send_reset_link_if_password_is_incorrect() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE email = $1")
+        .bind(&random_email)
+        .execute(&app.pg_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_login(&login_body(&random_email, "password456"))
+        .await;
+    assert_error(response, 401, "Incorrect credentials").await;
+    assert_eq!(app.send_queued_emails().await, 0);
+}
+
+#[api_test]
+async fn should_record_audit_event_when_breached_password_is_flagged() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.add_breached_password("password123");
+
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .get_audit_events(
+            &format!("email={}", random_email),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse");
+
+    let kinds: Vec<AuditEventKind> = body.events.iter().map(|event| event.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            AuditEventKind::LoginFailed,
+            AuditEventKind::BreachedPasswordDetected,
+            AuditEventKind::Signup,
+        ]
+    );
+}
@@ -0,0 +1,296 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "password123",
+    })
+}
+
+// Signs up a user without 2FA, logs them in and returns their auth token
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let response = app.post_login(&login_body(email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn mount_email_mock(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+fn change_email_body(new_email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "newEmail": new_email,
+        "password": "password123",
+    })
+}
+
+// Requests an email change and returns the tokens of the confirmation and cancel links
+async fn request_email_change(app: &TestApp, new_email: &str) -> (String, String) {
+    let response = app.post_change_email(&change_email_body(new_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.send_queued_emails().await, 2);
+
+    (
+        get_link_token(app, "Confirm your new email address", "?confirm-email=").await,
+        get_link_token(
+            app,
+            "Your email address is being changed",
+            "?cancel-email-change=",
+        )
+        .await,
+    )
+}
+
+// Extracts the token of a link from the last email with `subject` sent through Postmark
+async fn get_link_token(app: &TestApp, subject: &str, link: &str) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body = requests
+        .iter()
+        .rev()
+        .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+        .find(|body| body["Subject"] == subject)
+        .expect("No email with the subject found");
+
+    let text = body["TextBody"].as_str().expect("No text body found");
+    let (_, token) = text.split_once(link).expect("No link found");
+    token.split_whitespace().next().unwrap().to_owned()
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_not_authenticated() {
+    let response = app
+        .post_change_email(&change_email_body(&get_random_email()))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_new_email() {
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app.post_change_email(&change_email_body("invalid")).await;
+
+    assert_error(response, 400, "Invalid credentials").await;
+}
+
+#[api_test]
+async fn should_return_401_if_password_is_incorrect() {
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": get_random_email(),
+            "password": "wrong-password",
+        }))
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+    assert_eq!(app.send_queued_emails().await, 0);
+}
+
+#[api_test]
+async fn should_notify_owner_without_revealing_that_new_email_is_taken() {
+    let taken_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": taken_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    mount_email_mock(&app).await;
+
+    for new_email in [taken_email.to_uppercase(), random_email.clone()] {
+        let response = app.post_change_email(&change_email_body(&new_email)).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    assert_eq!(app.send_queued_emails().await, 2);
+
+    // Only the owners of the addresses hear about it, and nobody gets a confirmation link
+    let requests = app.email_server.received_requests().await.unwrap();
+    let mut recipients = Vec::new();
+    for request in &requests {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["Subject"], "Your email address is already in use");
+        recipients.push(body["To"].as_str().unwrap().to_owned());
+    }
+    recipients.sort();
+    let mut expected = vec![taken_email, random_email.clone()];
+    expected.sort();
+    assert_eq!(recipients, expected);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_change_email_only_once_confirmed() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    let token = signup_and_login(&app, &old_email).await;
+    mount_email_mock(&app).await;
+
+    let (confirm_token, cancel_token) = request_email_change(&app, &new_email).await;
+
+    // Nothing changes until the new address is confirmed
+    let response = app.post_login(&login_body(&new_email)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body(&old_email)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&login_body(&new_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Tokens issued for the old email are revoked, including the email change links
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_error(response, 401, "Invalid auth token").await;
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm_token }))
+        .await;
+    assert_error(response, 401, "Invalid auth token").await;
+    let response = app
+        .post_cancel_email_change(&serde_json::json!({ "token": cancel_token }))
+        .await;
+    assert_error(response, 401, "Invalid auth token").await;
+}
+
+#[api_test]
+async fn should_revoke_tokens_and_void_confirmation_if_cancelled() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    let token = signup_and_login(&app, &old_email).await;
+    mount_email_mock(&app).await;
+
+    let (confirm_token, cancel_token) = request_email_change(&app, &new_email).await;
+
+    let response = app
+        .post_cancel_email_change(&serde_json::json!({ "token": cancel_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_error(response, 401, "Invalid auth token").await;
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm_token }))
+        .await;
+    assert_error(response, 401, "Invalid auth token").await;
+
+    let response = app.post_login(&login_body(&old_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_409_if_new_email_was_taken_before_confirmation() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    signup_and_login(&app, &old_email).await;
+    mount_email_mock(&app).await;
+
+    let (confirm_token, _) = request_email_change(&app, &new_email).await;
+
+    let signup_body = serde_json::json!({
+        "email": new_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm_token }))
+        .await;
+    assert_error(response, 409, "User already exists").await;
+}
+
+#[api_test]
+async fn should_drop_pending_2fa_code_of_old_email() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    signup_and_login(&app, &old_email).await;
+    mount_email_mock(&app).await;
+
+    let (confirm_token, _) = request_email_change(&app, &new_email).await;
+
+    let old_email = Email::parse(Secret::new(old_email)).unwrap();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    app.two_fa_code_store
+        .add_code(old_email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let result = app
+        .two_fa_code_store
+        .consume_code(&old_email, &login_attempt_id, &code)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    let token = serde_json::json!({ "token": "invalid" });
+
+    let response = app.post_confirm_email_change(&token).await;
+    assert_error(response, 401, "Invalid auth token").await;
+
+    let response = app.post_cancel_email_change(&token).await;
+    assert_error(response, 401, "Invalid auth token").await;
+}
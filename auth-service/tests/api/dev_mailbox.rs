@@ -0,0 +1,107 @@
+use auth_service::routes::{DevMailboxResponse, TwoFactorAuthResponse};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a user with 2FA and logs in, which queues a 2FA code email
+async fn login_with_2fa(app: &TestApp, email: &str) -> TwoFactorAuthResponse {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+}
+
+async fn get_mailbox(app: &TestApp, recipient: &str) -> DevMailboxResponse {
+    let response = app.get_dev_mailbox(recipient, "application/json").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<DevMailboxResponse>()
+        .await
+        .expect("Could not deserialize response body to DevMailboxResponse")
+}
+
+#[api_test]
+async fn should_read_2fa_code_from_dev_mailbox() {
+    let random_email = get_random_email();
+    let login_response = login_with_2fa(&app, &random_email).await;
+
+    assert_eq!(app.capture_queued_emails().await, 1);
+
+    let mailbox = get_mailbox(&app, &random_email).await;
+
+    assert_eq!(mailbox.emails.len(), 1);
+    assert_eq!(mailbox.emails[0].subject, "2FA code");
+
+    let code = mailbox.emails[0]
+        .text_body
+        .split_whitespace()
+        .find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit()))
+        .expect("No 2FA code found");
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": code,
+    });
+
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_only_list_emails_sent_to_recipient() {
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+    login_with_2fa(&app, &first_email).await;
+    login_with_2fa(&app, &second_email).await;
+
+    assert_eq!(app.capture_queued_emails().await, 2);
+
+    let mailbox = get_mailbox(&app, &first_email).await;
+
+    assert_eq!(mailbox.emails.len(), 1);
+    assert_eq!(mailbox.emails[0].recipient, first_email);
+}
+
+#[api_test]
+async fn should_render_dev_mailbox_as_html_for_browsers() {
+    let random_email = get_random_email();
+    login_with_2fa(&app, &random_email).await;
+
+    assert_eq!(app.capture_queued_emails().await, 1);
+
+    let response = app.get_dev_mailbox(&random_email, "text/html").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+
+    let page = response.text().await.unwrap();
+
+    assert!(page.contains("2FA code"));
+    assert!(page.contains(&random_email));
+}
@@ -0,0 +1,121 @@
+use auth_service::domain::{Email, EmailMessage, EmailQueueError, QueuedEmailStatus};
+use secrecy::Secret;
+use test_helpers::api_test;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn message() -> EmailMessage {
+    EmailMessage {
+        subject: "Subject".to_owned(),
+        html_body: "<p>Body</p>".to_owned(),
+        text_body: "Body".to_owned(),
+    }
+}
+
+async fn enqueue_email(app: &TestApp) -> Uuid {
+    let recipient = Email::parse(Secret::new(get_random_email())).unwrap();
+
+    app.email_queue
+        .enqueue(&recipient, &message())
+        .await
+        .expect("Failed to enqueue email")
+}
+
+async fn get_status(app: &TestApp, email_id: Uuid) -> QueuedEmailStatus {
+    app.email_queue
+        .get_status(email_id)
+        .await
+        .expect("Failed to get email status")
+}
+
+#[api_test]
+async fn should_return_206_even_if_email_provider_fails() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // The test worker gives up after 3 attempts
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    for expected_attempts in [1, 1, 1, 0] {
+        assert_eq!(app.send_queued_emails().await, expected_attempts);
+    }
+}
+
+#[api_test]
+async fn should_mark_email_as_sent() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email_id = enqueue_email(&app).await;
+
+    assert_eq!(get_status(&app, email_id).await, QueuedEmailStatus::Pending);
+
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    assert_eq!(get_status(&app, email_id).await, QueuedEmailStatus::Sent);
+
+    // Sent emails are not sent again
+    assert_eq!(app.send_queued_emails().await, 0);
+}
+
+#[api_test]
+async fn should_retry_failed_emails_until_dead_lettered() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let email_id = enqueue_email(&app).await;
+
+    for expected_status in [
+        QueuedEmailStatus::Pending,
+        QueuedEmailStatus::Pending,
+        QueuedEmailStatus::Dead,
+    ] {
+        assert_eq!(app.send_queued_emails().await, 1);
+        assert_eq!(get_status(&app, email_id).await, expected_status);
+    }
+
+    assert_eq!(app.send_queued_emails().await, 0);
+}
+
+#[api_test]
+async fn should_return_email_not_found_for_unknown_id() {
+    let outcome = app.email_queue.get_status(Uuid::new_v4()).await;
+
+    assert_eq!(outcome.unwrap_err(), EmailQueueError::EmailNotFound);
+}
@@ -0,0 +1,138 @@
+use std::{sync::Arc, time::Duration};
+
+use auth_service::{routes::MetricsResponse, utils::constants::ADMIN_API_KEY, ErrorResponse};
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn get_metrics(app: &TestApp) -> MetricsResponse {
+    let response = app.get_metrics(ADMIN_API_KEY.expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<MetricsResponse>()
+        .await
+        .expect("Could not deserialize response body to MetricsResponse")
+}
+
+// Keeps a worker busy until the write lock on `gate` is released
+fn blocked_job(app: &TestApp, gate: &Arc<RwLock<()>>) -> JoinHandle<()> {
+    let executor = app.hashing_executor.clone();
+    let gate = gate.clone();
+    tokio::spawn(async move {
+        executor
+            .run(move || drop(gate.blocking_read()))
+            .await
+            .expect("Blocked job failed");
+    })
+}
+
+// Fills every worker and the whole queue of the executor
+async fn saturate(app: &TestApp, gate: &Arc<RwLock<()>>) -> Vec<JoinHandle<()>> {
+    let metrics = app.hashing_executor.metrics();
+    let mut jobs = Vec::new();
+
+    for _ in 0..metrics.workers {
+        jobs.push(blocked_job(app, gate));
+    }
+    while app.hashing_executor.metrics().in_flight < metrics.workers {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    for _ in 0..metrics.queue_capacity {
+        jobs.push(blocked_job(app, gate));
+    }
+    while app.hashing_executor.metrics().queued < metrics.queue_capacity {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    jobs
+}
+
+#[api_test]
+async fn should_return_503_if_password_hashing_is_saturated() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let gate = Arc::new(RwLock::new(()));
+    let guard = gate.write().await;
+    let jobs = saturate(&app, &gate).await;
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests in progress, try again later"
+    );
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 503);
+
+    let metrics = get_metrics(&app).await.password_hashing.unwrap();
+    assert_eq!(metrics.rejected, 2);
+    assert_eq!(metrics.in_flight, metrics.workers);
+    assert_eq!(metrics.queued, metrics.queue_capacity);
+
+    drop(guard);
+    for job in jobs {
+        job.await.unwrap();
+    }
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_report_password_hashing_metrics() {
+    let before = get_metrics(&app).await.password_hashing.unwrap();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let after = get_metrics(&app).await.password_hashing.unwrap();
+
+    assert_eq!(after.workers, 2);
+    assert_eq!(after.queue_capacity, 2);
+    assert_eq!(after.completed, before.completed + 1);
+    assert_eq!(after.queued, 0);
+    assert_eq!(after.in_flight, 0);
+    assert_eq!(after.rejected, 0);
+}
+
+#[api_test]
+async fn should_return_401_if_metrics_are_requested_without_admin_api_key() {
+    let response = app.get_metrics("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -1,18 +1,35 @@
-use std::{str::FromStr, sync::Arc};
+use std::{io::Write, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, domain::Email, get_postgres_pool, get_redis_client, services::{data_stores::{
+    app_state::{AppState, AuditLogType, BannedTokenStoreType, EmailQueueType, KnownDeviceStoreType, OutboxStoreType, TwoFACodeStoreType, UserStoreType}, domain::{split_sha1_hash, Email, Password, PasswordPolicy, PhoneNumber, User, UserStore}, get_postgres_pool, get_redis_connection_manager, services::{capture_email_client::{CaptureEmailClient, DevMailbox}, data_stores::{
+            postgres_audit_log::PostgresAuditLog, postgres_email_queue::PostgresEmailQueue, postgres_known_device_store::PostgresKnownDeviceStore,
+            postgres_banned_token_store::PostgresBannedTokenStore,
+            postgres_outbox_store::PostgresOutboxStore,
+            postgres_two_fa_code_store::PostgresTwoFACodeStore,
             postgres_user_store::PostgresUserStore,
             redis_banned_token_store::RedisBannedTokenStore,
             redis_two_fa_code_store::RedisTwoFACodeStore,
-        }, postmark_email_client::PostmarkEmailClient}, utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME}, Application
+        }, email_worker::{EmailWorker, EmailWorkerConfig}, hashing_executor::{HashingExecutor, HashingExecutorConfig}, local_password_checker::LocalPasswordChecker, postmark_email_client::PostmarkEmailClient, twilio_sms_client::TwilioSmsClient, webhook_dispatcher::{WebhookDispatcher, WebhookDispatcherConfig}}, utils::constants::{test, TokenStoreBackend, UserStoreBackend, ADMIN_API_KEY_HEADER_NAME, CSRF_COOKIE_NAME, CSRF_HEADER_NAME, DATABASE_URL, REDIS_HOST_NAME}, Application
+};
+#[cfg(feature = "sqlite")]
+use auth_service::services::data_stores::{
+    sqlite_audit_log::SqliteAuditLog, sqlite_banned_token_store::SqliteBannedTokenStore,
+    sqlite_email_queue::SqliteEmailQueue, sqlite_known_device_store::SqliteKnownDeviceStore,
+    sqlite_outbox_store::SqliteOutboxStore, sqlite_two_fa_code_store::SqliteTwoFACodeStore,
+    sqlite_user_store::SqliteUserStore,
+};
+use redis::aio::ConnectionManager;
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    Client, Method, Url,
 };
-use reqwest::{cookie::Jar, Client};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Connection, Executor, PgConnection, PgPool,
 };
+#[cfg(feature = "sqlite")]
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use wiremock::MockServer;
@@ -24,30 +41,164 @@ pub struct TestApp {
     pub two_fa_code_store: TwoFACodeStoreType,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
+    pub sms_server: MockServer,
+    pub email_queue: EmailQueueType,
+    pub email_worker: EmailWorker,
+    pub capture_email_worker: EmailWorker,
+    pub outbox_store: OutboxStoreType,
+    pub webhook_dispatcher: WebhookDispatcher,
+    pub pwned_passwords_dir: PathBuf,
+    pub pg_pool: PgPool,
+    pub hashing_executor: HashingExecutor,
     pub db_name: String,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::build(TokenStoreBackend::Redis, UserStoreBackend::Postgres).await
+    }
+
+    // Keeps 2FA codes and banned tokens in Postgres, as with `TOKEN_STORE_BACKEND=postgres`
+    pub async fn new_with_postgres_token_stores() -> Self {
+        Self::build(TokenStoreBackend::Postgres, UserStoreBackend::Postgres).await
+    }
+
+    // Keeps everything in SQLite, as with `USER_STORE_BACKEND=sqlite` and
+    // `TOKEN_STORE_BACKEND=sqlite`. Helpers reading from `pg_pool` find nothing.
+    #[cfg(feature = "sqlite")]
+    pub async fn new_with_sqlite_stores() -> Self {
+        Self::build(TokenStoreBackend::Sqlite, UserStoreBackend::Sqlite).await
+    }
+
+    async fn build(
+        token_store_backend: TokenStoreBackend,
+        user_store_backend: UserStoreBackend,
+    ) -> Self {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(&db_name).await;
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        // Small enough for tests to saturate it
+        let hashing_executor = HashingExecutor::new(HashingExecutorConfig {
+            workers: 2,
+            queue_capacity: 2,
+        });
+        // In memory, so it costs next to nothing when the app doesn't use it
+        #[cfg(feature = "sqlite")]
+        let sqlite_pool = configure_sqlite().await;
+        let (user_store, known_device_store, audit_log, outbox_store, email_queue): (
+            UserStoreType,
+            KnownDeviceStoreType,
+            AuditLogType,
+            OutboxStoreType,
+            EmailQueueType,
+        ) = match user_store_backend {
+            UserStoreBackend::Postgres => (
+                Arc::new(PostgresUserStore::new(
+                    pg_pool.clone(),
+                    hashing_executor.clone(),
+                )),
+                Arc::new(PostgresKnownDeviceStore::new(pg_pool.clone())),
+                Arc::new(PostgresAuditLog::new(pg_pool.clone())),
+                Arc::new(PostgresOutboxStore::new(pg_pool.clone())),
+                Arc::new(PostgresEmailQueue::new(pg_pool.clone())),
+            ),
+            #[cfg(feature = "sqlite")]
+            UserStoreBackend::Sqlite => (
+                Arc::new(SqliteUserStore::new(
+                    sqlite_pool.clone(),
+                    hashing_executor.clone(),
+                )),
+                Arc::new(SqliteKnownDeviceStore::new(sqlite_pool.clone())),
+                Arc::new(SqliteAuditLog::new(sqlite_pool.clone())),
+                Arc::new(SqliteOutboxStore::new(sqlite_pool.clone())),
+                Arc::new(SqliteEmailQueue::new(sqlite_pool.clone())),
+            ),
+            #[cfg(not(feature = "sqlite"))]
+            UserStoreBackend::Sqlite => unreachable!("SQLite needs the `sqlite` feature"),
+        };
+
+        // Failed deliveries are retried on the next round, so tests don't have to wait
+        let webhook_dispatcher = WebhookDispatcher::new(
+            outbox_store.clone(),
+            Client::new(),
+            WebhookDispatcherConfig {
+                base_backoff: Duration::ZERO,
+                max_attempts: 3,
+                ..Default::default()
+            },
+        );
 
-        let redis_conn = Arc::new(RwLock::new(configure_redis()));
-        let banned_token_store =
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let (banned_token_store, two_fa_code_store): (BannedTokenStoreType, TwoFACodeStoreType) =
+            match token_store_backend {
+                TokenStoreBackend::Redis => {
+                    let redis_conn = configure_redis().await;
+                    (
+                        Arc::new(RedisBannedTokenStore::new(redis_conn.clone())),
+                        Arc::new(RedisTwoFACodeStore::new(redis_conn)),
+                    )
+                }
+                TokenStoreBackend::Postgres => (
+                    Arc::new(PostgresBannedTokenStore::new(pg_pool.clone())),
+                    Arc::new(PostgresTwoFACodeStore::new(pg_pool.clone())),
+                ),
+                #[cfg(feature = "sqlite")]
+                TokenStoreBackend::Sqlite => (
+                    Arc::new(SqliteBannedTokenStore::new(sqlite_pool.clone())),
+                    Arc::new(SqliteTwoFACodeStore::new(sqlite_pool)),
+                ),
+                #[cfg(not(feature = "sqlite"))]
+                TokenStoreBackend::Sqlite => unreachable!("SQLite needs the `sqlite` feature"),
+            };
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
+        let email_client = Arc::new(configure_postmark_email_client(base_url));
+
+        // Tests send queued emails explicitly, and failed emails are retried on the next round
+        let email_worker = EmailWorker::new(
+            email_queue.clone(),
+            email_client,
+            EmailWorkerConfig {
+                base_backoff: Duration::ZERO,
+                max_attempts: 3,
+                ..Default::default()
+            },
+        );
+
+        // Sends queued emails to the dev mailbox instead of the Postmark mock
+        let dev_mailbox = DevMailbox::default();
+        let capture_email_worker = EmailWorker::new(
+            email_queue.clone(),
+            Arc::new(CaptureEmailClient::new(dev_mailbox.clone())),
+            EmailWorkerConfig::default(),
+        );
+
+        let sms_server = MockServer::start().await;
+        let sms_client = Arc::new(RwLock::new(configure_twilio_sms_client(sms_server.uri())));
+
+        // Every test gets its own breached password dataset, empty until passwords are added
+        let pwned_passwords_dir = std::env::temp_dir().join(format!("pwned-passwords-{}", db_name));
+        std::fs::create_dir_all(&pwned_passwords_dir).expect("Failed to create dataset directory");
+        let breached_password_checker = Arc::new(RwLock::new(
+            LocalPasswordChecker::new(pwned_passwords_dir.clone())
+                .expect("Failed to open dataset directory"),
+        ));
+
         let app_state = AppState::new(
             user_store,
             banned_token_store.clone(),
             two_fa_code_store.clone(),
-            email_client,
-        );
+            email_queue.clone(),
+            audit_log,
+            outbox_store.clone(),
+            known_device_store,
+            sms_client,
+            breached_password_checker,
+        )
+        .with_password_policy(test_password_policy())
+        .with_breached_password_check_at_login()
+        .with_dev_mailbox(dev_mailbox)
+        .with_hashing_executor(hashing_executor.clone());
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -73,11 +224,89 @@ impl TestApp {
             two_fa_code_store,
             http_client,
             email_server,
+            sms_server,
+            email_queue,
+            email_worker,
+            capture_email_worker,
+            outbox_store,
+            webhook_dispatcher,
+            pwned_passwords_dir,
+            pg_pool,
+            hashing_executor,
             db_name,
             clean_up_called: false,
         }
     }
 
+    // The id of the user signed up with `email`, which tokens refer to them by
+    pub async fn get_user_id(&self, email: &str) -> Uuid {
+        sqlx::query_scalar("SELECT id FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_one(&self.pg_pool)
+            .await
+            .expect("Failed to get user id.")
+    }
+
+    // The user signed up with `email`, as stored
+    pub async fn get_user(&self, email: &str) -> User {
+        let email = Email::parse(Secret::new(email.to_owned())).expect("Invalid email.");
+        PostgresUserStore::new(self.pg_pool.clone(), self.hashing_executor.clone())
+            .get_user(&email)
+            .await
+            .expect("Failed to get user.")
+    }
+
+    // The code of the last 2FA email queued for `email`, as the code stores don't give codes back
+    pub async fn get_two_fa_code(&self, email: &str) -> String {
+        let text_body: String = sqlx::query_scalar(
+            r#"
+            SELECT text_body FROM email_queue
+            WHERE recipient = $1 AND subject = '2FA code'
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(email)
+        .fetch_one(&self.pg_pool)
+        .await
+        .expect("No 2FA email queued.");
+
+        text_body
+            .trim_start_matches("Your 2FA code is ")
+            .chars()
+            .take(6)
+            .collect()
+    }
+
+    // Sends the emails queued so far and returns how many were attempted
+    pub async fn send_queued_emails(&self) -> usize {
+        self.email_worker
+            .run_once()
+            .await
+            .expect("Failed to send queued emails")
+    }
+
+    // Sends the emails queued so far to the dev mailbox and returns how many were attempted
+    pub async fn capture_queued_emails(&self) -> usize {
+        self.capture_email_worker
+            .run_once()
+            .await
+            .expect("Failed to capture queued emails")
+    }
+
+    // Adds the password to the breached password dataset
+    pub fn add_breached_password(&self, password: &str) {
+        let password = Password::parse(Secret::new(password.to_owned())).unwrap();
+        let (prefix, suffix) = split_sha1_hash(&password);
+
+        let mut range_file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.pwned_passwords_dir.join(format!("{}.txt", prefix)))
+            .expect("Failed to open range file");
+        writeln!(range_file, "{}:42", suffix).expect("Failed to write range file");
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
             .get(&format!("{}/", &self.address))
@@ -110,7 +339,59 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_login_with_user_agent<Body>(
+        &self,
+        body: &Body,
+        user_agent: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/login", &self.address))
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_report_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/report-login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
+        let mut request = self.http_client.post(&format!("{}/logout", &self.address));
+
+        // Echo the CSRF cookie back the way a browser client would
+        if let Some(csrf_token) = self.get_cookie(CSRF_COOKIE_NAME) {
+            request = request.header(CSRF_HEADER_NAME, csrf_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_without_csrf_token(&self) -> reqwest::Response {
         self.http_client
             .post(&format!("{}/logout", &self.address))
             .send()
@@ -118,6 +399,131 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_logout_from_origin(&self, origin: &str) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(&format!("{}/logout", &self.address))
+            .header("Origin", origin);
+
+        if let Some(csrf_token) = self.get_cookie(CSRF_COOKIE_NAME) {
+            request = request.header(CSRF_HEADER_NAME, csrf_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/logout", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post_with_csrf_token("email", body).await
+    }
+
+    pub async fn post_confirm_email_change<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/email/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_cancel_email_change<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/email/cancel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_profile(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/me", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_profile<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.send_with_csrf_token(Method::PATCH, "me", body).await
+    }
+
+    pub async fn delete_profile<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.send_with_csrf_token(Method::DELETE, "me", body).await
+    }
+
+    pub async fn post_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post_with_csrf_token("phone-number", body).await
+    }
+
+    pub async fn post_verify_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post_with_csrf_token("phone-number/verify", body).await
+    }
+
+    pub async fn post_two_fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post_with_csrf_token("two-fa-channel", body).await
+    }
+
+    // Echoes the CSRF cookie back the way a browser client would
+    async fn post_with_csrf_token<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.send_with_csrf_token(Method::POST, path, body).await
+    }
+
+    async fn send_with_csrf_token<Body>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .request(method, &format!("{}/{}", &self.address, path))
+            .json(body);
+
+        if let Some(csrf_token) = self.get_cookie(CSRF_COOKIE_NAME) {
+            request = request.header(CSRF_HEADER_NAME, csrf_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -142,12 +548,111 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_verify_token_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/verify-token", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_introspect(
+        &self,
+        token: &str,
+        client_credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(&format!("{}/introspect", &self.address))
+            .form(&[("token", token)]);
+
+        if let Some((client_id, client_secret)) = client_credentials {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub fn get_cookie(&self, name: &str) -> Option<String> {
+        let url = Url::parse(&self.address).expect("Failed to parse URL");
+        let cookies = self.cookie_jar.cookies(&url)?;
+        cookies
+            .to_str()
+            .ok()?
+            .split("; ")
+            .filter_map(|cookie| cookie.split_once('='))
+            .find(|(cookie_name, _)| *cookie_name == name)
+            .map(|(_, value)| value.to_owned())
+    }
+
+    pub async fn get_audit_events(&self, query: &str, api_key: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/audit-events?{}", &self.address, query))
+            .header(ADMIN_API_KEY_HEADER_NAME, api_key)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import_users<Body>(&self, body: &Body, api_key: &str) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(&format!("{}/admin/users/import", &self.address))
+            .header(ADMIN_API_KEY_HEADER_NAME, api_key)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self, api_key: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/metrics", &self.address))
+            .header(ADMIN_API_KEY_HEADER_NAME, api_key)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webhook(&self, url: &str, api_key: &str) -> reqwest::Response {
+        self.http_client
+            .post(&format!("{}/admin/webhooks", &self.address))
+            .header(ADMIN_API_KEY_HEADER_NAME, api_key)
+            .json(&serde_json::json!({ "url": url }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_webhooks(&self, api_key: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/admin/webhooks", &self.address))
+            .header(ADMIN_API_KEY_HEADER_NAME, api_key)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self, recipient: &str, accept: &str) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/dev/mailbox", &self.address))
+            .query(&[("recipient", recipient)])
+            .header(reqwest::header::ACCEPT, accept)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
         }
 
         delete_database(&self.db_name).await;
+        let _ = std::fs::remove_dir_all(&self.pwned_passwords_dir);
 
         self.clean_up_called = true;
     }
@@ -165,6 +670,25 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
+// A fresh in-memory database. It lives as long as its only connection, which is kept open.
+#[cfg(feature = "sqlite")]
+pub async fn configure_sqlite() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to create SQLite connection pool.");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&pool)
+        .await
+        .expect("Failed to run SQLite migrations.");
+
+    pool
+}
+
 async fn configure_postgresql(db_name: &str) -> PgPool {
     let postgresql_conn_url = DATABASE_URL.to_owned();
 
@@ -241,10 +765,9 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+async fn configure_redis() -> ConnectionManager {
+    get_redis_connection_manager(REDIS_HOST_NAME.to_owned())
+        .await
         .expect("Failed to get Redis connection")
 }
 
@@ -260,3 +783,31 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
+
+fn configure_twilio_sms_client(base_url: String) -> TwilioSmsClient {
+    let sender = PhoneNumber::parse(Secret::new(test::sms_client::SENDER.to_owned())).unwrap();
+
+    let http_client = Client::builder()
+        .timeout(test::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        base_url,
+        "test_account_sid".to_owned(),
+        Secret::new("auth_token".to_owned()),
+        sender,
+        http_client,
+    )
+}
+
+pub const DENY_LISTED_PASSWORD: &str = "letmein123";
+
+// Strength scoring is turned off so tests can keep using simple passwords
+fn test_password_policy() -> PasswordPolicy {
+    PasswordPolicy {
+        min_strength: 0,
+        ..Default::default()
+    }
+    .with_deny_list([DENY_LISTED_PASSWORD])
+}
@@ -0,0 +1,258 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use auth_service::{
+    routes::{ImportUsersResponse, SkipReason},
+    utils::{
+        constants::ADMIN_API_KEY,
+        password_hashing::{needs_rehash, Argon2Config},
+    },
+    ErrorResponse,
+};
+use pbkdf2::Pbkdf2;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn bcrypt_hash(password: &str) -> String {
+    bcrypt::hash(password, 4).unwrap()
+}
+
+fn pbkdf2_hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let params = pbkdf2::Params {
+        rounds: 1000,
+        ..Default::default()
+    };
+
+    Pbkdf2
+        .hash_password_customized(password.as_bytes(), None, None, params, &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn imported_user(email: &str, password_hash: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "passwordHash": password_hash,
+    })
+}
+
+async fn import_users(app: &TestApp, users: Vec<serde_json::Value>) -> ImportUsersResponse {
+    let response = app
+        .post_import_users(
+            &serde_json::json!({ "users": users }),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ImportUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ImportUsersResponse")
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> u16 {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+    .status()
+    .as_u16()
+}
+
+async fn get_password_hash(app: &TestApp, email: &str) -> Secret<String> {
+    let password_hash: String =
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_one(&app.pg_pool)
+            .await
+            .expect("Failed to fetch password hash");
+
+    Secret::new(password_hash)
+}
+
+#[api_test]
+async fn should_return_401_if_admin_api_key_is_invalid() {
+    let body = serde_json::json!({
+        "users": [imported_user(&get_random_email(), &bcrypt_hash("password123"))],
+    });
+
+    let response = app.post_import_users(&body, "invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_rehash_legacy_hashes_on_first_login() {
+    let bcrypt_email = get_random_email();
+    let pbkdf2_email = get_random_email();
+
+    let response = import_users(
+        &app,
+        vec![
+            imported_user(&bcrypt_email, &bcrypt_hash("password123")),
+            imported_user(&pbkdf2_email, &pbkdf2_hash("password123")),
+        ],
+    )
+    .await;
+
+    assert_eq!(response.imported, 2);
+    assert!(response.skipped.is_empty());
+
+    for email in [&bcrypt_email, &pbkdf2_email] {
+        assert_eq!(login(&app, email, "password123").await, 200);
+
+        let password_hash = get_password_hash(&app, email).await;
+        assert!(password_hash.expose_secret().starts_with("$argon2id$"));
+        assert!(!needs_rehash(&password_hash, &Argon2Config::default()));
+
+        assert_eq!(login(&app, email, "password123").await, 200);
+    }
+}
+
+#[api_test]
+async fn should_keep_argon2_hashes_with_current_params() {
+    let random_email = get_random_email();
+    let password_hash = app
+        .hashing_executor
+        .compute_password_hash(
+            Secret::new("password123".to_owned()),
+            Argon2Config::default(),
+        )
+        .await
+        .unwrap();
+
+    let response = import_users(
+        &app,
+        vec![imported_user(&random_email, password_hash.expose_secret())],
+    )
+    .await;
+    assert_eq!(response.imported, 1);
+
+    assert_eq!(login(&app, &random_email, "password123").await, 200);
+
+    assert_eq!(
+        get_password_hash(&app, &random_email).await.expose_secret(),
+        password_hash.expose_secret()
+    );
+}
+
+#[api_test]
+async fn should_not_rehash_legacy_hash_on_failed_login() {
+    let random_email = get_random_email();
+    let password_hash = bcrypt_hash("password123");
+
+    import_users(&app, vec![imported_user(&random_email, &password_hash)]).await;
+
+    assert_eq!(login(&app, &random_email, "password456").await, 401);
+
+    assert_eq!(
+        get_password_hash(&app, &random_email).await.expose_secret(),
+        &password_hash
+    );
+}
+
+#[api_test]
+async fn should_skip_users_that_cannot_be_imported() {
+    let existing_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": existing_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let random_email = get_random_email();
+    let response = import_users(
+        &app,
+        vec![
+            imported_user("invalid", &bcrypt_hash("password123")),
+            // Unsalted MD5
+            imported_user(&get_random_email(), "482c811da5d5b4bc6d497ffa98491e38"),
+            imported_user(&existing_email, &bcrypt_hash("password456")),
+            imported_user(&random_email, &bcrypt_hash("password123")),
+        ],
+    )
+    .await;
+
+    assert_eq!(response.imported, 1);
+
+    let reasons: Vec<SkipReason> = response.skipped.iter().map(|s| s.reason).collect();
+    assert_eq!(
+        reasons,
+        vec![
+            SkipReason::InvalidEmail,
+            SkipReason::UnsupportedPasswordHash,
+            SkipReason::UserAlreadyExists,
+        ]
+    );
+
+    // The existing user keeps their password
+    assert_eq!(login(&app, &existing_email, "password123").await, 200);
+    assert_eq!(login(&app, &random_email, "password123").await, 200);
+}
+
+#[api_test]
+async fn should_skip_password_hashes_with_excessive_costs() {
+    let costly_bcrypt_hash = bcrypt_hash("password123").replacen("$04$", "$20$", 1);
+    let costly_argon2_hash = app
+        .hashing_executor
+        .compute_password_hash(
+            Secret::new("password123".to_owned()),
+            Argon2Config::default(),
+        )
+        .await
+        .unwrap()
+        .expose_secret()
+        .replacen("m=15000", "m=4194304", 1);
+
+    let response = import_users(
+        &app,
+        vec![
+            imported_user(&get_random_email(), &costly_bcrypt_hash),
+            imported_user(&get_random_email(), &costly_argon2_hash),
+        ],
+    )
+    .await;
+
+    assert_eq!(response.imported, 0);
+
+    let reasons: Vec<SkipReason> = response.skipped.iter().map(|s| s.reason).collect();
+    assert_eq!(
+        reasons,
+        vec![
+            SkipReason::ExcessivePasswordHashCost,
+            SkipReason::ExcessivePasswordHashCost,
+        ]
+    );
+}
+
+#[api_test]
+async fn should_return_413_if_too_many_users() {
+    let password_hash = bcrypt_hash("password123");
+    let users: Vec<serde_json::Value> = (0..1001)
+        .map(|_| imported_user(&get_random_email(), &password_hash))
+        .collect();
+
+    let response = app
+        .post_import_users(
+            &serde_json::json!({ "users": users }),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 413);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many users in one import, 1000 at most"
+    );
+}
@@ -0,0 +1,188 @@
+use auth_service::{
+    routes::IntrospectResponse,
+    utils::constants::{INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[api_test]
+async fn should_return_401_if_client_credentials_missing_or_incorrect() {
+    let test_cases = [
+        None,
+        Some((INTROSPECTION_CLIENT_ID.as_str(), "wrong_secret")),
+    ];
+
+    for client_credentials in test_cases {
+        let response = app.post_introspect("token", client_credentials).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for credentials: {:?}",
+            client_credentials
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid client credentials".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_inactive_if_invalid_token() {
+    let client_credentials = (
+        INTROSPECTION_CLIENT_ID.as_str(),
+        INTROSPECTION_CLIENT_SECRET.expose_secret().as_str(),
+    );
+
+    let response = app
+        .post_introspect("invalid_token", Some(client_credentials))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert_eq!(body, IntrospectResponse::inactive());
+}
+
+#[api_test]
+async fn should_return_claims_if_valid_token() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let client_credentials = (
+        INTROSPECTION_CLIENT_ID.as_str(),
+        INTROSPECTION_CLIENT_SECRET.expose_secret().as_str(),
+    );
+
+    let response = app
+        .post_introspect(auth_cookie.value(), Some(client_credentials))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert!(body.active);
+    // Tokens refer to users by id, not by email
+    let user_id = app.get_user_id(&random_email).await;
+    assert_eq!(body.sub, Some(user_id.to_string()));
+    assert!(body.exp.is_some());
+    assert!(body.iat.is_some());
+    assert_eq!(body.roles, Some(vec!["user".to_owned()]));
+}
+
+#[api_test]
+async fn should_return_inactive_if_banned_token() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let token = auth_cookie.value().to_owned();
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let client_credentials = (
+        INTROSPECTION_CLIENT_ID.as_str(),
+        INTROSPECTION_CLIENT_SECRET.expose_secret().as_str(),
+    );
+
+    let response = app.post_introspect(&token, Some(client_credentials)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+
+    assert!(!body.active);
+}
+
+#[api_test]
+async fn should_return_400_if_token_missing() {
+    let response = app
+        .http_client
+        .post(&format!("{}/introspect", &app.address))
+        .basic_auth(
+            INTROSPECTION_CLIENT_ID.as_str(),
+            Some(INTROSPECTION_CLIENT_SECRET.expose_secret()),
+        )
+        .form(&[("token_type_hint", "access_token")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "invalid_request".to_owned()
+    );
+}
@@ -1,10 +1,9 @@
 use auth_service::{
-    domain::Email,
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::{auth::TOKEN_TTL_SECONDS, constants::JWT_COOKIE_NAME},
     ErrorResponse,
 };
-use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
 use test_helpers::api_test;
 use wiremock::{
     matchers::{method, path},
@@ -51,7 +50,7 @@ async fn should_return_400_if_invalid_input() {
         }),
         serde_json::json!({
             "email": "test@example.com",
-            "password": "pass123",
+            "password": "",
         }),
     ];
 
@@ -136,6 +135,34 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(Duration::from_secs(TOKEN_TTL_SECONDS as u64))
+    );
+}
+
+#[api_test]
+async fn should_return_200_if_email_differs_in_case_from_signup() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": format!(" {} ", random_email.to_uppercase()),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
 }
 
 #[api_test]
@@ -168,22 +195,25 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     let json_body = response
         .json::<TwoFactorAuthResponse>()
         .await
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
     assert_eq!(json_body.message, "2FA required".to_owned());
-
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-
-    let code_tuple = two_fa_code_store
-        .get_code(&Email::parse(Secret::new(random_email)).unwrap())
-        .await
-        .expect("Failed to get 2FA code");
-
-    assert_eq!(
-        code_tuple.0.as_ref().expose_secret(),
-        &json_body.login_attempt_id
-    );
+    assert!(!json_body.login_attempt_id.is_empty());
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value = serde_json::from_slice(&requests[0].body)
+        .expect("Could not deserialize email request body");
+    let code = app.get_two_fa_code(&random_email).await;
+
+    assert_eq!(email_body["Subject"], "2FA code");
+    assert!(email_body["TextBody"].as_str().unwrap().contains(&code));
+    assert!(email_body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("<strong>{}</strong>", code)));
 }
@@ -1,4 +1,7 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    utils::constants::{CSRF_COOKIE_NAME, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
 use reqwest::Url;
 use secrecy::Secret;
 use test_helpers::api_test;
@@ -31,6 +34,10 @@ async fn should_return_401_if_invalid_token() {
         ),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=csrf; SameSite=Lax; Path=/", CSRF_COOKIE_NAME),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
 
     let response = app.post_logout().await;
 
@@ -89,7 +96,50 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(auth_cookie.value().is_empty());
 
-    let banned_token_store = app.banned_token_store.read().await;
+    let banned_token_store = &app.banned_token_store;
+    let contains_token = banned_token_store
+        .contains_token(&Secret::new(token.to_string()))
+        .await
+        .expect("Failed to check if token is banned");
+
+    assert!(contains_token);
+}
+
+#[api_test]
+async fn should_return_200_if_valid_bearer_token() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let token = auth_cookie.value();
+
+    let response = app.post_logout_with_bearer(token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let banned_token_store = &app.banned_token_store;
     let contains_token = banned_token_store
         .contains_token(&Secret::new(token.to_string()))
         .await
@@ -143,3 +193,74 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
         "Missing auth token".to_owned()
     );
 }
+
+#[api_test]
+async fn should_return_403_if_csrf_token_missing() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(app.get_cookie(CSRF_COOKIE_NAME).is_some());
+
+    let response = app.post_logout_without_csrf_token().await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "CSRF check failed".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_403_if_origin_not_allowed() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout_from_origin("http://evil.example.com").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.post_logout_from_origin("http://localhost:8000").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -1,7 +1,23 @@
+mod audit_events;
+mod breached_passwords;
+mod change_email;
+mod dev_mailbox;
+mod email_queue;
+mod hashing_executor;
 mod helpers;
+mod import_users;
+mod introspect;
 mod login;
 mod logout;
+mod new_device;
+mod password_hashing;
+mod password_policy;
+mod phone_number;
+mod postgres_token_stores;
+mod profile;
 mod root;
 mod signup;
+mod user_stores;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
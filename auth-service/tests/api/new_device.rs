@@ -0,0 +1,225 @@
+use auth_service::{routes::ReportLoginResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+fn login_body(email: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": password,
+    })
+}
+
+// Extracts the "this wasn't me" token from the last email sent through Postmark
+async fn get_report_token(app: &TestApp) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)
+        .expect("Could not deserialize email request body");
+
+    assert_eq!(body["Subject"], "New sign-in to your account");
+
+    let text = body["TextBody"].as_str().expect("No text body found");
+    let (_, token) = text.split_once("?report=").expect("No report link found");
+    token.split_whitespace().next().unwrap().to_owned()
+}
+
+#[api_test]
+async fn should_not_send_email_for_first_or_known_device() {
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..2 {
+        let response = app
+            .post_login_with_user_agent(&login_body(&random_email, "password123"), "device-a")
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    assert_eq!(app.send_queued_emails().await, 0);
+}
+
+#[api_test]
+async fn should_send_email_for_new_device() {
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app
+        .post_login_with_user_agent(&login_body(&random_email, "password123"), "device-a")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Only the first login from device-b is reported
+    for _ in 0..2 {
+        let response = app
+            .post_login_with_user_agent(&login_body(&random_email, "password123"), "device-b")
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let text = body["TextBody"].as_str().unwrap();
+
+    assert!(text.contains("Device: device-b"));
+    assert!(text.contains("IP address: 127.0.0.1"));
+}
+
+#[api_test]
+async fn should_revoke_session_and_require_password_reset_if_login_reported() {
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app
+        .post_login_with_user_agent(&login_body(&random_email, "password123"), "device-a")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login_with_user_agent(&login_body(&random_email, "password123"), "device-b")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let session_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let report_token = get_report_token(&app).await;
+
+    let response = app
+        .post_report_login(&serde_json::json!({ "token": report_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let password_reset_token = response
+        .json::<ReportLoginResponse>()
+        .await
+        .expect("Could not deserialize response body to ReportLoginResponse")
+        .password_reset_token;
+
+    let response = app.post_verify_token_with_bearer(&session_token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Report links are single-use, they can't mint more reset tokens
+    let response = app
+        .post_report_login(&serde_json::json!({ "token": report_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Password reset required".to_owned()
+    );
+
+    let reset_body = serde_json::json!({
+        "token": password_reset_token,
+        "newPassword": "newpassword123",
+    });
+
+    let response = app.post_reset_password(&reset_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Reset tokens are single-use
+    let response = app.post_reset_password(&reset_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&login_body(&random_email, "password123"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login_with_user_agent(&login_body(&random_email, "newpassword123"), "device-b")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Nor lock the account again once its password was reset
+    let response = app
+        .post_report_login(&serde_json::json!({ "token": report_token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_report_or_reset_token() {
+    let response = app
+        .post_report_login(&serde_json::json!({ "token": "invalid_token" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": "invalid_token",
+            "newPassword": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,136 @@
+use auth_service::utils::password_hashing::{needs_rehash, Argon2Config};
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn get_password_hash(app: &TestApp, email: &str) -> Secret<String> {
+    let password_hash: String =
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_one(&app.pg_pool)
+            .await
+            .expect("Failed to fetch password hash");
+
+    Secret::new(password_hash)
+}
+
+async fn set_password_hash(app: &TestApp, email: &str, password_hash: &Secret<String>) {
+    sqlx::query("UPDATE users SET password_hash = $2 WHERE email = $1")
+        .bind(email)
+        .bind(password_hash.expose_secret())
+        .execute(&app.pg_pool)
+        .await
+        .expect("Failed to update password hash");
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) -> u16 {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    login(app, email).await
+}
+
+async fn login(app: &TestApp, email: &str) -> u16 {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+    .status()
+    .as_u16()
+}
+
+#[api_test]
+async fn should_upgrade_weaker_password_hash_on_login() {
+    let random_email = get_random_email();
+
+    assert_eq!(signup_and_login(&app, &random_email).await, 200);
+
+    // As if the user signed up before the parameters were strengthened
+    let weak_config = Argon2Config {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+    let weak_hash = app
+        .hashing_executor
+        .compute_password_hash(Secret::new("password123".to_owned()), weak_config)
+        .await
+        .unwrap();
+    set_password_hash(&app, &random_email, &weak_hash).await;
+
+    assert_eq!(login(&app, &random_email).await, 200);
+
+    let upgraded_hash = get_password_hash(&app, &random_email).await;
+    assert_ne!(upgraded_hash.expose_secret(), weak_hash.expose_secret());
+    assert!(!needs_rehash(&upgraded_hash, &Argon2Config::default()));
+
+    assert_eq!(login(&app, &random_email).await, 200);
+}
+
+#[api_test]
+async fn should_keep_current_password_hash_on_login() {
+    let random_email = get_random_email();
+
+    assert_eq!(signup_and_login(&app, &random_email).await, 200);
+    let password_hash = get_password_hash(&app, &random_email).await;
+
+    assert_eq!(login(&app, &random_email).await, 200);
+
+    assert_eq!(
+        get_password_hash(&app, &random_email).await.expose_secret(),
+        password_hash.expose_secret()
+    );
+}
+
+#[api_test]
+async fn should_not_upgrade_password_hash_on_failed_login() {
+    let random_email = get_random_email();
+
+    assert_eq!(signup_and_login(&app, &random_email).await, 200);
+
+    let weak_config = Argon2Config {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+    let weak_hash = app
+        .hashing_executor
+        .compute_password_hash(Secret::new("password123".to_owned()), weak_config)
+        .await
+        .unwrap();
+    set_password_hash(&app, &random_email, &weak_hash).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        get_password_hash(&app, &random_email).await.expose_secret(),
+        weak_hash.expose_secret()
+    );
+}
+
+#[api_test]
+async fn should_return_500_if_password_hash_cannot_be_verified() {
+    let random_email = get_random_email();
+
+    assert_eq!(signup_and_login(&app, &random_email).await, 200);
+
+    // Not a wrong password, the stored hash itself is broken
+    let broken_hash = Secret::new("corrupted".to_owned());
+    set_password_hash(&app, &random_email, &broken_hash).await;
+
+    assert_eq!(login(&app, &random_email).await, 500);
+}
@@ -0,0 +1,157 @@
+use auth_service::{
+    domain::PasswordRule,
+    utils::auth::{generate_action_token, TokenPurpose},
+    ErrorResponse,
+};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp, DENY_LISTED_PASSWORD};
+
+const POLICY_ERROR: &str = "Password does not meet the requirements";
+
+fn signup_body(email: &str, password: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": password,
+        "requires2FA": false
+    })
+}
+
+// Returns the rules listed in a password policy error
+async fn violated_rules(response: reqwest::Response) -> Vec<PasswordRule> {
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert_eq!(body.error, POLICY_ERROR);
+    body.violations.into_iter().map(|v| v.rule).collect()
+}
+
+#[api_test]
+async fn should_return_400_if_password_is_too_short() {
+    let response = app
+        .post_signup(&signup_body(&get_random_email(), "pass123"))
+        .await;
+
+    assert_eq!(
+        violated_rules(response).await,
+        vec![PasswordRule::MinLength]
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_password_is_too_long() {
+    let response = app
+        .post_signup(&signup_body(&get_random_email(), &"a".repeat(129)))
+        .await;
+
+    assert_eq!(
+        violated_rules(response).await,
+        vec![PasswordRule::MaxLength]
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_password_contains_email() {
+    let random_email = get_random_email();
+    let (local_part, _) = random_email.split_once('@').unwrap();
+
+    let response = app
+        .post_signup(&signup_body(
+            &random_email,
+            &format!("{}!", local_part.to_uppercase()),
+        ))
+        .await;
+
+    assert_eq!(
+        violated_rules(response).await,
+        vec![PasswordRule::ContainsEmail]
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_password_is_deny_listed() {
+    let response = app
+        .post_signup(&signup_body(
+            &get_random_email(),
+            &DENY_LISTED_PASSWORD.to_uppercase(),
+        ))
+        .await;
+
+    assert_eq!(violated_rules(response).await, vec![PasswordRule::DenyList]);
+}
+
+#[api_test]
+async fn should_list_every_violated_rule() {
+    let random_email = get_random_email();
+    let (local_part, _) = random_email.split_once('@').unwrap();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, &local_part.repeat(4)))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    let rules: Vec<PasswordRule> = body.violations.iter().map(|v| v.rule).collect();
+
+    assert_eq!(
+        rules,
+        vec![PasswordRule::MaxLength, PasswordRule::ContainsEmail]
+    );
+    assert!(body.violations.iter().all(|v| !v.message.is_empty()));
+}
+
+#[api_test]
+async fn should_return_400_if_new_password_violates_policy_on_reset() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&signup_body(&random_email, "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let user = app.get_user(&random_email).await;
+    let reset_token = generate_action_token(&user, TokenPurpose::PasswordReset, 600, None).unwrap();
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": DENY_LISTED_PASSWORD,
+        }))
+        .await;
+    assert_eq!(violated_rules(response).await, vec![PasswordRule::DenyList]);
+
+    // The token is still usable after a rejected password
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": reset_token,
+            "newPassword": "newpassword123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_login_password_is_too_long() {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "a".repeat(129),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+
+    assert_eq!(body.error, "Invalid credentials");
+    assert!(body.violations.is_empty());
+}
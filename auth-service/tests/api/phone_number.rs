@@ -0,0 +1,289 @@
+use auth_service::{
+    routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse,
+};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PHONE_NUMBER: &str = "+40712345678";
+
+// Signs up a user with 2FA and logs them in through an email 2FA code
+async fn login_with_email_2fa(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let login_attempt_id = get_login_attempt_id(response).await;
+    let code = app.get_two_fa_code(email).await;
+    let verify_2fa_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    });
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+}
+
+async fn get_login_attempt_id(response: reqwest::Response) -> String {
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn mount_sms_mock(app: &TestApp, expected_calls: u64) {
+    Mock::given(path_regex("/Messages.json$"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(expected_calls)
+        .mount(&app.sms_server)
+        .await;
+}
+
+// Extracts the code from the form encoded body of the last SMS sent through the provider
+async fn get_last_sms_code(app: &TestApp) -> String {
+    let requests = app.sms_server.received_requests().await.unwrap();
+    let body = String::from_utf8(requests.last().unwrap().body.clone()).unwrap();
+
+    let (_, code) = body.split_once("+is+").expect("No code found");
+    code.chars().take(6).collect()
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_not_authenticated() {
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[api_test]
+async fn should_return_401_if_user_of_token_is_deleted() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 0).await;
+
+    sqlx::query("DELETE FROM users WHERE email = $1")
+        .bind(&random_email)
+        .execute(&app.pg_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_error(response, 401, "Invalid auth token").await;
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_phone_number() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 0).await;
+
+    for phone_number in ["", "0712345678", "+0712345678", "+40 712 345 678", "+4071"] {
+        let response = app
+            .post_phone_number(&serde_json::json!({ "phoneNumber": phone_number }))
+            .await;
+
+        assert_error(response, 400, "Invalid phone number").await;
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_incorrect_verification_code() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 1).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let code = get_last_sms_code(&app).await;
+    let incorrect_code = if code == "123456" { "654321" } else { "123456" };
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": incorrect_code }))
+        .await;
+
+    assert_error(response, 400, "Invalid verification code").await;
+}
+
+#[api_test]
+async fn should_return_400_if_sms_channel_chosen_before_verification() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 1).await;
+
+    let response = app
+        .post_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_error(response, 400, "Phone number not verified").await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_error(response, 400, "Phone number not verified").await;
+}
+
+#[api_test]
+async fn should_send_2fa_code_by_sms_once_phone_number_verified() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 2).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let code = get_last_sms_code(&app).await;
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Verification codes are single-use
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_error(response, 400, "Invalid verification code").await;
+
+    let response = app
+        .post_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    // The code went out by SMS rather than by email
+    assert_eq!(app.send_queued_emails().await, 0);
+
+    let requests = app.sms_server.received_requests().await.unwrap();
+    let body = String::from_utf8(requests.last().unwrap().body.clone()).unwrap();
+    assert!(body.contains("To=%2B40712345678"));
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": get_login_attempt_id(response).await,
+        "2FACode": get_last_sms_code(&app).await,
+    });
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_reject_phone_number_changes_without_csrf_token() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 0).await;
+
+    let response = app
+        .http_client
+        .post(&format!("{}/phone-number", &app.address))
+        .json(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_error(response, 403, "CSRF check failed").await;
+}
+
+#[api_test]
+async fn should_return_429_if_verification_code_was_just_sent() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 1).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Neither the same user nor the same number gets another code straight away
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+40712345679" }))
+        .await;
+    assert_error(response, 429, "Too many requests, try again later").await;
+
+    let other_email = get_random_email();
+    login_with_email_2fa(&app, &other_email).await;
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_error(response, 429, "Too many requests, try again later").await;
+}
+
+#[api_test]
+async fn should_store_phone_verification_code_hashed() {
+    let random_email = get_random_email();
+    login_with_email_2fa(&app, &random_email).await;
+    mount_sms_mock(&app, 1).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let stored_code: Option<String> =
+        sqlx::query_scalar("SELECT phone_verification_code FROM users WHERE email = $1")
+            .bind(&random_email)
+            .fetch_one(&app.pg_pool)
+            .await
+            .unwrap();
+
+    let stored_code = stored_code.expect("No verification code stored");
+    assert_ne!(stored_code, get_last_sms_code(&app).await);
+}
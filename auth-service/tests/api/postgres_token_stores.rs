@@ -0,0 +1,294 @@
+use auth_service::{
+    domain::{
+        BannedTokenStore, Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError,
+    },
+    routes::TwoFactorAuthResponse,
+    services::{
+        data_stores::{
+            postgres_banned_token_store::PostgresBannedTokenStore,
+            postgres_two_fa_code_store::PostgresTwoFACodeStore,
+        },
+        expired_entries_purger::{ExpiredEntriesPurger, ExpiredEntriesPurgerConfig},
+    },
+    utils::constants::JWT_COOKIE_NAME,
+};
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn random_email() -> Email {
+    Email::parse(Secret::new(get_random_email())).unwrap()
+}
+
+fn purger(app: &TestApp) -> ExpiredEntriesPurger {
+    ExpiredEntriesPurger::new(
+        PostgresTwoFACodeStore::new(app.pg_pool.clone()),
+        PostgresBannedTokenStore::new(app.pg_pool.clone()),
+        ExpiredEntriesPurgerConfig::default(),
+    )
+}
+
+async fn expire(app: &TestApp, table: &str) {
+    sqlx::query(&format!(
+        "UPDATE {} SET expires_at = NOW() - INTERVAL '1 second'",
+        table
+    ))
+    .execute(&app.pg_pool)
+    .await
+    .expect("Failed to expire entries.");
+}
+
+#[api_test]
+async fn should_add_replace_and_remove_2fa_codes() {
+    let store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let email = random_email();
+    let first_login_attempt_id = LoginAttemptId::default();
+    let first_code = TwoFACode::default();
+
+    let result = store
+        .consume_code(&email, &first_login_attempt_id, &first_code)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+    store
+        .add_code(
+            email.clone(),
+            first_login_attempt_id.clone(),
+            first_code.clone(),
+        )
+        .await
+        .unwrap();
+
+    // A new login attempt replaces the code of the previous one
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+    let result = store
+        .consume_code(&email, &first_login_attempt_id, &first_code)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
+
+    store.remove_code(&email).await.unwrap();
+    let result = store.consume_code(&email, &login_attempt_id, &code).await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[api_test]
+async fn should_store_2fa_codes_hashed() {
+    let store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let email = random_email();
+    let code = TwoFACode::default();
+
+    store
+        .add_code(email.clone(), LoginAttemptId::default(), code.clone())
+        .await
+        .unwrap();
+
+    let stored_code: String = sqlx::query_scalar("SELECT code FROM two_fa_codes WHERE email = $1")
+        .bind(email.as_ref().expose_secret())
+        .fetch_one(&app.pg_pool)
+        .await
+        .expect("No 2FA code stored");
+    assert_ne!(&stored_code, code.as_ref().expose_secret());
+}
+
+#[api_test]
+async fn should_consume_2fa_code_only_once() {
+    let store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    // An incorrect code leaves it in place
+    let result = store
+        .consume_code(&email, &LoginAttemptId::default(), &code)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
+
+    let (first, second) = tokio::join!(
+        store.consume_code(&email, &login_attempt_id, &code),
+        store.consume_code(&email, &login_attempt_id, &code)
+    );
+    let consumed = [first, second].into_iter().filter(Result::is_ok).count();
+    assert_eq!(consumed, 1);
+
+    let result = store.consume_code(&email, &login_attempt_id, &code).await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[api_test]
+async fn should_not_accept_expired_2fa_codes() {
+    let store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+    expire(&app, "two_fa_codes").await;
+
+    let result = store.consume_code(&email, &login_attempt_id, &code).await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[api_test]
+async fn should_ban_tokens_until_they_expire() {
+    let store = PostgresBannedTokenStore::new(app.pg_pool.clone());
+    let token = Secret::new("token".to_owned());
+
+    assert!(!store.contains_token(&token).await.unwrap());
+
+    // Banning a token twice is not an error
+    for _ in 0..2 {
+        store
+            .add_token(Secret::new(token.expose_secret().to_owned()))
+            .await
+            .unwrap();
+    }
+    assert!(store.contains_token(&token).await.unwrap());
+
+    expire(&app, "banned_tokens").await;
+    assert!(!store.contains_token(&token).await.unwrap());
+}
+
+#[api_test]
+async fn should_purge_only_expired_entries() {
+    let two_fa_code_store = PostgresTwoFACodeStore::new(app.pg_pool.clone());
+    let banned_token_store = PostgresBannedTokenStore::new(app.pg_pool.clone());
+
+    let expired_email = random_email();
+    two_fa_code_store
+        .add_code(
+            expired_email,
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+    banned_token_store
+        .add_token(Secret::new("expired".to_owned()))
+        .await
+        .unwrap();
+    expire(&app, "two_fa_codes").await;
+    expire(&app, "banned_tokens").await;
+
+    let email = random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    two_fa_code_store
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+    let token = Secret::new("token".to_owned());
+    banned_token_store
+        .add_token(Secret::new(token.expose_secret().to_owned()))
+        .await
+        .unwrap();
+
+    let purger = purger(&app);
+    assert_eq!(purger.run_once().await.unwrap(), 2);
+    assert_eq!(purger.run_once().await.unwrap(), 0);
+
+    assert_eq!(
+        two_fa_code_store
+            .consume_code(&email, &login_attempt_id, &code)
+            .await,
+        Ok(())
+    );
+    assert!(banned_token_store.contains_token(&token).await.unwrap());
+}
+
+// The API flows only go through the stores of the app, so the one below runs on the Postgres
+// backend as deployed with `TOKEN_STORE_BACKEND=postgres`
+#[tokio::test]
+async fn should_log_in_with_2fa_and_log_out_with_postgres_token_stores() {
+    let mut app = TestApp::new_with_postgres_token_stores().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    // The code went into Postgres rather than Redis
+    let stored_codes: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM two_fa_codes WHERE email = $1")
+            .bind(&random_email)
+            .fetch_one(&app.pg_pool)
+            .await
+            .expect("Failed to count 2FA codes");
+    assert_eq!(stored_codes, 1);
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": app.get_two_fa_code(&random_email).await,
+    });
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Codes are single-use
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is banned in Postgres, and rejected from then on
+    assert!(PostgresBannedTokenStore::new(app.pg_pool.clone())
+        .contains_token(&Secret::new(token.clone()))
+        .await
+        .unwrap());
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
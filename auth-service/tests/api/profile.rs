@@ -0,0 +1,221 @@
+use auth_service::{
+    routes::{ProfileResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use test_helpers::api_test;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+}
+
+fn login_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "password123",
+    })
+}
+
+async fn get_profile(app: &TestApp) -> ProfileResponse {
+    let response = app.get_profile().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ProfileResponse>()
+        .await
+        .expect("Could not deserialize response body to ProfileResponse")
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_not_authenticated() {
+    assert_error(app.get_profile().await, 400, "Missing auth token").await;
+
+    let response = app
+        .patch_profile(&serde_json::json!({ "displayName": "Ada" }))
+        .await;
+    assert_error(response, 400, "Missing auth token").await;
+}
+
+#[api_test]
+async fn should_return_profile_of_logged_in_user() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let user = app.get_user(&random_email).await;
+    assert_eq!(user.last_login_at, None);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let profile = get_profile(&app).await;
+
+    assert_eq!(profile.id, user.id);
+    assert_eq!(profile.email, random_email);
+    assert_eq!(profile.display_name, None);
+    assert!(!profile.requires_2fa);
+    assert_eq!(profile.created_at, user.created_at);
+    assert_eq!(profile.updated_at, user.updated_at);
+    assert_eq!(profile.password_changed_at, Some(user.created_at));
+    assert!(profile.last_login_at.unwrap() >= profile.created_at);
+}
+
+#[api_test]
+async fn should_stamp_last_login_at_once_2fa_is_verified() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.get_user(&random_email).await.last_login_at, None);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let code = app.get_two_fa_code(&random_email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(app.get_user(&random_email).await.last_login_at.is_some());
+}
+
+#[api_test]
+async fn should_update_display_name() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    app.post_login(&login_body(&random_email)).await;
+    let created_profile = get_profile(&app).await;
+
+    let response = app
+        .patch_profile(&serde_json::json!({ "displayName": "  Ada Lovelace " }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let profile = response
+        .json::<ProfileResponse>()
+        .await
+        .expect("Could not deserialize response body to ProfileResponse");
+
+    assert_eq!(profile.display_name, Some("Ada Lovelace".to_owned()));
+    assert!(profile.updated_at > created_profile.updated_at);
+    assert_eq!(profile.last_login_at, created_profile.last_login_at);
+    assert_eq!(get_profile(&app).await.display_name, profile.display_name);
+
+    // Missing fields are left alone, null clears them
+    let response = app.patch_profile(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(get_profile(&app).await.display_name, profile.display_name);
+
+    let response = app
+        .patch_profile(&serde_json::json!({ "displayName": null }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(get_profile(&app).await.display_name, None);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_display_name() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    app.post_login(&login_body(&random_email)).await;
+
+    let too_long = "a".repeat(101);
+    for display_name in ["", "   ", "Ada\nLovelace", too_long.as_str()] {
+        let response = app
+            .patch_profile(&serde_json::json!({ "displayName": display_name }))
+            .await;
+
+        assert_error(response, 400, "Invalid display name").await;
+    }
+
+    assert_eq!(get_profile(&app).await.display_name, None);
+}
+
+#[api_test]
+async fn should_return_403_if_csrf_token_missing() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    app.post_login(&login_body(&random_email)).await;
+
+    let response = app
+        .http_client
+        .patch(&format!("{}/me", &app.address))
+        .json(&serde_json::json!({ "displayName": "Ada" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_error(response, 403, "CSRF check failed").await;
+}
+
+#[api_test]
+async fn should_delete_account_once_password_is_confirmed() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    app.post_login(&login_body(&random_email)).await;
+
+    let response = app
+        .delete_profile(&serde_json::json!({ "password": "password456" }))
+        .await;
+    assert_error(response, 401, "Incorrect credentials").await;
+    get_profile(&app).await;
+
+    let response = app
+        .delete_profile(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let user_id: Option<Uuid> = sqlx::query_scalar("SELECT id FROM users WHERE email = $1")
+        .bind(&random_email)
+        .fetch_optional(&app.pg_pool)
+        .await
+        .unwrap();
+    assert_eq!(user_id, None);
+
+    let response = app.post_login(&login_body(&random_email)).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_delete_account_without_authentication() {
+    let response = app
+        .delete_profile(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_error(response, 400, "Missing auth token").await;
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use auth_service::{routes::SignupResponse, ErrorResponse};
 use test_helpers::api_test;
 
@@ -81,11 +83,6 @@ async fn should_return_400_if_invalid_input() {
             "password": "password123",
             "requires2FA": true
         }),
-        serde_json::json!({
-            "email": "test@example.com",
-            "password": "pass123",
-            "requires2FA": true
-        }),
     ];
 
     for input in inputs.iter() {
@@ -134,3 +131,129 @@ async fn should_return_409_if_email_already_exists() {
         "User already exists".to_owned()
     );
 }
+
+#[api_test]
+async fn should_return_409_if_email_exists_in_a_different_case() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email.to_uppercase(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": format!("  {}  ", random_email),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_return_409_if_stored_email_differs_in_case() {
+    let random_email = get_random_email();
+
+    // Stored as is, like an account kept in its original case
+    sqlx::query("INSERT INTO users (email, password_hash) VALUES ($1, 'hash')")
+        .bind(random_email.to_uppercase())
+        .execute(&app.pg_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "User already exists".to_owned()
+    );
+}
+
+fn signup_body(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    })
+}
+
+// Waits until a query of the app is blocked on a row lock held by the test
+async fn wait_for_blocked_query(app: &TestApp) {
+    loop {
+        let blocked: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pg_stat_activity \
+             WHERE datname = current_database() AND wait_event_type = 'Lock'",
+        )
+        .fetch_one(&app.pg_pool)
+        .await
+        .unwrap();
+
+        if blocked > 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[api_test]
+async fn should_not_hold_back_signups_while_another_is_in_progress() {
+    let blocked_email = get_random_email();
+    let blocked_body = signup_body(&blocked_email);
+    let other_body = signup_body(&get_random_email());
+
+    // An uncommitted account makes a signup with the same email wait on the unique constraint
+    let mut transaction = app.pg_pool.begin().await.unwrap();
+    sqlx::query("INSERT INTO users (email, password_hash) VALUES ($1, 'hash')")
+        .bind(&blocked_email)
+        .execute(&mut *transaction)
+        .await
+        .unwrap();
+
+    let blocked_signup = app.post_signup(&blocked_body);
+    let other_signup = async {
+        wait_for_blocked_query(&app).await;
+
+        let response = tokio::time::timeout(Duration::from_secs(10), app.post_signup(&other_body))
+            .await
+            .expect("Signup was held back by the one in progress");
+        assert_eq!(response.status().as_u16(), 201);
+
+        transaction.commit().await.unwrap();
+    };
+
+    let (blocked_response, ()) = tokio::join!(blocked_signup, other_signup);
+    assert_eq!(blocked_response.status().as_u16(), 409);
+}
+
+#[api_test]
+async fn should_create_only_one_account_for_concurrent_signups_with_the_same_email() {
+    let signup_body = signup_body(&get_random_email());
+
+    let responses = tokio::join!(
+        app.post_signup(&signup_body),
+        app.post_signup(&signup_body),
+        app.post_signup(&signup_body),
+    );
+
+    let mut statuses = [responses.0, responses.1, responses.2].map(|r| r.status().as_u16());
+    statuses.sort();
+    assert_eq!(statuses, [201, 409, 409]);
+}
@@ -0,0 +1,700 @@
+#[cfg(feature = "sqlite")]
+use auth_service::services::{
+    data_stores::sqlite_user_store::SqliteUserStore,
+    hashing_executor::{HashingExecutor, HashingExecutorConfig},
+};
+use auth_service::{
+    domain::{
+        DisplayName, Email, ImportedUser, Password, PhoneNumber, TwoFAChannel, TwoFACode, User,
+        UserId, UserStore, UserStoreError,
+    },
+    services::data_stores::postgres_user_store::PostgresUserStore,
+    utils::password_hashing::{compute_password_hash, needs_rehash, Argon2Config},
+};
+use chrono::{Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+
+#[cfg(feature = "sqlite")]
+use crate::helpers::configure_sqlite;
+use crate::helpers::{get_random_email, TestApp};
+
+// Every check runs against each user store, so they all behave the same behind the trait
+macro_rules! user_store_tests {
+    ($($check:ident),* $(,)?) => {
+        mod postgres {
+            use super::*;
+
+            $(
+                #[api_test]
+                async fn $check() {
+                    let store =
+                        PostgresUserStore::new(app.pg_pool.clone(), app.hashing_executor.clone());
+                    super::$check(&store).await;
+                }
+            )*
+        }
+
+        #[cfg(feature = "sqlite")]
+        mod sqlite {
+            use super::*;
+
+            $(
+                #[tokio::test]
+                async fn $check() {
+                    super::$check(&sqlite_user_store().await).await;
+                }
+            )*
+        }
+    };
+}
+
+user_store_tests!(
+    should_add_and_get_users,
+    should_reject_duplicate_emails,
+    should_validate_credentials,
+    should_update_password_and_clear_reset_flag,
+    should_delete_users,
+    should_change_email_and_revoke_tokens,
+    should_verify_phone_number,
+    should_reject_expired_phone_verification_code,
+    should_set_display_name_and_last_login_at,
+    should_import_users_with_their_password_hash,
+    should_upgrade_weak_password_hashes_on_login,
+    should_reject_emails_differing_only_in_case,
+);
+
+#[cfg(feature = "sqlite")]
+async fn sqlite_user_store() -> SqliteUserStore {
+    let hashing_executor = HashingExecutor::new(HashingExecutorConfig {
+        workers: 2,
+        queue_capacity: 2,
+    });
+    SqliteUserStore::new(configure_sqlite().await, hashing_executor)
+}
+
+fn random_email() -> Email {
+    Email::parse(Secret::new(get_random_email())).unwrap()
+}
+
+fn password(password: &str) -> Password {
+    Password::parse(Secret::new(password.to_owned())).unwrap()
+}
+
+async fn add_user(store: &impl UserStore, requires_2fa: bool) -> User {
+    let user = User::new(random_email(), password("password123"), requires_2fa);
+    store.add_user(user.clone()).await.unwrap();
+    user
+}
+
+async fn should_add_and_get_users(store: &impl UserStore) {
+    let user = add_user(store, true).await;
+
+    let stored_user = store.get_user(&user.email).await.unwrap();
+    assert_eq!(stored_user.id, user.id);
+    assert_eq!(stored_user.email, user.email);
+    assert!(stored_user.requires_2fa);
+    assert!(!stored_user.password_reset_required);
+    assert_eq!(stored_user.phone_number, None);
+    assert_eq!(stored_user.two_fa_channel, TwoFAChannel::Email);
+    assert_eq!(stored_user.token_version, 0);
+    assert_eq!(stored_user.display_name, None);
+    assert_eq!(stored_user.last_login_at, None);
+
+    // The password is only kept hashed
+    assert_ne!(stored_user.password, user.password);
+
+    let stored_user = store.get_user_by_id(&user.id).await.unwrap();
+    assert_eq!(stored_user.email, user.email);
+
+    assert_eq!(
+        store.get_user(&random_email()).await.unwrap_err(),
+        UserStoreError::UserNotFound
+    );
+    assert_eq!(
+        store.get_user_by_id(&UserId::default()).await.unwrap_err(),
+        UserStoreError::UserNotFound
+    );
+}
+
+async fn should_reject_duplicate_emails(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+
+    let other_user = User::new(user.email.clone(), password("password456"), false);
+    assert_eq!(
+        store.add_user(other_user).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+}
+
+async fn should_validate_credentials(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+
+    assert!(store
+        .validate_user(&user.email, &password("password123"))
+        .await
+        .is_ok());
+    assert_eq!(
+        store
+            .validate_user(&user.email, &password("password456"))
+            .await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+    assert_eq!(
+        store
+            .validate_user(&random_email(), &password("password123"))
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_update_password_and_clear_reset_flag(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+
+    store
+        .set_password_reset_required(&user.email, true)
+        .await
+        .unwrap();
+    assert!(
+        store
+            .get_user(&user.email)
+            .await
+            .unwrap()
+            .password_reset_required
+    );
+
+    store
+        .update_password(&user.email, password("password456"))
+        .await
+        .unwrap();
+
+    let updated_user = store.get_user(&user.email).await.unwrap();
+    assert!(!updated_user.password_reset_required);
+    assert!(updated_user.password_changed_at.is_some());
+    assert_eq!(
+        store
+            .validate_user(&user.email, &password("password123"))
+            .await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+    assert!(store
+        .validate_user(&user.email, &password("password456"))
+        .await
+        .is_ok());
+
+    assert_eq!(
+        store
+            .update_password(&random_email(), password("password456"))
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store
+            .set_password_reset_required(&random_email(), true)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_delete_users(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+
+    assert!(store.delete_user(&user.email).await.is_ok());
+    assert_eq!(
+        store.get_user(&user.email).await.unwrap_err(),
+        UserStoreError::UserNotFound
+    );
+    assert_eq!(
+        store.delete_user(&user.email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_change_email_and_revoke_tokens(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+    let other_user = add_user(store, false).await;
+    let new_email = random_email();
+
+    store
+        .change_email(&user.id, new_email.clone())
+        .await
+        .unwrap();
+
+    let changed_user = store.get_user(&new_email).await.unwrap();
+    assert_eq!(changed_user.id, user.id);
+    assert_eq!(changed_user.token_version, 1);
+    assert_eq!(
+        store.get_user(&user.email).await.unwrap_err(),
+        UserStoreError::UserNotFound
+    );
+
+    assert_eq!(
+        store.change_email(&other_user.id, new_email).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+    assert_eq!(
+        store.change_email(&UserId::default(), random_email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    store.revoke_tokens(&user.id).await.unwrap();
+    assert_eq!(
+        store.get_user_by_id(&user.id).await.unwrap().token_version,
+        2
+    );
+    assert_eq!(
+        store.revoke_tokens(&UserId::default()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_verify_phone_number(store: &impl UserStore) {
+    let user = add_user(store, true).await;
+    let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_owned())).unwrap();
+    let code = TwoFACode::parse(Secret::new("123456".to_owned())).unwrap();
+    let wrong_code = TwoFACode::parse(Secret::new("654321".to_owned())).unwrap();
+
+    store
+        .set_phone_number(
+            &user.email,
+            phone_number.clone(),
+            code.clone(),
+            Utc::now() + Duration::minutes(10),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .set_two_fa_channel(&user.email, TwoFAChannel::Sms)
+            .await,
+        Err(UserStoreError::PhoneNumberNotVerified)
+    );
+    assert_eq!(
+        store.verify_phone_number(&user.email, &wrong_code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+
+    store.verify_phone_number(&user.email, &code).await.unwrap();
+    store
+        .set_two_fa_channel(&user.email, TwoFAChannel::Sms)
+        .await
+        .unwrap();
+
+    let verified_user = store.get_user(&user.email).await.unwrap();
+    assert_eq!(verified_user.phone_number, Some(phone_number));
+    assert!(verified_user.phone_number_verified);
+    assert_eq!(verified_user.two_fa_channel, TwoFAChannel::Sms);
+
+    // Codes are single-use
+    assert_eq!(
+        store.verify_phone_number(&user.email, &code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+    assert_eq!(
+        store.verify_phone_number(&random_email(), &code).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_reject_expired_phone_verification_code(store: &impl UserStore) {
+    let user = add_user(store, true).await;
+    let phone_number = PhoneNumber::parse(Secret::new("+40712345678".to_owned())).unwrap();
+    let code = TwoFACode::parse(Secret::new("123456".to_owned())).unwrap();
+
+    store
+        .set_phone_number(
+            &user.email,
+            phone_number,
+            code.clone(),
+            Utc::now() - Duration::seconds(1),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.verify_phone_number(&user.email, &code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+    assert!(
+        !store
+            .get_user(&user.email)
+            .await
+            .unwrap()
+            .phone_number_verified
+    );
+}
+
+async fn should_set_display_name_and_last_login_at(store: &impl UserStore) {
+    let user = add_user(store, false).await;
+
+    let display_name = DisplayName::parse("Ada").unwrap();
+    store
+        .set_display_name(&user.id, Some(display_name.clone()))
+        .await
+        .unwrap();
+    let updated_user = store.get_user(&user.email).await.unwrap();
+    assert_eq!(updated_user.display_name, Some(display_name));
+    assert!(updated_user.updated_at >= updated_user.created_at);
+
+    // PostgreSQL keeps timestamps to the microsecond
+    let now = Utc::now();
+    store.set_last_login_at(&user.id, now).await.unwrap();
+    let logged_in_user = store.get_user(&user.email).await.unwrap();
+    assert_eq!(
+        logged_in_user.last_login_at.map(|at| at.timestamp_micros()),
+        Some(now.timestamp_micros())
+    );
+    assert_eq!(logged_in_user.updated_at, updated_user.updated_at);
+
+    store.set_display_name(&user.id, None).await.unwrap();
+    assert_eq!(
+        store.get_user(&user.email).await.unwrap().display_name,
+        None
+    );
+
+    assert_eq!(
+        store.set_display_name(&UserId::default(), None).await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store.set_last_login_at(&UserId::default(), now).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn should_import_users_with_their_password_hash(store: &impl UserStore) {
+    // Another store's hash of "password123", so the imported user logs in with it
+    let user = add_user(store, false).await;
+    let password_hash = store.get_user(&user.email).await.unwrap().password;
+    let imported_user = ImportedUser {
+        id: UserId::default(),
+        email: random_email(),
+        password_hash: password_hash.as_ref().clone(),
+        requires_2fa: true,
+    };
+
+    store.import_user(imported_user.clone()).await.unwrap();
+
+    let stored_user = store.get_user_by_id(&imported_user.id).await.unwrap();
+    assert_eq!(stored_user.email, imported_user.email);
+    assert!(stored_user.requires_2fa);
+    assert!(store
+        .validate_user(&imported_user.email, &password("password123"))
+        .await
+        .is_ok());
+
+    assert_eq!(
+        store.import_user(imported_user).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+}
+
+async fn should_upgrade_weak_password_hashes_on_login(store: &impl UserStore) {
+    // Weaker than the default parameters the store hashes with
+    let weak_config = Argon2Config {
+        memory_cost: 1024,
+        time_cost: 1,
+        parallelism: 1,
+    };
+    let weak_password_hash =
+        compute_password_hash(&Secret::new("password123".to_owned()), weak_config).unwrap();
+    let imported_user = ImportedUser {
+        id: UserId::default(),
+        email: random_email(),
+        password_hash: weak_password_hash.clone(),
+        requires_2fa: false,
+    };
+    store.import_user(imported_user.clone()).await.unwrap();
+
+    store
+        .validate_user(&imported_user.email, &password("password123"))
+        .await
+        .unwrap();
+
+    let upgraded_password_hash = store.get_user(&imported_user.email).await.unwrap().password;
+    assert_ne!(
+        upgraded_password_hash.as_ref().expose_secret(),
+        weak_password_hash.expose_secret()
+    );
+    assert!(!needs_rehash(
+        upgraded_password_hash.as_ref(),
+        &Argon2Config::default()
+    ));
+    assert!(store
+        .validate_user(&imported_user.email, &password("password123"))
+        .await
+        .is_ok());
+}
+
+async fn should_reject_emails_differing_only_in_case(store: &impl UserStore) {
+    // As stored when the local part keeps its case
+    let email = get_random_email();
+    let mixed_case = Email::parse_with(Secret::new(format!("Ada.{}", email)), false).unwrap();
+    let lowercase = Email::parse(Secret::new(format!("ada.{}", email))).unwrap();
+
+    let user = User::new(mixed_case.clone(), password("password123"), false);
+    store.add_user(user).await.unwrap();
+    assert_eq!(store.get_user(&mixed_case).await.unwrap().email, mixed_case);
+
+    // Lookups match the case, yet no other account can take the address in another case
+    assert_eq!(
+        store.get_user(&lowercase).await.unwrap_err(),
+        UserStoreError::UserNotFound
+    );
+    let other_user = User::new(lowercase.clone(), password("password123"), false);
+    assert_eq!(
+        store.add_user(other_user).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+
+    let other_user = add_user(store, false).await;
+    assert_eq!(
+        store.change_email(&other_user.id, lowercase).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+}
+
+// The API flows that go beyond the user store, on an app keeping everything in SQLite
+#[cfg(feature = "sqlite")]
+mod sqlite_app {
+    use auth_service::{
+        domain::{DomainEvent, DomainEventKind},
+        routes::TwoFactorAuthResponse,
+        utils::constants::{ADMIN_API_KEY, JWT_COOKIE_NAME},
+        ErrorResponse,
+    };
+    use secrecy::ExposeSecret;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::helpers::{get_random_email, TestApp};
+
+    fn login_body(email: &str, password: &str) -> serde_json::Value {
+        serde_json::json!({
+            "email": email,
+            "password": password,
+        })
+    }
+
+    async fn signup(app: &TestApp, email: &str) {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+        assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+    }
+
+    // The subject and text body of the last email sent through Postmark
+    async fn last_email(app: &TestApp) -> (String, String) {
+        let requests = app.email_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body)
+            .expect("Could not deserialize email request body");
+
+        (
+            body["Subject"].as_str().unwrap().to_owned(),
+            body["TextBody"].as_str().unwrap().to_owned(),
+        )
+    }
+
+    async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+        assert_eq!(response.status().as_u16(), status);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn should_require_password_reset_at_login() {
+        let mut app = TestApp::new_with_sqlite_stores().await;
+        let random_email = get_random_email();
+        signup(&app, &random_email).await;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&app.email_server)
+            .await;
+
+        // Flags the user in SQLite
+        app.add_breached_password("password123");
+        let response = app
+            .post_login(&login_body(&random_email, "password123"))
+            .await;
+        assert_error(response, 403, "Password reset required").await;
+
+        assert_eq!(app.send_queued_emails().await, 1);
+        let (subject, text) = last_email(&app).await;
+        assert_eq!(subject, "Your password must be changed");
+        let (_, reset_token) = text.split_once("?reset=").expect("No reset link found");
+        let reset_token = reset_token.split_whitespace().next().unwrap();
+
+        let response = app
+            .post_reset_password(&serde_json::json!({
+                "token": reset_token,
+                "newPassword": "newpassword123",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let response = app
+            .post_login(&login_body(&random_email, "newpassword123"))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        app.clean_up().await;
+    }
+
+    #[tokio::test]
+    async fn should_notify_new_devices() {
+        let mut app = TestApp::new_with_sqlite_stores().await;
+        let random_email = get_random_email();
+        signup(&app, &random_email).await;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&app.email_server)
+            .await;
+
+        // Known devices are kept in SQLite, next to the user
+        for user_agent in ["device-a", "device-a", "device-b"] {
+            let response = app
+                .post_login_with_user_agent(&login_body(&random_email, "password123"), user_agent)
+                .await;
+            assert_eq!(response.status().as_u16(), 200);
+        }
+
+        assert_eq!(app.send_queued_emails().await, 1);
+        let (subject, _) = last_email(&app).await;
+        assert_eq!(subject, "New sign-in to your account");
+
+        app.clean_up().await;
+    }
+
+    #[tokio::test]
+    async fn should_deliver_webhooks_from_the_sqlite_outbox() {
+        let mut app = TestApp::new_with_sqlite_stores().await;
+        let webhook_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&webhook_server)
+            .await;
+
+        let response = app
+            .post_webhook(
+                &format!("{}/webhook", webhook_server.uri()),
+                ADMIN_API_KEY.expose_secret(),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        // The event is written in the transaction adding the user
+        let random_email = get_random_email();
+        signup(&app, &random_email).await;
+
+        let attempted = app
+            .webhook_dispatcher
+            .run_once()
+            .await
+            .expect("Failed to dispatch webhooks");
+        assert_eq!(attempted, 1);
+
+        let requests = webhook_server.received_requests().await.unwrap();
+        let event: DomainEvent =
+            serde_json::from_slice(&requests[0].body).expect("Could not deserialize webhook body");
+        assert_eq!(event.kind, DomainEventKind::UserSignedUp);
+        assert_eq!(event.data["email"], random_email);
+
+        // Delivered events are not sent again
+        let attempted = app
+            .webhook_dispatcher
+            .run_once()
+            .await
+            .expect("Failed to dispatch webhooks");
+        assert_eq!(attempted, 0);
+
+        app.clean_up().await;
+    }
+
+    #[tokio::test]
+    async fn should_log_in_with_2fa_and_log_out() {
+        let mut app = TestApp::new_with_sqlite_stores().await;
+        let random_email = get_random_email();
+
+        let signup_body = serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        });
+        assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&app.email_server)
+            .await;
+
+        let response = app
+            .post_login(&login_body(&random_email, "password123"))
+            .await;
+        assert_eq!(response.status().as_u16(), 206);
+        let login_attempt_id = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .login_attempt_id;
+
+        // The code is read from the sent email, as the queue is in SQLite
+        assert_eq!(app.send_queued_emails().await, 1);
+        let (subject, text) = last_email(&app).await;
+        assert_eq!(subject, "2FA code");
+        let code: String = text
+            .trim_start_matches("Your 2FA code is ")
+            .chars()
+            .take(6)
+            .collect();
+
+        let verify_2fa_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        });
+        let response = app.post_verify_2fa(&verify_2fa_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let token = response
+            .cookies()
+            .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+            .expect("No auth cookie found")
+            .value()
+            .to_owned();
+
+        // Codes are single-use
+        let response = app.post_verify_2fa(&verify_2fa_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        let response = app.post_verify_token_with_bearer(&token).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        // The token is banned in SQLite, and rejected from then on
+        let response = app.post_logout().await;
+        assert_eq!(response.status().as_u16(), 200);
+        let response = app.post_verify_token_with_bearer(&token).await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        app.clean_up().await;
+    }
+}
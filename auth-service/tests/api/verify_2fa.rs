@@ -1,10 +1,10 @@
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode},
+    domain::{LoginAttemptId, TwoFACode},
     routes::TwoFactorAuthResponse,
     utils::constants::JWT_COOKIE_NAME,
     ErrorResponse,
 };
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 use test_helpers::api_test;
 use wiremock::{
     matchers::{method, path},
@@ -126,6 +126,8 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -136,15 +138,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let login_attempt_id = response_body.login_attempt_id;
 
-    let code_tuple = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
-        .await
-        .unwrap();
-
-    let two_fa_code = code_tuple.1.as_ref();
+    let two_fa_code = app.get_two_fa_code(&random_email).await;
 
     // --------------------------
 
@@ -156,17 +150,17 @@ async fn should_return_401_if_incorrect_credentials() {
         (
             incorrect_email.as_str(),
             login_attempt_id.as_str(),
-            two_fa_code.expose_secret().as_str(),
+            two_fa_code.as_str(),
         ),
         (
             random_email.as_str(),
             incorrect_login_attempt_id.expose_secret(),
-            two_fa_code.expose_secret().as_str(),
+            two_fa_code.as_str(),
         ),
         (
             random_email.as_str(),
             login_attempt_id.as_str(),
-            incorrect_two_fa_code.expose_secret().as_str(),
+            incorrect_two_fa_code.as_str(),
         ),
     ];
 
@@ -229,6 +223,8 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -239,15 +235,7 @@ async fn should_return_401_if_old_code() {
 
     let login_attempt_id = response_body.login_attempt_id;
 
-    let code_tuple = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
-        .await
-        .unwrap();
-
-    let code = code_tuple.1.as_ref();
+    let code = app.get_two_fa_code(&random_email).await;
 
     // Second login call
 
@@ -255,12 +243,14 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     // 2FA attempt with old login_attempt_id and code
 
     let request_body = serde_json::json!({
         "email": random_email,
         "loginAttemptId": login_attempt_id,
-        "2FACode": code.expose_secret()
+        "2FACode": code
     });
 
     let response = app.post_verify_2fa(&request_body).await;
@@ -298,21 +288,19 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     let json_body = response
         .json::<TwoFactorAuthResponse>()
         .await
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
-    let code_tuple = {
-        let two_fa_code_store = app.two_fa_code_store.read().await;
-        let email = Email::parse(Secret::new(random_email.clone())).unwrap();
-        two_fa_code_store.get_code(&email).await.unwrap()
-    };
+    let code = app.get_two_fa_code(&random_email).await;
 
     let verify_2fa_body = serde_json::json!({
         "email":random_email,
         "loginAttemptId": json_body.login_attempt_id,
-        "2FACode": code_tuple.1.as_ref().expose_secret().to_string(),
+        "2FACode": code,
     });
 
     let response = app.post_verify_2fa(&verify_2fa_body).await;
@@ -327,6 +315,57 @@ async fn should_return_200_if_correct_code() {
     assert!(!auth_cookie.value().is_empty());
 }
 
+#[api_test]
+async fn should_return_200_if_email_differs_in_case_from_login() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email.to_uppercase(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    // The code is sent to the normalized email
+    let code = app.get_two_fa_code(&random_email).await;
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": json_body.login_attempt_id,
+        "2FACode": code,
+    });
+
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
 #[api_test]
 async fn should_return_401_if_same_code_twice() {
     let random_email = get_random_email();
@@ -357,21 +396,19 @@ async fn should_return_401_if_same_code_twice() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.send_queued_emails().await, 1);
+
     let json_body = response
         .json::<TwoFactorAuthResponse>()
         .await
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
-    let code_tuple = {
-        let two_fa_code_store = app.two_fa_code_store.read().await;
-        let email = Email::parse(Secret::new(random_email.clone())).unwrap();
-        two_fa_code_store.get_code(&email).await.unwrap()
-    };
+    let code = app.get_two_fa_code(&random_email).await;
 
     let verify_2fa_body = serde_json::json!({
         "email":random_email,
         "loginAttemptId": json_body.login_attempt_id,
-        "2FACode": code_tuple.1.as_ref().expose_secret().to_string(),
+        "2FACode": code,
     });
 
     let response = app.post_verify_2fa(&verify_2fa_body).await;
@@ -398,3 +435,59 @@ async fn should_return_401_if_same_code_twice() {
         "Incorrect credentials".to_owned()
     );
 }
+
+#[api_test]
+async fn should_accept_only_one_of_concurrent_requests_with_same_code() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert_eq!(app.send_queued_emails().await, 1);
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let code = app.get_two_fa_code(&random_email).await;
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": json_body.login_attempt_id,
+        "2FACode": code,
+    });
+
+    // Both requests are in flight before either is answered
+    let (first, second) = tokio::join!(
+        app.post_verify_2fa(&verify_2fa_body),
+        app.post_verify_2fa(&verify_2fa_body),
+    );
+
+    let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+    statuses.sort();
+    assert_eq!(statuses, [200, 401]);
+}
@@ -4,17 +4,26 @@ use test_helpers::api_test;
 use crate::helpers::{get_random_email, TestApp};
 
 #[api_test]
-async fn should_return_422_if_malformed_input() {
-    let test_cases = [serde_json::json!({})];
+async fn should_return_400_if_token_missing() {
+    let test_cases = [serde_json::json!({}), serde_json::json!({ "token": "" })];
 
     for test_case in test_cases.iter() {
         let response = app.post_verify_token(test_case).await;
         assert_eq!(
             response.status().as_u16(),
-            422,
+            400,
             "Failed for input: {:?}",
             test_case
         );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Missing auth token".to_owned()
+        );
     }
 }
 
@@ -57,6 +66,41 @@ async fn should_return_200_valid_token() {
     assert_eq!(response.status().as_u16(), 200);
 }
 
+#[api_test]
+async fn should_return_200_valid_bearer_token() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app
+        .post_verify_token_with_bearer(auth_cookie.value())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
 #[api_test]
 async fn should_return_401_if_invalid_token() {
     let verify_token_body = serde_json::json!({
@@ -137,3 +181,36 @@ async fn should_return_401_if_banned_token() {
         "Invalid auth token".to_owned()
     );
 }
+
+#[api_test]
+async fn should_verify_token_in_body_over_auth_cookie() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The valid auth cookie is sent along, but the caller asked about this token
+    let verify_token_body = serde_json::json!({
+        "token": "invalid_token",
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use auth_service::{
+    domain::{DomainEvent, DomainEventKind},
+    routes::{ListWebhookEndpointsResponse, RegisterWebhookResponse},
+    services::webhook_dispatcher::{sign_webhook, WebhookDispatcher, WebhookDispatcherConfig},
+    utils::constants::{
+        ADMIN_API_KEY, WEBHOOK_ID_HEADER_NAME, WEBHOOK_SIGNATURE_HEADER_NAME,
+        WEBHOOK_TIMESTAMP_HEADER_NAME,
+    },
+    ErrorResponse,
+};
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[api_test]
+async fn should_return_401_if_admin_api_key_incorrect() {
+    let response = app
+        .post_webhook("http://localhost/webhook", "wrong_key")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_webhooks("wrong_key").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_url() {
+    let test_cases = ["", "not a url", "ftp://localhost/webhook"];
+
+    for url in test_cases {
+        let response = app.post_webhook(url, ADMIN_API_KEY.expose_secret()).await;
+
+        assert_eq!(response.status().as_u16(), 400, "Failed for url: {}", url);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid webhook URL".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_register_and_list_webhook_endpoints() {
+    let url = "http://localhost:9000/webhook";
+
+    let response = app.post_webhook(url, ADMIN_API_KEY.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let registered = response
+        .json::<RegisterWebhookResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterWebhookResponse");
+
+    assert_eq!(registered.url, url);
+    assert!(!registered.secret.is_empty());
+
+    let response = app.get_webhooks(ADMIN_API_KEY.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ListWebhookEndpointsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListWebhookEndpointsResponse");
+
+    assert_eq!(body.endpoints.len(), 1);
+    assert_eq!(body.endpoints[0].id, registered.id);
+    assert_eq!(body.endpoints[0].url, url);
+}
+
+#[api_test]
+async fn should_deliver_signed_webhook_on_signup() {
+    let webhook_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/webhook"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+
+    let response = app
+        .post_webhook(
+            &format!("{}/webhook", webhook_server.uri()),
+            ADMIN_API_KEY.expose_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let registered = response
+        .json::<RegisterWebhookResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterWebhookResponse");
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let attempted = app
+        .webhook_dispatcher
+        .run_once()
+        .await
+        .expect("Failed to dispatch webhooks");
+
+    assert_eq!(attempted, 1);
+
+    let requests = webhook_server.received_requests().await.unwrap();
+    let request = &requests[0];
+
+    let event: DomainEvent =
+        serde_json::from_slice(&request.body).expect("Could not deserialize webhook body");
+
+    assert_eq!(event.kind, DomainEventKind::UserSignedUp);
+    assert_eq!(event.data["email"], random_email);
+    assert_eq!(
+        event.data["user_id"],
+        app.get_user_id(&random_email).await.to_string()
+    );
+    assert_eq!(
+        request.headers[WEBHOOK_ID_HEADER_NAME],
+        event.id.to_string().as_str()
+    );
+
+    let timestamp: i64 = request.headers[WEBHOOK_TIMESTAMP_HEADER_NAME]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        request.headers[WEBHOOK_SIGNATURE_HEADER_NAME],
+        sign_webhook(&Secret::new(registered.secret), timestamp, &request.body).as_str()
+    );
+
+    // Delivered events are not sent again
+    let attempted = app
+        .webhook_dispatcher
+        .run_once()
+        .await
+        .expect("Failed to dispatch webhooks");
+
+    assert_eq!(attempted, 0);
+}
+
+#[api_test]
+async fn should_retry_failed_webhooks_until_dead_lettered() {
+    let webhook_server = MockServer::start().await;
+
+    // The test dispatcher gives up after 3 attempts
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&webhook_server)
+        .await;
+
+    let response = app
+        .post_webhook(&webhook_server.uri(), ADMIN_API_KEY.expose_secret())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    for expected_attempts in [1, 1, 1, 0] {
+        let attempted = app
+            .webhook_dispatcher
+            .run_once()
+            .await
+            .expect("Failed to dispatch webhooks");
+
+        assert_eq!(attempted, expected_attempts);
+    }
+}
+
+#[api_test]
+async fn should_not_deliver_webhooks_twice_when_endpoint_is_slow() {
+    let webhook_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+        .expect(3)
+        .mount(&webhook_server)
+        .await;
+
+    let response = app
+        .post_webhook(&webhook_server.uri(), ADMIN_API_KEY.expose_secret())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    for _ in 0..3 {
+        let signup_body = serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        });
+
+        let response = app.post_signup(&signup_body).await;
+
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    // Sending the three deliveries one after the other would outlast the lease
+    let dispatcher = WebhookDispatcher::new(
+        app.outbox_store.clone(),
+        Client::new(),
+        WebhookDispatcherConfig {
+            lease: Duration::from_secs(1),
+            ..Default::default()
+        },
+    );
+
+    let (first, second) = tokio::join!(dispatcher.run_once(), async {
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        dispatcher.run_once().await
+    });
+
+    assert_eq!(first.expect("Failed to dispatch webhooks"), 3);
+    assert_eq!(second.expect("Failed to dispatch webhooks"), 0);
+}
+
+#[api_test]
+async fn should_deliver_webhook_when_account_is_deleted() {
+    let webhook_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&webhook_server)
+        .await;
+
+    let response = app
+        .post_webhook(&webhook_server.uri(), ADMIN_API_KEY.expose_secret())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let user_id = app.get_user_id(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .delete_profile(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let attempted = app
+        .webhook_dispatcher
+        .run_once()
+        .await
+        .expect("Failed to dispatch webhooks");
+
+    assert_eq!(attempted, 2);
+
+    let events: Vec<DomainEvent> = webhook_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            serde_json::from_slice(&request.body).expect("Could not deserialize webhook body")
+        })
+        .collect();
+
+    let event = events
+        .iter()
+        .find(|event| event.kind == DomainEventKind::UserDeleted)
+        .expect("No user.deleted webhook received");
+
+    assert_eq!(event.data["email"], random_email);
+    assert_eq!(event.data["user_id"], user_id.to_string());
+}